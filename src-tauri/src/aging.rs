@@ -0,0 +1,303 @@
+// src-tauri/src/aging.rs
+use chrono::{Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::csv_export::CsvExportRequest;
+use crate::database::{Database, Invoice};
+use crate::pdf_generator::{round_cents, Totals};
+
+// How often the background checker looks for newly overdue invoices
+const CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Receivables grouped by how long they are past due.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AgingBucket {
+    #[serde(rename = "current")]
+    Current,
+    #[serde(rename = "1-30")]
+    Days1To30,
+    #[serde(rename = "31-60")]
+    Days31To60,
+    #[serde(rename = "61-90")]
+    Days61To90,
+    #[serde(rename = "90+")]
+    Over90,
+}
+
+impl AgingBucket {
+    pub const ALL: [AgingBucket; 5] = [
+        AgingBucket::Current,
+        AgingBucket::Days1To30,
+        AgingBucket::Days31To60,
+        AgingBucket::Days61To90,
+        AgingBucket::Over90,
+    ];
+
+    pub fn for_days_overdue(days: i64) -> AgingBucket {
+        match days {
+            i64::MIN..=0 => AgingBucket::Current,
+            1..=30 => AgingBucket::Days1To30,
+            31..=60 => AgingBucket::Days31To60,
+            61..=90 => AgingBucket::Days61To90,
+            _ => AgingBucket::Over90,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            AgingBucket::Current => "Current",
+            AgingBucket::Days1To30 => "1-30",
+            AgingBucket::Days31To60 => "31-60",
+            AgingBucket::Days61To90 => "61-90",
+            AgingBucket::Over90 => "90+",
+        }
+    }
+}
+
+/// Outstanding amounts in one currency, by bucket.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct BucketTotals {
+    pub currency: String,
+    pub current: f64,
+    pub days_1_30: f64,
+    pub days_31_60: f64,
+    pub days_61_90: f64,
+    pub over_90: f64,
+    pub total: f64,
+}
+
+impl BucketTotals {
+    fn new(currency: &str) -> BucketTotals {
+        BucketTotals { currency: currency.to_string(), ..Default::default() }
+    }
+
+    pub fn get(&self, bucket: AgingBucket) -> f64 {
+        match bucket {
+            AgingBucket::Current => self.current,
+            AgingBucket::Days1To30 => self.days_1_30,
+            AgingBucket::Days31To60 => self.days_31_60,
+            AgingBucket::Days61To90 => self.days_61_90,
+            AgingBucket::Over90 => self.over_90,
+        }
+    }
+
+    fn add(&mut self, bucket: AgingBucket, amount: f64) {
+        let sum = match bucket {
+            AgingBucket::Current => &mut self.current,
+            AgingBucket::Days1To30 => &mut self.days_1_30,
+            AgingBucket::Days31To60 => &mut self.days_31_60,
+            AgingBucket::Days61To90 => &mut self.days_61_90,
+            AgingBucket::Over90 => &mut self.over_90,
+        };
+        *sum = round_cents(*sum + amount);
+        self.total = round_cents(self.total + amount);
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AgingEntry {
+    pub invoice_id: String,
+    pub invoice_number: String,
+    pub artist_id: String,
+    pub artist_name: String,
+    pub issue_date: NaiveDate,
+    pub due_date: NaiveDate,
+    // Zero for invoices that aren't due yet
+    pub days_overdue: i64,
+    pub bucket: AgingBucket,
+    pub currency: String,
+    // Gross amount including tax
+    pub outstanding: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ArtistAging {
+    pub artist_id: String,
+    pub artist_name: String,
+    // One entry per currency the artist invoices in
+    pub totals: Vec<BucketTotals>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AgingReport {
+    pub as_of: NaiveDate,
+    // Most overdue first
+    pub invoices: Vec<AgingEntry>,
+    // By artist name
+    pub artists: Vec<ArtistAging>,
+    // One entry per currency; amounts in different currencies are never added up
+    pub totals: Vec<BucketTotals>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct AgingReportRequest {
+    // Defaults to today
+    pub as_of: Option<NaiveDate>,
+    // Limits the report to one artist
+    pub artist_id: Option<String>,
+    pub pdf_path: Option<String>,
+    // One row per invoice; only the output path, columns and number format apply
+    pub csv: Option<CsvExportRequest>,
+}
+
+/// Ages every open invoice as of `as_of`.
+pub fn aging_report(db: &Database, as_of: NaiveDate, artist_id: Option<&str>) -> Result<AgingReport, String> {
+    let invoices = db.get_open_invoices().map_err(|e| e.to_string())?;
+    let artists: HashMap<String, String> = db
+        .get_all_artists()
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .map(|a| (a.id, a.name))
+        .collect();
+    let selected = invoices.into_iter().filter(|i| artist_id.is_none_or(|id| i.artist_id == id));
+    Ok(build_report(as_of, selected, &artists))
+}
+
+fn build_report(
+    as_of: NaiveDate,
+    invoices: impl IntoIterator<Item = Invoice>,
+    artist_names: &HashMap<String, String>,
+) -> AgingReport {
+    let mut entries: Vec<AgingEntry> = invoices
+        .into_iter()
+        .map(|invoice| {
+            let days_overdue = (as_of - invoice.due_date).num_days().max(0);
+            AgingEntry {
+                artist_name: artist_names.get(&invoice.artist_id).cloned().unwrap_or_default(),
+                outstanding: Totals::for_invoice(&invoice).gross,
+                bucket: AgingBucket::for_days_overdue(days_overdue),
+                days_overdue,
+                issue_date: invoice.issue_date,
+                due_date: invoice.due_date,
+                invoice_id: invoice.id,
+                invoice_number: invoice.invoice_number,
+                artist_id: invoice.artist_id,
+                currency: invoice.currency,
+            }
+        })
+        .collect();
+    entries.sort_by(|a, b| b.days_overdue.cmp(&a.days_overdue).then_with(|| a.due_date.cmp(&b.due_date)));
+
+    let mut artists: Vec<ArtistAging> = Vec::new();
+    let mut totals: Vec<BucketTotals> = Vec::new();
+    for entry in &entries {
+        let artist = match artists.iter_mut().position(|a| a.artist_id == entry.artist_id) {
+            Some(index) => &mut artists[index],
+            None => {
+                artists.push(ArtistAging {
+                    artist_id: entry.artist_id.clone(),
+                    artist_name: entry.artist_name.clone(),
+                    totals: Vec::new(),
+                });
+                artists.last_mut().unwrap()
+            }
+        };
+        totals_for(&mut artist.totals, &entry.currency).add(entry.bucket, entry.outstanding);
+        totals_for(&mut totals, &entry.currency).add(entry.bucket, entry.outstanding);
+    }
+    artists.sort_by_key(|a| a.artist_name.to_lowercase());
+    for totals in artists.iter_mut().map(|a| &mut a.totals).chain([&mut totals]) {
+        totals.sort_by(|a, b| a.currency.cmp(&b.currency));
+    }
+
+    AgingReport { as_of, invoices: entries, artists, totals }
+}
+
+fn totals_for<'a>(totals: &'a mut Vec<BucketTotals>, currency: &str) -> &'a mut BucketTotals {
+    match totals.iter().position(|t| t.currency == currency) {
+        Some(index) => &mut totals[index],
+        None => {
+            totals.push(BucketTotals::new(currency));
+            totals.last_mut().unwrap()
+        }
+    }
+}
+
+/// Starts the background thread that marks unpaid invoices overdue once their
+/// due date has passed.
+pub fn start_overdue_checker(db: Database) {
+    std::thread::spawn(move || loop {
+        // Nothing to check while the database is unavailable
+        if db.error().is_none() {
+            if let Err(e) = db.mark_overdue_invoices(Local::now().date_naive()) {
+                eprintln!("Overdue check failed: {}", e);
+            }
+        }
+        std::thread::sleep(CHECK_INTERVAL);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::NewInvoice;
+
+    fn date(value: &str) -> NaiveDate {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn buckets() {
+        assert_eq!(AgingBucket::for_days_overdue(-5), AgingBucket::Current);
+        assert_eq!(AgingBucket::for_days_overdue(0), AgingBucket::Current);
+        assert_eq!(AgingBucket::for_days_overdue(1), AgingBucket::Days1To30);
+        assert_eq!(AgingBucket::for_days_overdue(31), AgingBucket::Days31To60);
+        assert_eq!(AgingBucket::for_days_overdue(90), AgingBucket::Days61To90);
+        assert_eq!(AgingBucket::for_days_overdue(91), AgingBucket::Over90);
+    }
+
+    #[test]
+    fn report_groups_by_artist_and_currency() {
+        let db = Database::in_memory().unwrap();
+        let artist = |name: &str| {
+            db.create_artist(crate::database::NewArtist { name: name.to_string(), ..Default::default() }).unwrap()
+        };
+        let (nova, echo) = (artist("Nova"), artist("Echo"));
+        let invoice = |artist_id: &str, number: &str, due: &str, amount: f64, currency: &str| {
+            db.create_invoice(NewInvoice {
+                artist_id: artist_id.to_string(),
+                project_id: None,
+                invoice_number: number.to_string(),
+                amount,
+                status: "pending".to_string(),
+                issue_date: date("2024-01-01"),
+                due_date: Some(date(due)),
+                payment_terms: None,
+                currency: Some(currency.to_string()),
+                client_id: None,
+                bill_to: None,
+                bill_to_email: None,
+                items: "[]".to_string(),
+                notes: None,
+            })
+            .unwrap()
+        };
+        invoice(&nova.id, "INV-1", "2024-06-30", 100.0, "USD");
+        invoice(&nova.id, "INV-2", "2024-06-10", 200.0, "USD");
+        invoice(&nova.id, "INV-3", "2024-02-01", 300.0, "EUR");
+        let paid = invoice(&echo.id, "INV-4", "2024-01-31", 400.0, "USD");
+        db.record_invoice_payment(paid.id, None, None).unwrap();
+        invoice(&echo.id, "INV-5", "2024-04-15", 50.0, "USD");
+
+        let report = aging_report(&db, date("2024-06-30"), None).unwrap();
+        let numbers: Vec<&str> = report.invoices.iter().map(|e| e.invoice_number.as_str()).collect();
+        assert_eq!(numbers, ["INV-3", "INV-5", "INV-2", "INV-1"]);
+        assert_eq!(report.invoices[0].days_overdue, 150);
+        assert_eq!(report.invoices[3].bucket, AgingBucket::Current);
+
+        let names: Vec<&str> = report.artists.iter().map(|a| a.artist_name.as_str()).collect();
+        assert_eq!(names, ["Echo", "Nova"]);
+        let nova_totals = &report.artists[1].totals;
+        assert_eq!(nova_totals.iter().map(|t| t.currency.as_str()).collect::<Vec<_>>(), ["EUR", "USD"]);
+        assert_eq!((nova_totals[1].current, nova_totals[1].days_1_30, nova_totals[1].total), (100.0, 200.0, 300.0));
+
+        let usd = &report.totals[1];
+        assert_eq!((usd.days_61_90, usd.total), (50.0, 350.0));
+        assert_eq!(report.totals[0].over_90, 300.0);
+
+        let echo_only = aging_report(&db, date("2024-06-30"), Some(&echo.id)).unwrap();
+        assert_eq!(echo_only.invoices.len(), 1);
+    }
+}
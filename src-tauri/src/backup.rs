@@ -0,0 +1,627 @@
+// src-tauri/src/backup.rs
+use rusqlite::types::{Value as SqlValue, ValueRef};
+use rusqlite::{params_from_iter, Connection, ErrorCode, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::database::{self, Database, SCHEMA_VERSION};
+
+pub const BACKUP_FORMAT: &str = "project-invoicer-backup";
+// Version of the archive layout, independent of the database schema
+pub const BACKUP_VERSION: u32 = 1;
+const MANIFEST_NAME: &str = "backup.json";
+// Errors listed before the rest are summarised
+const MAX_REPORTED_ERRORS: usize = 20;
+
+// Parents before children, the order rows are restored in
+const TABLES: [&str; 7] =
+    ["artists", "projects", "clients", "invoices", "branding_profiles", "invoice_reminders", "invoice_emails"];
+
+// Logs that are only ever appended to; they have no updated_at, so rows already here are kept
+const LOG_TABLES: [&str; 2] = ["invoice_reminders", "invoice_emails"];
+
+// Branding columns that point at files, and the kind they're archived as
+const FILE_COLUMNS: [(&str, &str); 2] = [("logo_path", "logo"), ("font_path", "font")];
+
+/// The manifest stored as backup.json at the root of the archive.
+#[derive(Debug, Serialize, Deserialize)]
+struct Backup {
+    format: String,
+    version: u32,
+    // PRAGMA user_version of the database the rows were read from
+    schema_version: i64,
+    app_version: String,
+    created_at: String,
+    // Table name -> rows as column/value objects
+    tables: BTreeMap<String, Vec<Map<String, Value>>>,
+    #[serde(default)]
+    files: Vec<BackupFile>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct BackupFile {
+    // Entry name inside the archive
+    archive_path: String,
+    // "logo", "font" or "template"
+    kind: String,
+    // Where the file lived when the backup was made
+    original_path: String,
+    // The branding profile using it, for logos and fonts
+    #[serde(default)]
+    profile_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BackupSummary {
+    pub path: String,
+    pub schema_version: i64,
+    pub rows: BTreeMap<String, usize>,
+    pub files: usize,
+    // Files referenced by branding profiles that no longer exist
+    pub missing_files: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RestoreMode {
+    // Keep existing data; rows from the backup are added, or win when newer
+    #[default]
+    Merge,
+    // Delete everything first so the database matches the backup exactly
+    Replace,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RestoreRequest {
+    pub path: String,
+    #[serde(default)]
+    pub mode: RestoreMode,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TableRestore {
+    pub table: String,
+    pub inserted: usize,
+    pub updated: usize,
+    // Rows already present and at least as recent as the backup's
+    pub skipped: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RestoreReport {
+    pub mode: RestoreMode,
+    pub backup_version: u32,
+    pub schema_version: i64,
+    pub tables: Vec<TableRestore>,
+    pub files_restored: usize,
+    // Rows left out because they clash with existing data, e.g. a reused invoice number
+    pub conflicts: Vec<String>,
+}
+
+/// Writes every table plus the files branding profiles and invoice templates
+/// rely on to a ZIP archive at `output_path`.
+pub fn export_backup(db: &Database, output_path: &str) -> Result<BackupSummary, String> {
+    // One transaction, so the tables are read from a consistent snapshot
+    let (schema_version, tables) = db.with_transaction(|conn| {
+        let schema_version: i64 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        let mut tables = BTreeMap::new();
+        for table in TABLES {
+            tables.insert(table.to_string(), read_rows(conn, table)?);
+        }
+        Ok((schema_version, tables))
+    })
+    .map_err(|e| format!("Failed to read the database: {}", e))?;
+
+    let mut files = Vec::new();
+    let mut contents = Vec::new();
+    let mut missing_files = Vec::new();
+    for profile in &tables["branding_profiles"] {
+        let profile_id = profile.get("id").and_then(Value::as_str).unwrap_or_default();
+        for (column, kind) in FILE_COLUMNS {
+            let Some(path) = profile.get(column).and_then(Value::as_str).filter(|p| !p.trim().is_empty()) else {
+                continue;
+            };
+            match std::fs::read(path) {
+                Ok(bytes) => {
+                    files.push(BackupFile {
+                        archive_path: format!("files/{}/{}/{}", kind, profile_id, file_name(path)),
+                        kind: kind.to_string(),
+                        original_path: path.to_string(),
+                        profile_id: Some(profile_id.to_string()),
+                    });
+                    contents.push(bytes);
+                }
+                Err(_) => missing_files.push(path.to_string()),
+            }
+        }
+    }
+    if let Ok(entries) = std::fs::read_dir(templates_dir()) {
+        for path in entries.flatten().map(|e| e.path()).filter(|p| p.is_file()) {
+            let bytes = std::fs::read(&path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+            let original_path = path.to_string_lossy().to_string();
+            files.push(BackupFile {
+                archive_path: format!("files/template/{}", file_name(&original_path)),
+                kind: "template".to_string(),
+                original_path,
+                profile_id: None,
+            });
+            contents.push(bytes);
+        }
+    }
+
+    let backup = Backup {
+        format: BACKUP_FORMAT.to_string(),
+        version: BACKUP_VERSION,
+        schema_version,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        created_at: chrono::Utc::now().to_rfc3339(),
+        tables,
+        files,
+    };
+    let manifest = serde_json::to_vec_pretty(&backup).map_err(|e| e.to_string())?;
+
+    let output = Path::new(output_path);
+    if let Some(parent) = output.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let file = File::create(output).map_err(|e| format!("Failed to create {}: {}", output.display(), e))?;
+    let mut zip = ZipWriter::new(file);
+    let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+    zip.start_file(MANIFEST_NAME, options).map_err(|e| e.to_string())?;
+    zip.write_all(&manifest).map_err(|e| e.to_string())?;
+    for (entry, bytes) in backup.files.iter().zip(&contents) {
+        zip.start_file(entry.archive_path.as_str(), options).map_err(|e| e.to_string())?;
+        zip.write_all(bytes).map_err(|e| e.to_string())?;
+    }
+    zip.finish().map_err(|e| format!("Failed to write {}: {}", output.display(), e))?;
+
+    Ok(BackupSummary {
+        path: output.to_string_lossy().to_string(),
+        schema_version,
+        rows: backup.tables.iter().map(|(table, rows)| (table.clone(), rows.len())).collect(),
+        files: backup.files.len(),
+        missing_files,
+    })
+}
+
+/// Restores an archive written by `export_backup`. The whole archive is
+/// validated before anything is written, and the rows go in as a single
+/// transaction.
+pub fn import_backup(db: &Database, request: &RestoreRequest) -> Result<RestoreReport, String> {
+    let path = Path::new(&request.path);
+    let file = File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    let mut archive = ZipArchive::new(file).map_err(|_| format!("{} is not a backup archive", path.display()))?;
+    let backup: Backup = {
+        let mut manifest = archive
+            .by_name(MANIFEST_NAME)
+            .map_err(|_| format!("{} is not a backup archive (no {})", path.display(), MANIFEST_NAME))?;
+        let mut json = Vec::new();
+        manifest.read_to_end(&mut json).map_err(|e| e.to_string())?;
+        serde_json::from_slice(&json).map_err(|e| format!("The backup manifest is invalid: {}", e))?
+    };
+
+    if backup.format != BACKUP_FORMAT {
+        return Err(format!("Unknown backup format '{}'", backup.format));
+    }
+    if backup.version > BACKUP_VERSION || backup.schema_version > SCHEMA_VERSION {
+        return Err("This backup was made by a newer version of the app; update before restoring it".to_string());
+    }
+
+    let mode = request.mode;
+    let report = db.with_transaction(|conn| {
+        // Rows may reference ones later in the backup, e.g. a credit note
+        // listed before the invoice it credits; references were validated
+        conn.execute_batch("PRAGMA defer_foreign_keys = ON")?;
+        let columns = TABLES
+            .iter()
+            .map(|table| Ok((*table, table_columns(conn, table)?)))
+            .collect::<rusqlite::Result<HashMap<_, _>>>()?;
+        let errors = validate(&backup, &columns, conn, mode)?;
+        if !errors.is_empty() {
+            return Err(invalid_backup(errors));
+        }
+
+        if mode == RestoreMode::Replace {
+            for table in TABLES.iter().rev() {
+                conn.execute(&format!("DELETE FROM {}", table), [])?;
+            }
+        }
+
+        let mut report = RestoreReport {
+            mode,
+            backup_version: backup.version,
+            schema_version: backup.schema_version,
+            tables: Vec::new(),
+            files_restored: 0,
+            conflicts: Vec::new(),
+        };
+        for table in TABLES {
+            let rows = backup.tables.get(table).map(Vec::as_slice).unwrap_or_default();
+            let restored = restore_rows(conn, table, rows, &mut report.conflicts)?;
+            report.tables.push(restored);
+        }
+        // Backups from before dates were typed may hold them in older formats
+        if backup.schema_version < 2 {
+            database::normalize_dates(conn)?;
+        }
+
+        // Files are unpacked last so a failure rolls the rows back with them
+        report.files_restored = restore_files(conn, &mut archive, &backup.files, mode)?;
+        Ok(report)
+    })
+    .map_err(|e| format!("Restore failed, nothing was changed: {}", e))?;
+
+    Ok(report)
+}
+
+/// Older backups may lack columns added since; those rows take the column
+/// defaults, which is all the migration the current schema needs.
+fn validate(
+    backup: &Backup,
+    columns: &HashMap<&str, Vec<Column>>,
+    conn: &Connection,
+    mode: RestoreMode,
+) -> rusqlite::Result<Vec<String>> {
+    let mut errors = Vec::new();
+    for table in backup.tables.keys() {
+        if !TABLES.contains(&table.as_str()) {
+            errors.push(format!("unknown table '{}'", table));
+        }
+    }
+
+    for table in TABLES {
+        let table_columns = &columns[table];
+        for (index, row) in backup.tables.get(table).into_iter().flatten().enumerate() {
+            let mut problems = Vec::new();
+            for (name, value) in row {
+                if !table_columns.iter().any(|c| &c.name == name) {
+                    problems.push(format!("unknown column '{}'", name));
+                } else if value.is_array() || value.is_object() {
+                    problems.push(format!("{} must be a plain value", name));
+                }
+            }
+            for column in table_columns.iter().filter(|c| c.required) {
+                if row.get(&column.name).is_none_or(Value::is_null) {
+                    problems.push(format!("{} is required", column.name));
+                }
+            }
+            if !problems.is_empty() {
+                errors.push(format!("{} row {}: {}", table, index + 1, problems.join(", ")));
+            }
+        }
+    }
+
+    // Every reference must resolve, either within the backup or, when
+    // merging, to a row that's already here
+    let mut ids: HashMap<&str, HashSet<String>> = HashMap::new();
+    for table in ["artists", "projects", "clients", "invoices"] {
+        let mut known: HashSet<String> = backup
+            .tables
+            .get(table)
+            .into_iter()
+            .flatten()
+            .filter_map(|row| row.get("id").and_then(Value::as_str).map(str::to_string))
+            .collect();
+        if mode == RestoreMode::Merge {
+            let mut stmt = conn.prepare(&format!("SELECT id FROM {}", table))?;
+            let existing = stmt.query_map([], |row| row.get::<_, String>(0))?;
+            known.extend(existing.collect::<rusqlite::Result<Vec<_>>>()?);
+        }
+        ids.insert(table, known);
+    }
+    let references = [
+        ("projects", "artist_id", "artists"),
+        ("invoices", "artist_id", "artists"),
+        ("invoices", "project_id", "projects"),
+        ("invoices", "client_id", "clients"),
+        ("branding_profiles", "artist_id", "artists"),
+        ("invoice_reminders", "invoice_id", "invoices"),
+        ("invoice_emails", "invoice_id", "invoices"),
+    ];
+    for (table, column, parent) in references {
+        for (index, row) in backup.tables.get(table).into_iter().flatten().enumerate() {
+            if let Some(id) = row.get(column).and_then(Value::as_str) {
+                if !ids[parent].contains(id) {
+                    errors.push(format!("{} row {}: {} '{}' doesn't exist", table, index + 1, column, id));
+                }
+            }
+        }
+    }
+    Ok(errors)
+}
+
+fn invalid_backup(errors: Vec<String>) -> rusqlite::Error {
+    let mut message = errors.iter().take(MAX_REPORTED_ERRORS).cloned().collect::<Vec<_>>().join("; ");
+    if errors.len() > MAX_REPORTED_ERRORS {
+        message.push_str(&format!("; and {} more", errors.len() - MAX_REPORTED_ERRORS));
+    }
+    rusqlite::Error::ToSqlConversionFailure(format!("Invalid backup: {}", message).into())
+}
+
+fn restore_rows(
+    conn: &Connection,
+    table: &str,
+    rows: &[Map<String, Value>],
+    conflicts: &mut Vec<String>,
+) -> rusqlite::Result<TableRestore> {
+    let mut restored = TableRestore { table: table.to_string(), ..Default::default() };
+    for row in rows {
+        let id = row.get("id").and_then(Value::as_str).unwrap_or_default();
+        let names: Vec<&String> = row.keys().collect();
+        let values: Vec<SqlValue> = row.values().map(sql_value).collect();
+
+        let version = if LOG_TABLES.contains(&table) { "created_at" } else { "updated_at" };
+        let existing: Option<String> = conn
+            .query_row(&format!("SELECT {} FROM {} WHERE id = ?1", version, table), [id], |r| r.get(0))
+            .optional()?;
+        match existing {
+            None => {
+                let placeholders: Vec<String> = (1..=names.len()).map(|i| format!("?{}", i)).collect();
+                let sql = format!(
+                    "INSERT INTO {} ({}) VALUES ({})",
+                    table,
+                    names.iter().map(|n| n.as_str()).collect::<Vec<_>>().join(", "),
+                    placeholders.join(", ")
+                );
+                match conn.execute(&sql, params_from_iter(&values)) {
+                    Ok(_) => restored.inserted += 1,
+                    Err(rusqlite::Error::SqliteFailure(e, message)) if e.code == ErrorCode::ConstraintViolation => {
+                        conflicts.push(format!("{} {}: {}", table, id, message.unwrap_or_else(|| e.to_string())));
+                        restored.skipped += 1;
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+            // Merging: the more recently edited copy wins
+            Some(updated_at) if row.get(version).and_then(Value::as_str) > Some(updated_at.as_str()) => {
+                let assignments: Vec<String> =
+                    names.iter().enumerate().map(|(i, name)| format!("{} = ?{}", name, i + 1)).collect();
+                let sql = format!("UPDATE {} SET {} WHERE id = ?{}", table, assignments.join(", "), names.len() + 1);
+                let mut params = values;
+                params.push(SqlValue::Text(id.to_string()));
+                match conn.execute(&sql, params_from_iter(&params)) {
+                    Ok(_) => restored.updated += 1,
+                    Err(rusqlite::Error::SqliteFailure(e, message)) if e.code == ErrorCode::ConstraintViolation => {
+                        conflicts.push(format!("{} {}: {}", table, id, message.unwrap_or_else(|| e.to_string())));
+                        restored.skipped += 1;
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+            Some(_) => restored.skipped += 1,
+        }
+    }
+    Ok(restored)
+}
+
+/// Unpacks templates into the templates folder and branding files whose
+/// original path doesn't exist on this machine into the app's data folder,
+/// pointing the profiles at their new location.
+fn restore_files(
+    conn: &Connection,
+    archive: &mut ZipArchive<File>,
+    files: &[BackupFile],
+    mode: RestoreMode,
+) -> rusqlite::Result<usize> {
+    let mut restored = 0;
+    for file in files {
+        let target = match file.kind.as_str() {
+            "template" => templates_dir().join(file_name(&file.original_path)),
+            _ if Path::new(&file.original_path).exists() => continue,
+            kind => database::get_data_dir()
+                .join("attachments")
+                .join(file.profile_id.as_deref().unwrap_or(kind))
+                .join(file_name(&file.original_path)),
+        };
+        if target.exists() && mode == RestoreMode::Merge {
+            continue;
+        }
+
+        let mut bytes = Vec::new();
+        archive
+            .by_name(&file.archive_path)
+            .map_err(|e| file_error(&file.archive_path, e))?
+            .read_to_end(&mut bytes)
+            .map_err(|e| file_error(&file.archive_path, e))?;
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| file_error(&file.archive_path, e))?;
+        }
+        std::fs::write(&target, bytes).map_err(|e| file_error(&file.archive_path, e))?;
+        restored += 1;
+
+        if let Some((column, _)) = FILE_COLUMNS.iter().find(|(_, kind)| *kind == file.kind) {
+            conn.execute(
+                &format!("UPDATE branding_profiles SET {} = ?1 WHERE {} = ?2", column, column),
+                [target.to_string_lossy().as_ref(), file.original_path.as_str()],
+            )?;
+        }
+    }
+    Ok(restored)
+}
+
+fn file_error(name: &str, error: impl std::fmt::Display) -> rusqlite::Error {
+    rusqlite::Error::ToSqlConversionFailure(format!("Failed to restore {}: {}", name, error).into())
+}
+
+struct Column {
+    name: String,
+    // NOT NULL without a default, so a backup row must supply it
+    required: bool,
+}
+
+fn table_columns(conn: &Connection, table: &str) -> rusqlite::Result<Vec<Column>> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let columns = stmt.query_map([], |row| {
+        let not_null: bool = row.get(3)?;
+        let default: Option<String> = row.get(4)?;
+        Ok(Column { name: row.get(1)?, required: not_null && default.is_none() })
+    })?;
+    columns.collect()
+}
+
+fn read_rows(conn: &Connection, table: &str) -> rusqlite::Result<Vec<Map<String, Value>>> {
+    let mut stmt = conn.prepare(&format!("SELECT * FROM {} ORDER BY created_at, id", table))?;
+    let names: Vec<String> = stmt.column_names().into_iter().map(str::to_string).collect();
+    let rows = stmt.query_map([], |row| {
+        let mut map = Map::new();
+        for (index, name) in names.iter().enumerate() {
+            map.insert(name.clone(), json_value(row.get_ref(index)?)?);
+        }
+        Ok(map)
+    })?;
+    rows.collect()
+}
+
+fn json_value(value: ValueRef) -> rusqlite::Result<Value> {
+    Ok(match value {
+        ValueRef::Null => Value::Null,
+        ValueRef::Integer(n) => Value::from(n),
+        ValueRef::Real(n) => Value::from(n),
+        ValueRef::Text(text) => Value::String(String::from_utf8_lossy(text).to_string()),
+        ValueRef::Blob(_) => {
+            return Err(rusqlite::Error::ToSqlConversionFailure("binary columns can't be backed up".into()))
+        }
+    })
+}
+
+fn sql_value(value: &Value) -> SqlValue {
+    match value {
+        Value::Bool(flag) => SqlValue::Integer(*flag as i64),
+        Value::Number(n) => n.as_i64().map_or_else(|| SqlValue::Real(n.as_f64().unwrap_or(0.0)), SqlValue::Integer),
+        Value::String(text) => SqlValue::Text(text.clone()),
+        // Arrays and objects were rejected by validate()
+        _ => SqlValue::Null,
+    }
+}
+
+fn templates_dir() -> PathBuf {
+    database::get_data_dir().join("templates")
+}
+
+fn file_name(path: &str) -> String {
+    Path::new(path)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| "file".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{ArtistPatch, NewArtist, NewInvoice};
+
+    /// Exports `db` to a scratch archive and returns its path.
+    fn export(db: &Database) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("project-invoicer-backup-{}.zip", uuid::Uuid::new_v4()));
+        export_backup(db, path.to_str().unwrap()).unwrap();
+        path
+    }
+
+    fn restore(db: &Database, path: &Path, mode: RestoreMode) -> RestoreReport {
+        let request = RestoreRequest { path: path.to_string_lossy().to_string(), mode };
+        import_backup(db, &request).unwrap()
+    }
+
+    fn artist_names(db: &Database) -> Vec<String> {
+        db.get_all_artists().unwrap().into_iter().map(|a| a.name).collect()
+    }
+
+    fn rename(db: &Database, artist_id: &str, name: &str) {
+        db.update_artist(artist_id, ArtistPatch { name: Some(name.to_string()), ..Default::default() }).unwrap();
+    }
+
+    #[test]
+    fn merge_keeps_the_most_recently_edited_rows() {
+        let db = Database::in_memory().unwrap();
+        let artist = |name: &str| db.create_artist(NewArtist { name: name.to_string(), ..Default::default() }).unwrap();
+        let (nova, echo) = (artist("Nova"), artist("Echo"));
+        let first = export(&db);
+
+        // A second copy of the data; Nova is edited there first and here last
+        let other = Database::in_memory().unwrap();
+        restore(&other, &first, RestoreMode::Replace);
+        rename(&other, &nova.id, "Nova (edited there)");
+        rename(&other, &echo.id, "Echo (edited there)");
+        other.create_artist(NewArtist { name: "Vega".to_string(), ..Default::default() }).unwrap();
+        rename(&db, &nova.id, "Nova (edited here)");
+        let second = export(&other);
+
+        let report = restore(&db, &second, RestoreMode::Merge);
+        let artists = &report.tables[0];
+        assert_eq!((artists.table.as_str(), artists.inserted, artists.updated, artists.skipped), ("artists", 1, 1, 1));
+        assert_eq!(artist_names(&db), ["Echo (edited there)", "Nova (edited here)", "Vega"]);
+
+        // Replacing takes the backup as it is, older rows included
+        restore(&db, &first, RestoreMode::Replace);
+        assert_eq!(artist_names(&db), ["Echo", "Nova"]);
+
+        std::fs::remove_file(first).unwrap();
+        std::fs::remove_file(second).unwrap();
+    }
+
+    #[test]
+    fn reminders_and_emails_are_restored_once() {
+        let db = Database::in_memory().unwrap();
+        let artist = db.create_artist(NewArtist { name: "Nova".to_string(), ..Default::default() }).unwrap();
+        let invoice = db
+            .create_invoice(NewInvoice {
+                artist_id: artist.id,
+                project_id: None,
+                invoice_number: "INV-1".to_string(),
+                amount: 250.0,
+                status: "pending".to_string(),
+                issue_date: "2024-03-01".parse().unwrap(),
+                due_date: Some("2024-03-31".parse().unwrap()),
+                payment_terms: None,
+                currency: None,
+                client_id: None,
+                bill_to: None,
+                bill_to_email: None,
+                items: r#"[{"description":"Mixing","amount":250.0}]"#.to_string(),
+                notes: None,
+            })
+            .unwrap();
+        let to = ["client@example.com".to_string()];
+        db.record_invoice_email(&invoice.id, &to, &[], &[], "Invoice INV-1", "Attached.").unwrap();
+        db.record_invoice_reminder(&invoice.id, 7, &to[0], "INV-1 is overdue", None).unwrap();
+        db.record_invoice_reminder(&invoice.id, 14, &to[0], "INV-1 is overdue", Some("timed out".to_string()))
+            .unwrap();
+        let path = export(&db);
+        let reminders = |db: &Database| {
+            let reminders = db.get_invoice_reminders(&invoice.id).unwrap();
+            reminders.into_iter().map(|r| (r.id, r.offset_days, r.error, r.created_at)).collect::<Vec<_>>()
+        };
+        let emails = |db: &Database| {
+            let emails = db.get_invoice_emails(&invoice.id).unwrap();
+            emails.into_iter().map(|e| (e.id, e.to_addresses, e.subject, e.created_at)).collect::<Vec<_>>()
+        };
+        let counts = |report: &RestoreReport, table: &str| {
+            let t = report.tables.iter().find(|t| t.table == table).unwrap();
+            (t.inserted, t.updated, t.skipped)
+        };
+
+        for mode in [RestoreMode::Replace, RestoreMode::Merge] {
+            let restored = Database::in_memory().unwrap();
+            let report = restore(&restored, &path, mode);
+            assert_eq!(counts(&report, "invoice_reminders"), (2, 0, 0));
+            assert_eq!(counts(&report, "invoice_emails"), (1, 0, 0));
+            assert_eq!(reminders(&restored), reminders(&db));
+            assert_eq!(emails(&restored), emails(&db));
+
+            // Merging the same backup again keeps the log rows already there
+            let report = restore(&restored, &path, RestoreMode::Merge);
+            assert_eq!(counts(&report, "invoice_reminders"), (0, 0, 2));
+            assert_eq!(counts(&report, "invoice_emails"), (0, 0, 1));
+            assert_eq!(reminders(&restored), reminders(&db));
+            assert_eq!(emails(&restored), emails(&db));
+        }
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
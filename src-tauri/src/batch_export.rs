@@ -0,0 +1,335 @@
+// src-tauri/src/batch_export.rs
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::database::InvoiceFilter;
+use crate::jobs::CancelToken;
+use crate::pdf_generator::InvoiceData;
+
+pub const DEFAULT_FILENAME_PATTERN: &str = "{artist}/{invoice_number}.pdf";
+pub const PROGRESS_EVENT: &str = "batch-pdf-progress";
+const MANIFEST_FILE_NAME: &str = "manifest.csv";
+const MAX_WORKERS: usize = 8;
+// Reported for invoices that were never rendered because the batch was cancelled
+const CANCELLED: &str = "Cancelled";
+
+const PLACEHOLDERS: [&str; 10] = [
+    "artist", "company", "invoice_number", "project", "status", "issue_date", "year", "month", "currency", "id",
+];
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct BatchPdfRequest {
+    // Explicit invoices; when empty, every invoice matching `filter`
+    #[serde(default)]
+    pub invoice_ids: Vec<String>,
+    #[serde(default)]
+    pub filter: InvoiceFilter,
+    // The output directory, or the .zip file to create when `zip` is set
+    pub output_path: String,
+    // e.g. "{artist}/{invoice_number}.pdf"; see PLACEHOLDERS
+    pub filename_pattern: Option<String>,
+    #[serde(default)]
+    pub zip: bool,
+    // Concurrent renders; defaults to the number of CPUs, capped at MAX_WORKERS
+    pub workers: Option<usize>,
+    #[serde(default)]
+    pub facturx: bool,
+    // Lets the UI cancel the batch with `cancel_job`
+    pub job_id: Option<String>,
+}
+
+/// Sent as a `batch-pdf-progress` event after each invoice finishes.
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchProgress {
+    pub completed: usize,
+    pub total: usize,
+    pub invoice_id: String,
+    pub invoice_number: String,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchItemResult {
+    pub invoice_id: String,
+    pub invoice_number: String,
+    // Relative to the output directory or the ZIP root
+    pub file: String,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchSummary {
+    pub output_path: String,
+    pub succeeded: usize,
+    pub failed: usize,
+    // Set when the batch was cancelled; unrendered invoices fail with "Cancelled"
+    pub cancelled: bool,
+    pub results: Vec<BatchItemResult>,
+}
+
+/// Renders every invoice in `jobs` with `render` on a bounded pool of worker
+/// threads, then writes a manifest and, when requested, bundles the lot into
+/// a ZIP. A failing invoice is reported in the summary and doesn't stop the
+/// rest of the batch. Once `cancel` is set no further invoices are started
+/// and no ZIP is written.
+pub fn run_batch<R, P>(
+    jobs: Vec<InvoiceData>,
+    request: &BatchPdfRequest,
+    cancel: &CancelToken,
+    render: R,
+    on_progress: P,
+) -> Result<BatchSummary, String>
+where
+    R: Fn(InvoiceData, &Path) -> Result<(), String> + Sync,
+    P: Fn(BatchProgress) + Sync,
+{
+    let pattern = request.filename_pattern.as_deref().unwrap_or(DEFAULT_FILENAME_PATTERN);
+    validate_pattern(pattern)?;
+    let files = file_names(pattern, &jobs);
+
+    // ZIPs are assembled from a staging directory that is removed afterwards
+    let output_path = PathBuf::from(&request.output_path);
+    let output_dir = if request.zip {
+        std::env::temp_dir().join(format!("project-invoicer-batch-{}", uuid::Uuid::new_v4()))
+    } else {
+        output_path.clone()
+    };
+    std::fs::create_dir_all(&output_dir)
+        .map_err(|e| format!("Failed to create {}: {}", output_dir.display(), e))?;
+
+    let total = jobs.len();
+    let workers = request
+        .workers
+        .unwrap_or_else(|| std::thread::available_parallelism().map(|n| n.get()).unwrap_or(2))
+        .clamp(1, MAX_WORKERS)
+        .min(total.max(1));
+    let next = AtomicUsize::new(0);
+    let completed = AtomicUsize::new(0);
+    let errors: Mutex<Vec<Option<String>>> = Mutex::new(vec![Some(CANCELLED.to_string()); total]);
+
+    std::thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                if cancel.is_cancelled() {
+                    break;
+                }
+                let index = next.fetch_add(1, Ordering::SeqCst);
+                let Some(data) = jobs.get(index) else { break };
+
+                let path = output_dir.join(&files[index]);
+                let result = path
+                    .parent()
+                    .map_or(Ok(()), std::fs::create_dir_all)
+                    .map_err(|e| e.to_string())
+                    .and_then(|_| render(data.clone(), &path));
+                let error = result.err();
+                errors.lock().unwrap()[index] = error.clone();
+
+                on_progress(BatchProgress {
+                    completed: completed.fetch_add(1, Ordering::SeqCst) + 1,
+                    total,
+                    invoice_id: data.invoice.id.clone(),
+                    invoice_number: data.invoice.invoice_number.clone(),
+                    error,
+                });
+            });
+        }
+    });
+
+    let results: Vec<BatchItemResult> = jobs
+        .iter()
+        .zip(&files)
+        .zip(errors.into_inner().unwrap())
+        .map(|((data, file), error)| BatchItemResult {
+            invoice_id: data.invoice.id.clone(),
+            invoice_number: data.invoice.invoice_number.clone(),
+            file: zip_name(file),
+            error,
+        })
+        .collect();
+
+    // Cancelling after the last invoice started doesn't discard the batch
+    let cancelled = completed.load(Ordering::SeqCst) < total;
+    let manifest = manifest_csv(&jobs, &results)?;
+    if request.zip && cancelled {
+        let _ = std::fs::remove_dir_all(&output_dir);
+    } else if request.zip {
+        let bundled = write_zip(&output_path, &output_dir, &results, &manifest);
+        let _ = std::fs::remove_dir_all(&output_dir);
+        bundled?;
+    } else {
+        std::fs::write(output_dir.join(MANIFEST_FILE_NAME), manifest)
+            .map_err(|e| format!("Failed to write manifest: {}", e))?;
+    }
+
+    let failed = results.iter().filter(|r| r.error.is_some()).count();
+    Ok(BatchSummary {
+        output_path: output_path.to_string_lossy().to_string(),
+        succeeded: total - failed,
+        failed,
+        cancelled,
+        results,
+    })
+}
+
+/// Rejects unknown placeholders and patterns that could escape the output directory.
+pub fn validate_pattern(pattern: &str) -> Result<(), String> {
+    let mut rest = pattern;
+    while let Some(start) = rest.find('{') {
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| format!("Unclosed placeholder in filename pattern '{}'", pattern))?;
+        let name = &rest[start + 1..start + end];
+        if !PLACEHOLDERS.contains(&name) {
+            return Err(format!(
+                "Unknown placeholder {{{}}} in filename pattern (available: {})",
+                name,
+                PLACEHOLDERS.map(|p| format!("{{{}}}", p)).join(", ")
+            ));
+        }
+        rest = &rest[start + end + 1..];
+    }
+
+    let escapes = pattern.starts_with(['/', '\\'])
+        || pattern.contains(':')
+        || pattern.split(['/', '\\']).any(|c| c.trim() == "..");
+    if escapes {
+        return Err(format!("Filename pattern '{}' must stay inside the output folder", pattern));
+    }
+    Ok(())
+}
+
+/// Expands the pattern for every invoice. Names that would collide get a
+/// numeric suffix so no PDF overwrites another.
+fn file_names(pattern: &str, jobs: &[InvoiceData]) -> Vec<PathBuf> {
+    let mut taken = HashSet::new();
+    jobs.iter()
+        .map(|data| {
+            let expanded = expand_pattern(pattern, data);
+            let stem = if expanded.to_ascii_lowercase().ends_with(".pdf") {
+                expanded[..expanded.len() - 4].to_string()
+            } else {
+                expanded
+            };
+            let mut name = format!("{}.pdf", stem);
+            let mut counter = 2;
+            while !taken.insert(name.to_lowercase()) {
+                name = format!("{}-{}.pdf", stem, counter);
+                counter += 1;
+            }
+            name.split('/').collect()
+        })
+        .collect()
+}
+
+fn expand_pattern(pattern: &str, data: &InvoiceData) -> String {
+    let invoice = &data.invoice;
+    let value = |name: &str| -> String {
+        match name {
+            "artist" => data.artist.name.clone(),
+            "company" => data.artist.company_name.clone().filter(|s| !s.trim().is_empty())
+                .unwrap_or_else(|| data.artist.name.clone()),
+            "invoice_number" => invoice.invoice_number.clone(),
+            "project" => data.project.as_ref().map(|p| p.name.clone()).unwrap_or_else(|| "No Project".to_string()),
+            "status" => invoice.status.clone(),
+            "issue_date" => invoice.issue_date.to_string(),
+            "year" => invoice.issue_date.format("%Y").to_string(),
+            "month" => invoice.issue_date.format("%m").to_string(),
+            "currency" => invoice.currency.clone(),
+            _ => invoice.id.clone(),
+        }
+    };
+
+    let mut components = Vec::new();
+    for component in pattern.split(['/', '\\']) {
+        let mut expanded = String::new();
+        let mut rest = component;
+        while let Some(start) = rest.find('{') {
+            let end = start + rest[start..].find('}').unwrap_or(rest.len() - start);
+            expanded.push_str(&rest[..start]);
+            expanded.push_str(&sanitize(&value(&rest[start + 1..end])));
+            rest = rest.get(end + 1..).unwrap_or("");
+        }
+        expanded.push_str(rest);
+
+        let expanded = expanded.trim().trim_matches('.').to_string();
+        if !expanded.is_empty() {
+            components.push(expanded);
+        }
+    }
+
+    if components.is_empty() {
+        components.push(sanitize(&invoice.invoice_number));
+    }
+    components.join("/")
+}
+
+/// Makes a value safe to use in a file name on every platform.
+pub fn sanitize(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '-',
+            c if c.is_control() => '-',
+            c => c,
+        })
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+fn zip_name(path: &Path) -> String {
+    path.iter().map(|c| c.to_string_lossy()).collect::<Vec<_>>().join("/")
+}
+
+fn manifest_csv(jobs: &[InvoiceData], results: &[BatchItemResult]) -> Result<Vec<u8>, String> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer
+        .write_record([
+            "invoice_number", "artist", "issue_date", "due_date", "currency", "amount", "status", "file", "error",
+        ])
+        .map_err(|e| e.to_string())?;
+    for (data, result) in jobs.iter().zip(results) {
+        let invoice = &data.invoice;
+        writer
+            .write_record([
+                invoice.invoice_number.as_str(),
+                data.artist.name.as_str(),
+                &invoice.issue_date.to_string(),
+                &invoice.due_date.to_string(),
+                invoice.currency.as_str(),
+                &format!("{:.2}", invoice.amount),
+                invoice.status.as_str(),
+                if result.error.is_none() { result.file.as_str() } else { "" },
+                result.error.as_deref().unwrap_or(""),
+            ])
+            .map_err(|e| e.to_string())?;
+    }
+    writer.into_inner().map_err(|e| e.to_string())
+}
+
+fn write_zip(zip_path: &Path, staging_dir: &Path, results: &[BatchItemResult], manifest: &[u8]) -> Result<(), String> {
+    if let Some(parent) = zip_path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let file = File::create(zip_path).map_err(|e| format!("Failed to create {}: {}", zip_path.display(), e))?;
+    let mut zip = ZipWriter::new(file);
+    let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    for result in results.iter().filter(|r| r.error.is_none()) {
+        let pdf = std::fs::read(staging_dir.join(&result.file)).map_err(|e| e.to_string())?;
+        zip.start_file(result.file.as_str(), options).map_err(|e| e.to_string())?;
+        zip.write_all(&pdf).map_err(|e| e.to_string())?;
+    }
+    zip.start_file(MANIFEST_FILE_NAME, options).map_err(|e| e.to_string())?;
+    zip.write_all(manifest).map_err(|e| e.to_string())?;
+    zip.finish().map_err(|e| format!("Failed to write {}: {}", zip_path.display(), e))?;
+    Ok(())
+}
@@ -0,0 +1,83 @@
+// src-tauri/src/branding.rs
+use printpdf::image_crate::{self, DynamicImage};
+use printpdf::{Color, Rgb};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::database::BrandingProfile;
+use crate::fonts::FontFace;
+
+/// A branding profile with its files loaded, ready for the PDF renderer.
+#[derive(Clone, Default)]
+pub struct Branding {
+    pub logo: Option<DynamicImage>,
+    /// RGB components in 0.0..=1.0; black when unset
+    pub accent: Option<(f32, f32, f32)>,
+    pub font: Option<Arc<FontFace>>,
+    pub footer_text: Option<String>,
+}
+
+impl Branding {
+    /// Loads the logo and font referenced by `profile`. Relative paths are
+    /// looked up in the bundle's resource directory, so `assets/logo-white.png`
+    /// works out of the box. Files that can't be read are skipped so a broken
+    /// profile never stops an invoice from rendering.
+    pub fn load(profile: &BrandingProfile, resource_dir: Option<&Path>) -> Self {
+        let logo = non_empty(&profile.logo_path).and_then(|path| {
+            let path = resolve_path(path, resource_dir);
+            match image_crate::open(&path) {
+                Ok(image) => Some(image),
+                Err(e) => {
+                    eprintln!("Failed to load logo {}: {}", path.display(), e);
+                    None
+                }
+            }
+        });
+
+        let font = non_empty(&profile.font_path).and_then(|path| {
+            let path = resolve_path(path, resource_dir);
+            match std::fs::read(&path).map_err(|e| e.to_string()).and_then(FontFace::from_bytes) {
+                Ok(face) => Some(Arc::new(face)),
+                Err(e) => {
+                    eprintln!("Failed to load font {}: {}", path.display(), e);
+                    None
+                }
+            }
+        });
+
+        Branding {
+            logo,
+            accent: non_empty(&profile.accent_color).and_then(parse_hex_color),
+            font,
+            footer_text: non_empty(&profile.footer_text).map(str::to_string),
+        }
+    }
+
+    pub fn accent_color(&self) -> Color {
+        let (r, g, b) = self.accent.unwrap_or((0.0, 0.0, 0.0));
+        Color::Rgb(Rgb::new(r, g, b, None))
+    }
+}
+
+fn non_empty(value: &Option<String>) -> Option<&str> {
+    value.as_deref().map(str::trim).filter(|s| !s.is_empty())
+}
+
+fn resolve_path(path: &str, resource_dir: Option<&Path>) -> PathBuf {
+    let path = PathBuf::from(path);
+    match resource_dir {
+        Some(dir) if path.is_relative() => dir.join(path),
+        _ => path,
+    }
+}
+
+/// Parses `#RRGGBB` or `RRGGBB` into RGB components.
+pub fn parse_hex_color(value: &str) -> Option<(f32, f32, f32)> {
+    let hex = value.trim().trim_start_matches('#');
+    if hex.len() != 6 || !hex.is_ascii() {
+        return None;
+    }
+
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok().map(|v| v as f32 / 255.0);
+    Some((channel(0)?, channel(2)?, channel(4)?))
+}
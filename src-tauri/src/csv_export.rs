@@ -0,0 +1,389 @@
+// src-tauri/src/csv_export.rs
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufWriter;
+
+use crate::aging::{AgingEntry, AgingReport};
+use crate::database::{Artist, Database, Invoice, InvoiceFilter, Project};
+use crate::pdf_generator::{LineItem, Totals};
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CsvExportRequest {
+    pub output_path: String,
+    // Column names in output order; empty for every column
+    #[serde(default)]
+    pub columns: Vec<String>,
+    #[serde(default)]
+    pub statuses: Vec<String>,
+    pub artist_id: Option<String>,
+    // Inclusive range on the entity's main date: issue date for invoices,
+    // start date for projects, paid date for payments and creation date for
+    // artists
+    pub date_from: Option<NaiveDate>,
+    pub date_to: Option<NaiveDate>,
+    #[serde(default = "default_delimiter")]
+    pub delimiter: char,
+    #[serde(default = "default_decimal_separator")]
+    pub decimal_separator: char,
+    // Invoices only: one row per line item instead of one per invoice
+    #[serde(default)]
+    pub flatten_line_items: bool,
+}
+
+fn default_delimiter() -> char {
+    ','
+}
+
+fn default_decimal_separator() -> char {
+    '.'
+}
+
+enum Value {
+    Text(String),
+    Money(f64),
+    Number(f64),
+    Flag(bool),
+    Empty,
+}
+
+fn text(value: &str) -> Value {
+    Value::Text(value.to_string())
+}
+
+fn optional(value: &Option<String>) -> Value {
+    value.as_deref().map_or(Value::Empty, text)
+}
+
+fn date(value: &NaiveDate) -> Value {
+    Value::Text(value.format("%Y-%m-%d").to_string())
+}
+
+fn optional_date(value: &Option<NaiveDate>) -> Value {
+    value.as_ref().map_or(Value::Empty, date)
+}
+
+fn timestamp(value: &DateTime<Utc>) -> Value {
+    Value::Text(value.to_rfc3339())
+}
+
+type Column<T> = (&'static str, fn(&T) -> Value);
+
+struct InvoiceRow {
+    invoice: Invoice,
+    artist: Option<String>,
+    project: Option<String>,
+    totals: Totals,
+    item_count: usize,
+    // Set when line items are flattened
+    item: Option<(usize, LineItem)>,
+}
+
+const INVOICE_COLUMNS: [Column<InvoiceRow>; 22] = [
+    ("id", |r| text(&r.invoice.id)),
+    ("invoice_number", |r| text(&r.invoice.invoice_number)),
+    ("document_type", |r| text(&r.invoice.document_type)),
+    ("artist", |r| optional(&r.artist)),
+    ("artist_id", |r| text(&r.invoice.artist_id)),
+    ("client_id", |r| optional(&r.invoice.client_id)),
+    ("project", |r| optional(&r.project)),
+    ("status", |r| text(&r.invoice.status)),
+    ("issue_date", |r| date(&r.invoice.issue_date)),
+    ("due_date", |r| date(&r.invoice.due_date)),
+    ("paid_date", |r| optional_date(&r.invoice.paid_date)),
+    ("sent_at", |r| r.invoice.sent_at.as_ref().map_or(Value::Empty, timestamp)),
+    ("currency", |r| text(&r.invoice.currency)),
+    ("amount", |r| Value::Money(r.totals.net)),
+    ("tax_rate", |r| r.invoice.tax_rate.map_or(Value::Empty, Value::Number)),
+    ("tax", |r| r.totals.tax.map_or(Value::Empty, Value::Money)),
+    ("total", |r| Value::Money(r.totals.gross)),
+    ("payment_reference", |r| optional(&r.invoice.payment_reference)),
+    ("bill_to", |r| optional(&r.invoice.bill_to)),
+    ("bill_to_email", |r| optional(&r.invoice.bill_to_email)),
+    ("notes", |r| optional(&r.invoice.notes)),
+    ("item_count", |r| Value::Number(r.item_count as f64)),
+];
+
+const LINE_ITEM_COLUMNS: [Column<InvoiceRow>; 8] = [
+    ("item_number", |r| r.item.as_ref().map_or(Value::Empty, |(n, _)| Value::Number(*n as f64))),
+    ("item_description", |r| r.item.as_ref().map_or(Value::Empty, |(_, i)| text(&i.description))),
+    ("item_artist", |r| r.item.as_ref().map_or(Value::Empty, |(_, i)| optional(&i.artist))),
+    ("item_song_project", |r| r.item.as_ref().map_or(Value::Empty, |(_, i)| optional(&i.song_project))),
+    ("item_company", |r| r.item.as_ref().map_or(Value::Empty, |(_, i)| optional(&i.company))),
+    ("item_amount", |r| r.item.as_ref().map_or(Value::Empty, |(_, i)| Value::Money(i.amount))),
+    ("item_delivered", |r| r.item.as_ref().map_or(Value::Empty, |(_, i)| Value::Flag(i.delivered))),
+    ("item_invoiced", |r| r.item.as_ref().map_or(Value::Empty, |(_, i)| Value::Flag(i.invoiced))),
+];
+
+struct ProjectRow {
+    project: Project,
+    artist: Option<String>,
+}
+
+const PROJECT_COLUMNS: [Column<ProjectRow>; 10] = [
+    ("id", |r| text(&r.project.id)),
+    ("name", |r| text(&r.project.name)),
+    ("artist", |r| optional(&r.artist)),
+    ("artist_id", |r| text(&r.project.artist_id)),
+    ("status", |r| text(&r.project.status)),
+    ("start_date", |r| optional_date(&r.project.start_date)),
+    ("end_date", |r| optional_date(&r.project.end_date)),
+    ("budget", |r| Value::Money(r.project.budget)),
+    ("description", |r| optional(&r.project.description)),
+    ("created_at", |r| timestamp(&r.project.created_at)),
+];
+
+const ARTIST_COLUMNS: [Column<Artist>; 13] = [
+    ("id", |a| text(&a.id)),
+    ("name", |a| text(&a.name)),
+    ("company_name", |a| optional(&a.company_name)),
+    ("email", |a| optional(&a.email)),
+    ("phone", |a| optional(&a.phone)),
+    ("address", |a| optional(&a.address)),
+    ("tax_id", |a| optional(&a.tax_id)),
+    ("country_code", |a| optional(&a.country_code)),
+    ("iban", |a| optional(&a.bank_details.iban)),
+    ("bic", |a| optional(&a.bank_details.bic)),
+    ("account_holder", |a| optional(&a.bank_details.account_holder)),
+    ("notes", |a| optional(&a.notes)),
+    ("created_at", |a| timestamp(&a.created_at)),
+];
+
+const PAYMENT_COLUMNS: [Column<InvoiceRow>; 10] = [
+    ("invoice_id", |r| text(&r.invoice.id)),
+    ("invoice_number", |r| text(&r.invoice.invoice_number)),
+    ("artist", |r| optional(&r.artist)),
+    ("artist_id", |r| text(&r.invoice.artist_id)),
+    ("paid_date", |r| optional_date(&r.invoice.paid_date)),
+    ("payment_reference", |r| optional(&r.invoice.payment_reference)),
+    ("currency", |r| text(&r.invoice.currency)),
+    ("amount", |r| Value::Money(r.totals.net)),
+    ("tax", |r| r.totals.tax.map_or(Value::Empty, Value::Money)),
+    ("total", |r| Value::Money(r.totals.gross)),
+];
+
+const AGING_COLUMNS: [Column<AgingEntry>; 10] = [
+    ("invoice_id", |e| text(&e.invoice_id)),
+    ("invoice_number", |e| text(&e.invoice_number)),
+    ("artist", |e| text(&e.artist_name)),
+    ("artist_id", |e| text(&e.artist_id)),
+    ("issue_date", |e| date(&e.issue_date)),
+    ("due_date", |e| date(&e.due_date)),
+    ("days_overdue", |e| Value::Number(e.days_overdue as f64)),
+    ("bucket", |e| text(e.bucket.label())),
+    ("currency", |e| text(&e.currency)),
+    ("outstanding", |e| Value::Money(e.outstanding)),
+];
+
+/// Writes one row per invoice, or one per line item when flattening.
+/// Returns the number of data rows written.
+pub fn export_invoices(db: &Database, request: &CsvExportRequest) -> Result<usize, String> {
+    let filter = InvoiceFilter {
+        artist_id: request.artist_id.clone(),
+        project_id: None,
+        statuses: request.statuses.clone(),
+        issued_from: request.date_from,
+        issued_to: request.date_to,
+    };
+    let invoices = db.get_all_invoices().map_err(|e| e.to_string())?;
+    let artists = artist_names(db)?;
+    let projects: HashMap<String, String> = db.get_all_projects()
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .map(|p| (p.id, p.name))
+        .collect();
+
+    let mut columns = INVOICE_COLUMNS.to_vec();
+    if request.flatten_line_items {
+        columns.extend(LINE_ITEM_COLUMNS);
+    }
+    let mut writer = CsvWriter::create(request, &columns)?;
+
+    for invoice in invoices.into_iter().filter(|i| filter.matches(i)) {
+        let items: Vec<LineItem> = serde_json::from_str(&invoice.items)
+            .map_err(|e| format!("Failed to parse line items of {}: {}", invoice.invoice_number, e))?;
+        let mut row = InvoiceRow {
+            artist: artists.get(&invoice.artist_id).cloned(),
+            project: invoice.project_id.as_ref().and_then(|id| projects.get(id)).cloned(),
+            totals: Totals::for_invoice(&invoice),
+            item_count: items.len(),
+            item: None,
+            invoice,
+        };
+
+        if request.flatten_line_items && !items.is_empty() {
+            for (index, item) in items.into_iter().enumerate() {
+                row.item = Some((index + 1, item));
+                writer.write(&row)?;
+            }
+        } else {
+            writer.write(&row)?;
+        }
+    }
+
+    writer.finish()
+}
+
+pub fn export_projects(db: &Database, request: &CsvExportRequest) -> Result<usize, String> {
+    let projects = db.get_all_projects().map_err(|e| e.to_string())?;
+    let artists = artist_names(db)?;
+    let mut writer = CsvWriter::create(request, &PROJECT_COLUMNS)?;
+
+    let selected = projects.into_iter().filter(|p| {
+        request.artist_id.as_ref().is_none_or(|id| &p.artist_id == id)
+            && (request.statuses.is_empty() || request.statuses.contains(&p.status))
+            && in_range(request, p.start_date)
+    });
+    for project in selected {
+        let artist = artists.get(&project.artist_id).cloned();
+        writer.write(&ProjectRow { project, artist })?;
+    }
+
+    writer.finish()
+}
+
+/// Statuses don't apply to artists; `artist_id` exports a single artist.
+pub fn export_artists(db: &Database, request: &CsvExportRequest) -> Result<usize, String> {
+    let artists = db.get_all_artists().map_err(|e| e.to_string())?;
+    let mut writer = CsvWriter::create(request, &ARTIST_COLUMNS)?;
+
+    let selected = artists.iter().filter(|a| {
+        request.artist_id.as_ref().is_none_or(|id| &a.id == id) && in_range(request, Some(a.created_at.date_naive()))
+    });
+    for artist in selected {
+        writer.write(artist)?;
+    }
+
+    writer.finish()
+}
+
+/// Payments are the invoices with a recorded payment date.
+pub fn export_payments(db: &Database, request: &CsvExportRequest) -> Result<usize, String> {
+    let invoices = db.get_all_invoices().map_err(|e| e.to_string())?;
+    let artists = artist_names(db)?;
+    let mut writer = CsvWriter::create(request, &PAYMENT_COLUMNS)?;
+
+    let selected = invoices.into_iter().filter(|i| {
+        i.paid_date.is_some()
+            && request.artist_id.as_ref().is_none_or(|id| &i.artist_id == id)
+            && (request.statuses.is_empty() || request.statuses.contains(&i.status))
+            && in_range(request, i.paid_date)
+    });
+    for invoice in selected {
+        writer.write(&InvoiceRow {
+            artist: artists.get(&invoice.artist_id).cloned(),
+            project: None,
+            totals: Totals::for_invoice(&invoice),
+            item_count: 0,
+            item: None,
+            invoice,
+        })?;
+    }
+
+    writer.finish()
+}
+
+/// One row per open invoice in the report; the request's filters don't apply.
+pub fn export_aging(report: &AgingReport, request: &CsvExportRequest) -> Result<usize, String> {
+    let mut writer = CsvWriter::create(request, &AGING_COLUMNS)?;
+    for entry in &report.invoices {
+        writer.write(entry)?;
+    }
+    writer.finish()
+}
+
+fn artist_names(db: &Database) -> Result<HashMap<String, String>, String> {
+    Ok(db.get_all_artists()
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .map(|a| (a.id, a.name))
+        .collect())
+}
+
+/// Whether `date` falls within the request's inclusive date range; undated
+/// rows only match when no range is set.
+fn in_range(request: &CsvExportRequest, date: Option<NaiveDate>) -> bool {
+    if request.date_from.is_none() && request.date_to.is_none() {
+        return true;
+    }
+    let Some(date) = date else { return false };
+    request.date_from.is_none_or(|from| date >= from) && request.date_to.is_none_or(|to| date <= to)
+}
+
+/// Streams rows straight to the output file.
+struct CsvWriter<T> {
+    writer: csv::Writer<BufWriter<File>>,
+    columns: Vec<Column<T>>,
+    decimal_separator: char,
+    rows: usize,
+}
+
+impl<T> CsvWriter<T> {
+    fn create(request: &CsvExportRequest, available: &[Column<T>]) -> Result<Self, String> {
+        let delimiter = request.delimiter;
+        if !delimiter.is_ascii() || matches!(delimiter, '"' | '\n' | '\r') {
+            return Err(format!("Unsupported CSV delimiter '{}'", delimiter));
+        }
+        if request.decimal_separator == delimiter {
+            return Err("The decimal separator can't be the same as the delimiter".to_string());
+        }
+
+        let columns = if request.columns.is_empty() {
+            available.to_vec()
+        } else {
+            request
+                .columns
+                .iter()
+                .map(|name| {
+                    available.iter().find(|(column, _)| column == name).copied().ok_or_else(|| {
+                        let names: Vec<&str> = available.iter().map(|(column, _)| *column).collect();
+                        format!("Unknown column '{}' (available: {})", name, names.join(", "))
+                    })
+                })
+                .collect::<Result<Vec<_>, _>>()?
+        };
+
+        let file = File::create(&request.output_path)
+            .map_err(|e| format!("Failed to create {}: {}", request.output_path, e))?;
+        let mut writer = csv::WriterBuilder::new()
+            .delimiter(delimiter as u8)
+            .from_writer(BufWriter::new(file));
+        writer
+            .write_record(columns.iter().map(|(name, _)| *name))
+            .map_err(|e| e.to_string())?;
+
+        Ok(CsvWriter {
+            writer,
+            columns,
+            decimal_separator: request.decimal_separator,
+            rows: 0,
+        })
+    }
+
+    fn write(&mut self, row: &T) -> Result<(), String> {
+        let record = self.columns.iter().map(|(_, value)| match value(row) {
+            Value::Text(text) => text,
+            Value::Money(amount) => self.number(format!("{:.2}", amount)),
+            Value::Number(number) => self.number(number.to_string()),
+            Value::Flag(flag) => flag.to_string(),
+            Value::Empty => String::new(),
+        });
+        self.writer.write_record(record.collect::<Vec<_>>()).map_err(|e| e.to_string())?;
+        self.rows += 1;
+        Ok(())
+    }
+
+    fn number(&self, formatted: String) -> String {
+        if self.decimal_separator == '.' {
+            formatted
+        } else {
+            formatted.replace('.', &self.decimal_separator.to_string())
+        }
+    }
+
+    fn finish(mut self) -> Result<usize, String> {
+        self.writer.flush().map_err(|e| format!("Failed to write CSV: {}", e))?;
+        Ok(self.rows)
+    }
+}
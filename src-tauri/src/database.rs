@@ -1,12 +1,16 @@
 // src-tauri/src/database.rs
-use rusqlite::{Connection, Result, params};
-use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use rusqlite::{Connection, OptionalExtension, Result, params};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
 use uuid::Uuid;
-use chrono::Utc;
+use chrono::{DateTime, FixedOffset, Local, NaiveDate, NaiveDateTime, NaiveTime, Utc};
 use r2d2_sqlite::SqliteConnectionManager;
 use r2d2::{Pool, PooledConnection};
-use lazy_static::lazy_static;
+use tokio::sync::Semaphore;
+use crate::payment_terms::PaymentTerms;
+use crate::reminders::ReminderSchedule;
+use crate::validation::{self, Validator, MAX_NAME, MAX_NOTES, MAX_SHORT, MAX_TEXT};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Artist {
@@ -18,8 +22,390 @@ pub struct Artist {
     pub address: Option<String>,
     pub wire_details: Option<String>,
     pub notes: Option<String>,
-    pub created_at: String,
-    pub updated_at: String,
+    // VAT / tax registration number and ISO 3166-1 alpha-2 country, for e-invoices
+    pub tax_id: Option<String>,
+    pub country_code: Option<String>,
+    // Peppol participant ID as "scheme:identifier", e.g. "0088:5790000435975"
+    pub peppol_id: Option<String>,
+    // Default invoice PDF template for this artist
+    pub pdf_template: Option<String>,
+    // Default terms for new invoices that give neither terms nor a due date
+    pub payment_terms: Option<PaymentTerms>,
+    // Payment reminders for the artist's unpaid invoices; none when unset
+    pub reminder_schedule: Option<ReminderSchedule>,
+    #[serde(default)]
+    pub bank_details: BankDetails,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+// Structured payment details, used for the payment QR code on invoices
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BankDetails {
+    pub account_holder: Option<String>,
+    pub iban: Option<String>,
+    pub bic: Option<String>,
+    // US domestic transfers
+    pub routing_number: Option<String>,
+    pub account_number: Option<String>,
+    // "epc" for a SEPA transfer QR, "uri" for `payment_uri`, None for no QR code
+    pub payment_qr: Option<String>,
+    // e.g. "https://pay.example.com/?amount={amount}&ref={invoice_number}"
+    pub payment_uri: Option<String>,
+}
+
+impl BankDetails {
+    pub fn validate(&self) -> Vec<String> {
+        let mut v = Validator::new();
+        v.max_length("account_holder", self.account_holder.as_deref(), MAX_NAME);
+        v.iban("iban", self.iban.as_deref());
+        v.bic("bic", self.bic.as_deref());
+        v.max_length("routing_number", self.routing_number.as_deref(), MAX_SHORT);
+        v.max_length("account_number", self.account_number.as_deref(), MAX_SHORT);
+        v.max_length("payment_uri", self.payment_uri.as_deref(), MAX_TEXT);
+        v.into_errors()
+    }
+}
+
+// Input for set_artist_tax_details; blank fields are cleared
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ArtistTaxDetails {
+    pub tax_id: Option<String>,
+    pub country_code: Option<String>,
+    pub peppol_id: Option<String>,
+}
+
+impl ArtistTaxDetails {
+    pub fn validate(&self) -> Vec<String> {
+        let mut v = Validator::new();
+        v.max_length("tax_id", self.tax_id.as_deref(), MAX_SHORT);
+        validate_country_code(&mut v, "country_code", self.country_code.as_deref());
+        v.max_length("peppol_id", self.peppol_id.as_deref(), MAX_SHORT);
+        v.into_errors()
+    }
+}
+
+// Input for create_artist; omitted optional fields are left empty
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct NewArtist {
+    pub name: String,
+    pub company_name: Option<String>,
+    pub email: Option<String>,
+    pub phone: Option<String>,
+    pub address: Option<String>,
+    pub wire_details: Option<String>,
+    pub notes: Option<String>,
+}
+
+impl NewArtist {
+    pub fn validate(&self) -> Vec<String> {
+        validate_artist(
+            &self.name,
+            self.email.as_deref(),
+            self.phone.as_deref(),
+            [
+                ("company_name", self.company_name.as_deref(), MAX_NAME),
+                ("address", self.address.as_deref(), MAX_TEXT),
+                ("wire_details", self.wire_details.as_deref(), MAX_TEXT),
+                ("notes", self.notes.as_deref(), MAX_NOTES),
+            ],
+        )
+    }
+}
+
+// Changes for update_artist. Only the fields present are changed; null
+// clears an optional field.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ArtistPatch {
+    pub name: Option<String>,
+    #[serde(default, deserialize_with = "patch_field")]
+    pub company_name: Option<Option<String>>,
+    #[serde(default, deserialize_with = "patch_field")]
+    pub email: Option<Option<String>>,
+    #[serde(default, deserialize_with = "patch_field")]
+    pub phone: Option<Option<String>>,
+    #[serde(default, deserialize_with = "patch_field")]
+    pub address: Option<Option<String>>,
+    #[serde(default, deserialize_with = "patch_field")]
+    pub wire_details: Option<Option<String>>,
+    #[serde(default, deserialize_with = "patch_field")]
+    pub notes: Option<Option<String>>,
+}
+
+impl ArtistPatch {
+    pub fn apply(self, artist: &mut Artist) {
+        if let Some(name) = self.name {
+            artist.name = name;
+        }
+        if let Some(company_name) = self.company_name {
+            artist.company_name = company_name;
+        }
+        if let Some(email) = self.email {
+            artist.email = email;
+        }
+        if let Some(phone) = self.phone {
+            artist.phone = phone;
+        }
+        if let Some(address) = self.address {
+            artist.address = address;
+        }
+        if let Some(wire_details) = self.wire_details {
+            artist.wire_details = wire_details;
+        }
+        if let Some(notes) = self.notes {
+            artist.notes = notes;
+        }
+    }
+}
+
+impl Artist {
+    pub fn validate(&self) -> Vec<String> {
+        validate_artist(
+            &self.name,
+            self.email.as_deref(),
+            self.phone.as_deref(),
+            [
+                ("company_name", self.company_name.as_deref(), MAX_NAME),
+                ("address", self.address.as_deref(), MAX_TEXT),
+                ("wire_details", self.wire_details.as_deref(), MAX_TEXT),
+                ("notes", self.notes.as_deref(), MAX_NOTES),
+            ],
+        )
+    }
+}
+
+// Tells a field that was left out (None) from an explicit null (Some(None))
+fn patch_field<'de, T, D>(deserializer: D) -> std::result::Result<Option<Option<T>>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+// A customer billed on invoices. Invoices copy its details when they're
+// issued, so later edits don't change invoices already sent.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Client {
+    pub id: String,
+    // As printed on invoices
+    pub legal_name: String,
+    pub contact_name: Option<String>,
+    // Where invoices and payment reminders are emailed
+    pub email: Option<String>,
+    // Comma-separated addresses copied on invoice emails
+    pub cc_emails: Option<String>,
+    pub address_line1: Option<String>,
+    pub address_line2: Option<String>,
+    pub postal_code: Option<String>,
+    pub city: Option<String>,
+    pub region: Option<String>,
+    // ISO 3166-1 alpha-2
+    pub country_code: Option<String>,
+    // VAT / tax registration number
+    pub tax_id: Option<String>,
+    pub peppol_id: Option<String>,
+    // Defaults for new invoices to this client
+    pub currency: Option<String>,
+    pub payment_terms: Option<PaymentTerms>,
+    pub notes: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+// Input for create_client; omitted optional fields are left empty
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct NewClient {
+    pub legal_name: String,
+    pub contact_name: Option<String>,
+    pub email: Option<String>,
+    pub cc_emails: Option<String>,
+    pub address_line1: Option<String>,
+    pub address_line2: Option<String>,
+    pub postal_code: Option<String>,
+    pub city: Option<String>,
+    pub region: Option<String>,
+    pub country_code: Option<String>,
+    pub tax_id: Option<String>,
+    pub peppol_id: Option<String>,
+    pub currency: Option<String>,
+    pub payment_terms: Option<PaymentTerms>,
+    pub notes: Option<String>,
+}
+
+// Changes for update_client; see ArtistPatch
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ClientPatch {
+    pub legal_name: Option<String>,
+    #[serde(default, deserialize_with = "patch_field")]
+    pub contact_name: Option<Option<String>>,
+    #[serde(default, deserialize_with = "patch_field")]
+    pub email: Option<Option<String>>,
+    #[serde(default, deserialize_with = "patch_field")]
+    pub cc_emails: Option<Option<String>>,
+    #[serde(default, deserialize_with = "patch_field")]
+    pub address_line1: Option<Option<String>>,
+    #[serde(default, deserialize_with = "patch_field")]
+    pub address_line2: Option<Option<String>>,
+    #[serde(default, deserialize_with = "patch_field")]
+    pub postal_code: Option<Option<String>>,
+    #[serde(default, deserialize_with = "patch_field")]
+    pub city: Option<Option<String>>,
+    #[serde(default, deserialize_with = "patch_field")]
+    pub region: Option<Option<String>>,
+    #[serde(default, deserialize_with = "patch_field")]
+    pub country_code: Option<Option<String>>,
+    #[serde(default, deserialize_with = "patch_field")]
+    pub tax_id: Option<Option<String>>,
+    #[serde(default, deserialize_with = "patch_field")]
+    pub peppol_id: Option<Option<String>>,
+    #[serde(default, deserialize_with = "patch_field")]
+    pub currency: Option<Option<String>>,
+    #[serde(default, deserialize_with = "patch_field")]
+    pub payment_terms: Option<Option<PaymentTerms>>,
+    #[serde(default, deserialize_with = "patch_field")]
+    pub notes: Option<Option<String>>,
+}
+
+impl NewClient {
+    // The client record create_client saves, with a fresh id
+    pub fn into_client(self) -> Client {
+        let NewClient {
+            legal_name, contact_name, email, cc_emails, address_line1, address_line2, postal_code, city, region,
+            country_code, tax_id, peppol_id, currency, payment_terms, notes,
+        } = self;
+        let now = Utc::now();
+        Client {
+            id: Uuid::new_v4().to_string(),
+            legal_name, contact_name, email, cc_emails, address_line1, address_line2, postal_code, city, region,
+            country_code, tax_id, peppol_id, currency, payment_terms, notes,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    pub fn validate(&self) -> Vec<String> {
+        self.clone().into_client().validate()
+    }
+}
+
+impl ClientPatch {
+    pub fn apply(self, client: &mut Client) {
+        if let Some(legal_name) = self.legal_name {
+            client.legal_name = legal_name;
+        }
+        if let Some(contact_name) = self.contact_name {
+            client.contact_name = contact_name;
+        }
+        if let Some(email) = self.email {
+            client.email = email;
+        }
+        if let Some(cc_emails) = self.cc_emails {
+            client.cc_emails = cc_emails;
+        }
+        if let Some(address_line1) = self.address_line1 {
+            client.address_line1 = address_line1;
+        }
+        if let Some(address_line2) = self.address_line2 {
+            client.address_line2 = address_line2;
+        }
+        if let Some(postal_code) = self.postal_code {
+            client.postal_code = postal_code;
+        }
+        if let Some(city) = self.city {
+            client.city = city;
+        }
+        if let Some(region) = self.region {
+            client.region = region;
+        }
+        if let Some(country_code) = self.country_code {
+            client.country_code = country_code;
+        }
+        if let Some(tax_id) = self.tax_id {
+            client.tax_id = tax_id;
+        }
+        if let Some(peppol_id) = self.peppol_id {
+            client.peppol_id = peppol_id;
+        }
+        if let Some(currency) = self.currency {
+            client.currency = currency;
+        }
+        if let Some(payment_terms) = self.payment_terms {
+            client.payment_terms = payment_terms;
+        }
+        if let Some(notes) = self.notes {
+            client.notes = notes;
+        }
+    }
+}
+
+impl Client {
+    pub fn validate(&self) -> Vec<String> {
+        let mut v = Validator::new();
+        v.required("legal_name", &self.legal_name, MAX_NAME);
+        v.email("email", self.email.as_deref());
+        v.max_length("cc_emails", self.cc_emails.as_deref(), MAX_TEXT);
+        for address in self.cc_emails.iter().flat_map(|e| e.split(',')).filter(|a| !a.trim().is_empty()) {
+            v.email("cc_emails", Some(address));
+        }
+        validate_country_code(&mut v, "country_code", self.country_code.as_deref());
+        validate_currency(&mut v, "currency", self.currency.as_deref());
+        if let Some(terms) = &self.payment_terms {
+            for error in terms.validate(Local::now().date_naive()) {
+                v.error(error);
+            }
+            if matches!(terms, PaymentTerms::FixedDate { .. }) {
+                v.error("a fixed date can't be a client's default payment terms".to_string());
+            }
+        }
+        v.max_length("contact_name", self.contact_name.as_deref(), MAX_NAME);
+        v.max_length("address_line1", self.address_line1.as_deref(), MAX_NAME);
+        v.max_length("address_line2", self.address_line2.as_deref(), MAX_NAME);
+        v.max_length("postal_code", self.postal_code.as_deref(), MAX_SHORT);
+        v.max_length("city", self.city.as_deref(), MAX_NAME);
+        v.max_length("region", self.region.as_deref(), MAX_NAME);
+        v.max_length("tax_id", self.tax_id.as_deref(), MAX_SHORT);
+        v.max_length("peppol_id", self.peppol_id.as_deref(), MAX_SHORT);
+        v.max_length("notes", self.notes.as_deref(), MAX_NOTES);
+        v.into_errors()
+    }
+
+    /// The name and postal address as invoice `bill_to` lines: name first,
+    /// then the address; the country is kept in `buyer_country_code`.
+    pub fn bill_to(&self) -> String {
+        let city = [self.postal_code.as_deref(), self.city.as_deref()]
+            .into_iter()
+            .flatten()
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ");
+        [Some(self.legal_name.as_str()), self.address_line1.as_deref(), self.address_line2.as_deref(), Some(&city)]
+            .into_iter()
+            .chain([self.region.as_deref()])
+            .flatten()
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    // Trims every field and upper-cases the codes, leaving blanks as None
+    fn normalize(&mut self) {
+        let clean = |value: &mut Option<String>| {
+            *value = value.as_deref().map(str::trim).filter(|s| !s.is_empty()).map(str::to_string);
+        };
+        self.legal_name = self.legal_name.trim().to_string();
+        for value in [
+            &mut self.contact_name, &mut self.email, &mut self.cc_emails, &mut self.address_line1,
+            &mut self.address_line2, &mut self.postal_code, &mut self.city, &mut self.region, &mut self.country_code,
+            &mut self.tax_id, &mut self.peppol_id, &mut self.currency, &mut self.notes,
+        ] {
+            clean(value);
+        }
+        self.country_code = self.country_code.as_deref().map(str::to_uppercase);
+        self.currency = self.currency.as_deref().map(str::to_uppercase);
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -29,11 +415,91 @@ pub struct Project {
     pub name: String,
     pub description: Option<String>,
     pub status: String,
-    pub start_date: Option<String>,
-    pub end_date: Option<String>,
+    pub start_date: Option<NaiveDate>,
+    pub end_date: Option<NaiveDate>,
+    pub budget: f64,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct NewProject {
+    pub artist_id: String,
+    pub name: String,
+    pub description: Option<String>,
+    #[serde(default = "default_project_status")]
+    pub status: String,
+    pub start_date: Option<NaiveDate>,
+    pub end_date: Option<NaiveDate>,
+    #[serde(default)]
     pub budget: f64,
-    pub created_at: String,
-    pub updated_at: String,
+}
+
+fn default_project_status() -> String { "active".to_string() }
+
+impl NewProject {
+    pub fn validate(&self) -> Vec<String> {
+        validate_project(
+            &self.artist_id,
+            &self.name,
+            self.description.as_deref(),
+            &self.status,
+            self.start_date,
+            self.end_date,
+            self.budget,
+        )
+    }
+}
+
+// Changes for update_project; see ArtistPatch
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ProjectPatch {
+    pub name: Option<String>,
+    #[serde(default, deserialize_with = "patch_field")]
+    pub description: Option<Option<String>>,
+    pub status: Option<String>,
+    #[serde(default, deserialize_with = "patch_field")]
+    pub start_date: Option<Option<NaiveDate>>,
+    #[serde(default, deserialize_with = "patch_field")]
+    pub end_date: Option<Option<NaiveDate>>,
+    pub budget: Option<f64>,
+}
+
+impl ProjectPatch {
+    pub fn apply(self, project: &mut Project) {
+        if let Some(name) = self.name {
+            project.name = name;
+        }
+        if let Some(description) = self.description {
+            project.description = description;
+        }
+        if let Some(status) = self.status {
+            project.status = status;
+        }
+        if let Some(start_date) = self.start_date {
+            project.start_date = start_date;
+        }
+        if let Some(end_date) = self.end_date {
+            project.end_date = end_date;
+        }
+        if let Some(budget) = self.budget {
+            project.budget = budget;
+        }
+    }
+}
+
+impl Project {
+    pub fn validate(&self) -> Vec<String> {
+        validate_project(
+            &self.artist_id,
+            &self.name,
+            self.description.as_deref(),
+            &self.status,
+            self.start_date,
+            self.end_date,
+            self.budget,
+        )
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -44,49 +510,507 @@ pub struct Invoice {
     pub invoice_number: String,
     pub amount: f64,
     pub status: String,
-    pub issue_date: String,
-    pub due_date: String,
-    pub paid_date: Option<String>,
+    pub issue_date: NaiveDate,
+    pub due_date: NaiveDate,
+    // When set, due_date is computed from these
+    pub payment_terms: Option<PaymentTerms>,
+    pub paid_date: Option<NaiveDate>,
+    // Transaction ID / check number recorded with the payment
+    pub payment_reference: Option<String>,
+    // When the invoice was last emailed from the app
+    pub sent_at: Option<DateTime<Utc>>,
+    // ISO 4217 code
+    pub currency: String,
+    // VAT percentage; None when the invoice carries no tax breakdown
+    pub tax_rate: Option<f64>,
+    // UNCL5305 VAT category (S, Z, E, AE, K, G, O); derived from tax_rate when unset
+    pub tax_category: Option<String>,
+    pub buyer_tax_id: Option<String>,
+    pub buyer_country_code: Option<String>,
+    pub buyer_peppol_id: Option<String>,
+    // "invoice" or "credit_note"
+    pub document_type: String,
+    // The invoice a credit note corrects
+    pub credited_invoice_id: Option<String>,
+    // The client billed; bill_to and the buyer fields are copied from it
+    pub client_id: Option<String>,
+    pub bill_to: Option<String>,
+    // Where the invoice and payment reminders are emailed
+    pub bill_to_email: Option<String>,
+    pub items: String,
+    pub notes: Option<String>,
+    // Overrides the artist's PDF template for this invoice
+    pub pdf_template: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct NewInvoice {
+    pub artist_id: String,
+    // An empty string is treated as no project
+    pub project_id: Option<String>,
+    pub invoice_number: String,
+    pub amount: f64,
+    #[serde(default = "default_invoice_status")]
+    pub status: String,
+    pub issue_date: NaiveDate,
+    // Either a due date or payment terms; with neither the artist's default terms apply
+    #[serde(default)]
+    pub due_date: Option<NaiveDate>,
+    #[serde(default)]
+    pub payment_terms: Option<PaymentTerms>,
+    // Defaults to the client's currency, else USD
+    #[serde(default)]
+    pub currency: Option<String>,
+    // Fills bill_to, bill_to_email and the buyer tax details that aren't given
+    #[serde(default)]
+    pub client_id: Option<String>,
     pub bill_to: Option<String>,
+    #[serde(default)]
+    pub bill_to_email: Option<String>,
+    // JSON array of line items
+    #[serde(default = "default_items")]
     pub items: String,
     pub notes: Option<String>,
-    pub created_at: String,
-    pub updated_at: String,
 }
 
-fn get_db_path() -> PathBuf {
+fn default_invoice_status() -> String { "pending".to_string() }
+fn default_currency() -> String { "USD".to_string() }
+fn default_items() -> String { "[]".to_string() }
+
+impl NewInvoice {
+    pub fn validate(&self) -> Vec<String> {
+        let mut errors = validate_invoice(
+            &self.artist_id,
+            &self.invoice_number,
+            self.amount,
+            self.currency.as_deref(),
+            self.bill_to_email.as_deref(),
+            &self.items,
+            [("bill_to", self.bill_to.as_deref(), MAX_TEXT), ("notes", self.notes.as_deref(), MAX_NOTES)],
+        );
+        errors.extend(validate_due_date(self.issue_date, self.due_date, self.payment_terms.as_ref()));
+        errors
+    }
+}
+
+// Changes for update_invoice; see ArtistPatch
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct InvoicePatch {
+    #[serde(default, deserialize_with = "patch_field")]
+    pub project_id: Option<Option<String>>,
+    pub invoice_number: Option<String>,
+    pub amount: Option<f64>,
+    pub status: Option<String>,
+    pub issue_date: Option<NaiveDate>,
+    pub due_date: Option<NaiveDate>,
+    #[serde(default, deserialize_with = "patch_field")]
+    pub payment_terms: Option<Option<PaymentTerms>>,
+    // A different client replaces bill_to and the buyer details unless they're given too;
+    // issued invoices keep their client
+    #[serde(default, deserialize_with = "patch_field")]
+    pub client_id: Option<Option<String>>,
+    #[serde(default, deserialize_with = "patch_field")]
+    pub bill_to: Option<Option<String>>,
+    #[serde(default, deserialize_with = "patch_field")]
+    pub bill_to_email: Option<Option<String>>,
+    pub items: Option<String>,
+    #[serde(default, deserialize_with = "patch_field")]
+    pub notes: Option<Option<String>>,
+}
+
+impl InvoicePatch {
+    pub fn apply(self, invoice: &mut Invoice) {
+        if let Some(project_id) = self.project_id {
+            invoice.project_id = project_id.filter(|s| !s.is_empty());
+        }
+        if let Some(invoice_number) = self.invoice_number {
+            invoice.invoice_number = invoice_number;
+        }
+        if let Some(amount) = self.amount {
+            invoice.amount = amount;
+        }
+        if let Some(status) = self.status {
+            invoice.status = status;
+        }
+        if let Some(issue_date) = self.issue_date {
+            invoice.issue_date = issue_date;
+        }
+        if let Some(payment_terms) = self.payment_terms {
+            invoice.payment_terms = payment_terms;
+        }
+        // Terms move the due date along with the issue date unless one is given
+        match (self.due_date, &invoice.payment_terms) {
+            (Some(due_date), _) => invoice.due_date = due_date,
+            (None, Some(terms)) => invoice.due_date = terms.due_date(invoice.issue_date),
+            (None, None) => {}
+        }
+        if let Some(client_id) = self.client_id {
+            invoice.client_id = client_id.filter(|s| !s.is_empty());
+        }
+        if let Some(bill_to) = self.bill_to {
+            invoice.bill_to = bill_to;
+        }
+        if let Some(bill_to_email) = self.bill_to_email {
+            invoice.bill_to_email = bill_to_email.map(|e| e.trim().to_string()).filter(|e| !e.is_empty());
+        }
+        if let Some(items) = self.items {
+            invoice.items = items;
+        }
+        if let Some(notes) = self.notes {
+            invoice.notes = notes;
+        }
+    }
+}
+
+impl Invoice {
+    pub fn validate(&self) -> Vec<String> {
+        let mut errors = validate_invoice(
+            &self.artist_id,
+            &self.invoice_number,
+            self.amount,
+            Some(&self.currency),
+            self.bill_to_email.as_deref(),
+            &self.items,
+            [("bill_to", self.bill_to.as_deref(), MAX_TEXT), ("notes", self.notes.as_deref(), MAX_NOTES)],
+        );
+        errors.extend(validate_due_date(self.issue_date, Some(self.due_date), self.payment_terms.as_ref()));
+        errors
+    }
+
+    // Drafts haven't reached the client yet and still follow its details; pending
+    // is the status of an outstanding invoice that has been sent
+    pub fn is_issued(&self) -> bool {
+        self.status != "draft"
+    }
+}
+
+// Currency and VAT details used for tax totals and e-invoices
+#[derive(Debug, Clone, Deserialize)]
+pub struct InvoiceTaxDetails {
+    // ISO 4217 code
+    pub currency: String,
+    pub tax_rate: Option<f64>,
+    pub tax_category: Option<String>,
+    pub buyer_tax_id: Option<String>,
+    pub buyer_country_code: Option<String>,
+    pub buyer_peppol_id: Option<String>,
+}
+
+impl InvoiceTaxDetails {
+    pub fn validate(&self) -> Vec<String> {
+        let mut v = Validator::new();
+        let currency = self.currency.trim();
+        if currency.len() != 3 || !currency.chars().all(|c| c.is_ascii_alphabetic()) {
+            v.error(format!("currency '{}' is not a three-letter ISO 4217 code", currency));
+        }
+        if let Some(rate) = self.tax_rate {
+            v.non_negative("tax_rate", rate);
+        }
+        validate_country_code(&mut v, "buyer_country_code", self.buyer_country_code.as_deref());
+        v.max_length("buyer_tax_id", self.buyer_tax_id.as_deref(), MAX_SHORT);
+        v.max_length("buyer_peppol_id", self.buyer_peppol_id.as_deref(), MAX_SHORT);
+        v.into_errors()
+    }
+}
+
+// Selects invoices for batch operations; unset fields match every invoice
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct InvoiceFilter {
+    pub artist_id: Option<String>,
+    pub project_id: Option<String>,
+    #[serde(default)]
+    pub statuses: Vec<String>,
+    // Inclusive issue date range
+    pub issued_from: Option<NaiveDate>,
+    pub issued_to: Option<NaiveDate>,
+}
+
+impl InvoiceFilter {
+    pub fn matches(&self, invoice: &Invoice) -> bool {
+        self.artist_id.as_ref().is_none_or(|id| &invoice.artist_id == id)
+            && self.project_id.as_ref().is_none_or(|id| invoice.project_id.as_ref() == Some(id))
+            && (self.statuses.is_empty() || self.statuses.contains(&invoice.status))
+            && self.issued_from.is_none_or(|from| invoice.issue_date >= from)
+            && self.issued_to.is_none_or(|to| invoice.issue_date <= to)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BrandingProfile {
+    pub id: String,
+    // None for the sending entity's default profile
+    pub artist_id: Option<String>,
+    pub name: String,
+    pub logo_path: Option<String>,
+    pub accent_color: Option<String>,
+    pub font_path: Option<String>,
+    pub footer_text: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+// Input for create_branding_profile; omitted optional fields are left empty
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct NewBrandingProfile {
+    pub artist_id: Option<String>,
+    pub name: String,
+    pub logo_path: Option<String>,
+    pub accent_color: Option<String>,
+    pub font_path: Option<String>,
+    pub footer_text: Option<String>,
+}
+
+// Changes for update_branding_profile; see ArtistPatch
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct BrandingProfilePatch {
+    pub name: Option<String>,
+    #[serde(default, deserialize_with = "patch_field")]
+    pub logo_path: Option<Option<String>>,
+    #[serde(default, deserialize_with = "patch_field")]
+    pub accent_color: Option<Option<String>>,
+    #[serde(default, deserialize_with = "patch_field")]
+    pub font_path: Option<Option<String>>,
+    #[serde(default, deserialize_with = "patch_field")]
+    pub footer_text: Option<Option<String>>,
+}
+
+impl BrandingProfilePatch {
+    pub fn apply(self, profile: &mut BrandingProfile) {
+        if let Some(name) = self.name {
+            profile.name = name;
+        }
+        if let Some(logo_path) = self.logo_path {
+            profile.logo_path = logo_path;
+        }
+        if let Some(accent_color) = self.accent_color {
+            profile.accent_color = accent_color;
+        }
+        if let Some(font_path) = self.font_path {
+            profile.font_path = font_path;
+        }
+        if let Some(footer_text) = self.footer_text {
+            profile.footer_text = footer_text;
+        }
+    }
+}
+
+// A payment reminder email, logged whether or not it could be sent
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InvoiceReminder {
+    pub id: String,
+    pub invoice_id: String,
+    // The schedule step, in days from the due date
+    pub offset_days: i64,
+    pub recipient: String,
+    pub subject: String,
+    // Why sending failed; None when the reminder went out
+    pub error: Option<String>,
+    // When the reminder was sent or attempted
+    pub created_at: DateTime<Utc>,
+}
+
+// An invoice emailed with send_invoice; the message itself is kept as EML
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InvoiceEmail {
+    pub id: String,
+    pub invoice_id: String,
+    // Comma-separated addresses
+    pub to_addresses: String,
+    pub cc_addresses: Option<String>,
+    pub bcc_addresses: Option<String>,
+    pub subject: String,
+    pub created_at: DateTime<Utc>,
+}
+
+// Stored in PRAGMA user_version by init(); bump it when init() changes the schema
+pub const SCHEMA_VERSION: i64 = 6;
+
+// Lets a test keep settings, workspaces and snapshots in a scratch folder; per thread,
+// so tests running in parallel don't share it
+#[cfg(test)]
+thread_local! {
+    pub static TEST_DATA_DIR: std::cell::RefCell<Option<PathBuf>> = const { std::cell::RefCell::new(None) };
+}
+
+pub fn get_data_dir() -> PathBuf {
+    #[cfg(test)]
+    if let Some(dir) = TEST_DATA_DIR.with(|dir| dir.borrow().clone()) {
+        return dir;
+    }
     let mut path = dirs::data_dir().expect("Failed to get data directory");
     path.push("project-invoicer");
     std::fs::create_dir_all(&path).expect("Failed to create data directory");
-    path.push("database.db");
     path
 }
 
-// Connection pool configuration
-lazy_static! {
-    static ref CONNECTION_POOL: Pool<SqliteConnectionManager> = {
-        let manager = SqliteConnectionManager::file(get_db_path());
-        Pool::builder()
-            .max_size(10) // Maximum 10 connections
+// Connections per file-backed pool, which also bounds concurrent `Database::run` jobs
+const MAX_CONNECTIONS: u32 = 10;
+
+// The database used until another workspace is opened
+pub fn default_db_path() -> PathBuf {
+    get_data_dir().join("database.db")
+}
+
+/// Handle to the open database. Tauri manages one for the app and commands
+/// receive it as `State<Database>`; clones share the same pool, so switching
+/// workspaces on one switches all of them.
+#[derive(Clone)]
+pub struct Database {
+    // Err holds why no database could be opened
+    active: Arc<RwLock<std::result::Result<ActiveDatabase, String>>>,
+    // Limits how many blocking jobs wait on the pool at once
+    permits: Arc<Semaphore>,
+}
+
+struct ActiveDatabase {
+    // None for in-memory databases
+    path: Option<PathBuf>,
+    pool: Pool<SqliteConnectionManager>,
+}
+
+impl ActiveDatabase {
+    fn open(path: &Path) -> Result<ActiveDatabase> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| rusqlite::Error::ToSqlConversionFailure(e.into()))?;
+        }
+        // Connection pool configuration
+        let pool = Pool::builder()
+            .max_size(MAX_CONNECTIONS)
             .min_idle(Some(2)) // Keep at least 2 idle connections
             .connection_timeout(std::time::Duration::from_secs(30))
-            .build(manager)
-            .expect("Failed to create connection pool")
-    };
+            .build(SqliteConnectionManager::file(path).with_init(enable_foreign_keys))
+            .map_err(pool_error)?;
+        init(&*pool.get().map_err(pool_error)?)?;
+        Ok(ActiveDatabase { path: Some(path.to_path_buf()), pool })
+    }
+}
+
+// SQLite leaves foreign keys off per connection, which would skip the
+// ON DELETE CASCADE / SET NULL rules in the schema
+fn enable_foreign_keys(conn: &mut Connection) -> Result<()> {
+    conn.execute_batch("PRAGMA foreign_keys = ON")
+}
+
+fn pool_error(e: r2d2::Error) -> rusqlite::Error {
+    rusqlite::Error::SqliteFailure(
+        rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_BUSY),
+        Some(format!("Connection pool error: {}", e))
+    )
 }
 
-fn get_connection() -> Result<PooledConnection<SqliteConnectionManager>> {
-    CONNECTION_POOL
-        .get()
-        .map_err(|e| rusqlite::Error::SqliteFailure(
-            rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_BUSY),
-            Some(format!("Connection pool error: {}", e))
-        ))
+impl Database {
+    /// Opens the database file at `path`, creating and migrating it as needed.
+    pub fn open(path: &Path) -> Result<Database> {
+        let active = ActiveDatabase::open(path)?;
+        Ok(Database::new(Ok(active), MAX_CONNECTIONS))
+    }
+
+    /// A fresh database that lives only as long as this handle, e.g. for tests.
+    pub fn in_memory() -> Result<Database> {
+        // A single connection that is never recycled, since each in-memory
+        // connection would otherwise see its own empty database
+        let pool = Pool::builder()
+            .max_size(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
+            .build(SqliteConnectionManager::memory().with_init(enable_foreign_keys))
+            .map_err(pool_error)?;
+        init(&*pool.get().map_err(pool_error)?)?;
+        Ok(Database::new(Ok(ActiveDatabase { path: None, pool }), 1))
+    }
+
+    /// A handle for when no database could be opened at startup. Every query
+    /// fails with `error` until another database is switched to.
+    pub fn unavailable(error: String) -> Database {
+        Database::new(Err(error), MAX_CONNECTIONS)
+    }
+
+    fn new(active: std::result::Result<ActiveDatabase, String>, max_jobs: u32) -> Database {
+        Database {
+            active: Arc::new(RwLock::new(active)),
+            permits: Arc::new(Semaphore::new(max_jobs as usize)),
+        }
+    }
+
+    /// Why no database is open, if that's the case.
+    pub fn error(&self) -> Option<String> {
+        self.active.read().unwrap().as_ref().err().cloned()
+    }
+
+    /// The open database file; None for in-memory databases.
+    pub fn path(&self) -> Option<PathBuf> {
+        self.active.read().unwrap().as_ref().ok().and_then(|active| active.path.clone())
+    }
+
+    /// Opens and migrates the database at `path` in place of the current one.
+    /// The current database stays open when this fails.
+    pub fn switch_to(&self, path: &Path) -> Result<()> {
+        let active = ActiveDatabase::open(path)?;
+        *self.active.write().unwrap() = Ok(active);
+        Ok(())
+    }
+
+    pub fn connection(&self) -> Result<PooledConnection<SqliteConnectionManager>> {
+        // Cloning the pool lets the lock go before waiting for a connection
+        let pool = match self.active.read().unwrap().as_ref() {
+            Ok(active) => active.pool.clone(),
+            Err(error) => {
+                return Err(rusqlite::Error::SqliteFailure(
+                    rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_CANTOPEN),
+                    Some(format!("Database unavailable: {}", error)),
+                ))
+            }
+        };
+        pool.get().map_err(pool_error)
+    }
+
+    /// Runs `f` on the blocking thread pool so queries never stall the async
+    /// runtime. Jobs beyond the pool size wait here rather than on a thread.
+    pub async fn run<T, E, F>(&self, f: F) -> std::result::Result<T, String>
+    where
+        F: FnOnce(&Database) -> std::result::Result<T, E> + Send + 'static,
+        T: Send + 'static,
+        E: ToString,
+    {
+        let permit = self.permits.clone().acquire_owned().await.map_err(|e| e.to_string())?;
+        let db = self.clone();
+        tokio::task::spawn_blocking(move || {
+            let _permit = permit;
+            f(&db).map_err(|e| e.to_string())
+        })
+        .await
+        .map_err(|e| e.to_string())?
+    }
+
+    // Runs `f` in a single transaction, rolled back when it returns an error
+    pub fn with_transaction<T>(&self, f: impl FnOnce(&Connection) -> Result<T>) -> Result<T> {
+        let mut conn = self.connection()?;
+        let tx = conn.transaction()?;
+        let value = f(&tx)?;
+        tx.commit()?;
+        Ok(value)
+    }
+
+    // Migrates the open database, e.g. after a snapshot of an older schema was restored
+    pub fn migrate(&self) -> Result<()> {
+        init(&*self.connection()?)
+    }
 }
 
-pub fn init() -> Result<()> {
-    // Initialize the database using a direct connection for setup
-    let conn = Connection::open(get_db_path())?;
+fn init(conn: &Connection) -> Result<()> {
+    // Keep a copy of existing databases before changing their schema
+    let schema_version: i64 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    let has_tables: bool = conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table')",
+        [],
+        |row| row.get(0),
+    )?;
+    if has_tables && schema_version < SCHEMA_VERSION {
+        crate::snapshots::snapshot_connection(conn, crate::snapshots::PRE_MIGRATION)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(e.into()))?;
+    }
     
     // Create artists table with new fields
     conn.execute(
@@ -99,6 +1023,19 @@ pub fn init() -> Result<()> {
             address TEXT,
             wire_details TEXT,
             notes TEXT,
+            pdf_template TEXT,
+            tax_id TEXT,
+            country_code TEXT,
+            account_holder TEXT,
+            iban TEXT,
+            bic TEXT,
+            routing_number TEXT,
+            account_number TEXT,
+            payment_qr TEXT,
+            payment_uri TEXT,
+            peppol_id TEXT,
+            payment_terms TEXT,
+            reminder_schedule TEXT,
             created_at TEXT NOT NULL,
             updated_at TEXT NOT NULL
         )",
@@ -117,6 +1054,17 @@ pub fn init() -> Result<()> {
     if !columns.contains(&"wire_details".to_string()) {
         conn.execute("ALTER TABLE artists ADD COLUMN wire_details TEXT", [])?;
     }
+    if !columns.contains(&"pdf_template".to_string()) {
+        conn.execute("ALTER TABLE artists ADD COLUMN pdf_template TEXT", [])?;
+    }
+    for column in [
+        "tax_id", "country_code", "account_holder", "iban", "bic", "routing_number", "account_number", "payment_qr",
+        "payment_uri", "peppol_id", "payment_terms", "reminder_schedule",
+    ] {
+        if !columns.contains(&column.to_string()) {
+            conn.execute(&format!("ALTER TABLE artists ADD COLUMN {} TEXT", column), [])?;
+        }
+    }
     
     // Create projects table
     conn.execute(
@@ -136,6 +1084,31 @@ pub fn init() -> Result<()> {
         [],
     )?;
     
+    // Clients billed on invoices
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS clients (
+            id TEXT PRIMARY KEY,
+            legal_name TEXT NOT NULL,
+            contact_name TEXT,
+            email TEXT,
+            cc_emails TEXT,
+            address_line1 TEXT,
+            address_line2 TEXT,
+            postal_code TEXT,
+            city TEXT,
+            region TEXT,
+            country_code TEXT,
+            tax_id TEXT,
+            peppol_id TEXT,
+            currency TEXT,
+            payment_terms TEXT,
+            notes TEXT,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+    
     // Create invoices table with bill_to field
     conn.execute(
         "CREATE TABLE IF NOT EXISTS invoices (
@@ -151,6 +1124,20 @@ pub fn init() -> Result<()> {
             bill_to TEXT,
             items TEXT NOT NULL DEFAULT '[]',
             notes TEXT,
+            pdf_template TEXT,
+            payment_reference TEXT,
+            currency TEXT NOT NULL DEFAULT 'USD',
+            tax_rate REAL,
+            tax_category TEXT,
+            buyer_tax_id TEXT,
+            buyer_country_code TEXT,
+            buyer_peppol_id TEXT,
+            document_type TEXT NOT NULL DEFAULT 'invoice',
+            credited_invoice_id TEXT REFERENCES invoices (id) ON DELETE SET NULL,
+            payment_terms TEXT,
+            bill_to_email TEXT,
+            sent_at TEXT,
+            client_id TEXT REFERENCES clients (id) ON DELETE SET NULL,
             created_at TEXT NOT NULL,
             updated_at TEXT NOT NULL,
             FOREIGN KEY (artist_id) REFERENCES artists (id) ON DELETE CASCADE,
@@ -168,6 +1155,86 @@ pub fn init() -> Result<()> {
     if !invoice_columns.contains(&"bill_to".to_string()) {
         conn.execute("ALTER TABLE invoices ADD COLUMN bill_to TEXT", [])?;
     }
+    if !invoice_columns.contains(&"pdf_template".to_string()) {
+        conn.execute("ALTER TABLE invoices ADD COLUMN pdf_template TEXT", [])?;
+    }
+    if !invoice_columns.contains(&"payment_reference".to_string()) {
+        conn.execute("ALTER TABLE invoices ADD COLUMN payment_reference TEXT", [])?;
+    }
+    if !invoice_columns.contains(&"currency".to_string()) {
+        conn.execute("ALTER TABLE invoices ADD COLUMN currency TEXT NOT NULL DEFAULT 'USD'", [])?;
+    }
+    if !invoice_columns.contains(&"tax_rate".to_string()) {
+        conn.execute("ALTER TABLE invoices ADD COLUMN tax_rate REAL", [])?;
+    }
+    for column in [
+        "tax_category", "buyer_tax_id", "buyer_country_code", "buyer_peppol_id", "payment_terms", "bill_to_email",
+        "sent_at",
+    ] {
+        if !invoice_columns.contains(&column.to_string()) {
+            conn.execute(&format!("ALTER TABLE invoices ADD COLUMN {} TEXT", column), [])?;
+        }
+    }
+    if !invoice_columns.contains(&"document_type".to_string()) {
+        conn.execute("ALTER TABLE invoices ADD COLUMN document_type TEXT NOT NULL DEFAULT 'invoice'", [])?;
+    }
+    if !invoice_columns.contains(&"credited_invoice_id".to_string()) {
+        conn.execute(
+            "ALTER TABLE invoices ADD COLUMN credited_invoice_id TEXT REFERENCES invoices (id) ON DELETE SET NULL",
+            [],
+        )?;
+    }
+    if !invoice_columns.contains(&"client_id".to_string()) {
+        conn.execute("ALTER TABLE invoices ADD COLUMN client_id TEXT REFERENCES clients (id) ON DELETE SET NULL", [])?;
+    }
+    
+    // Create branding profiles table (artist_id NULL = default profile)
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS branding_profiles (
+            id TEXT PRIMARY KEY,
+            artist_id TEXT UNIQUE,
+            name TEXT NOT NULL,
+            logo_path TEXT,
+            accent_color TEXT,
+            font_path TEXT,
+            footer_text TEXT,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL,
+            FOREIGN KEY (artist_id) REFERENCES artists (id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    
+    // Payment reminders sent, or attempted when `error` is set
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS invoice_reminders (
+            id TEXT PRIMARY KEY,
+            invoice_id TEXT NOT NULL,
+            offset_days INTEGER NOT NULL,
+            recipient TEXT NOT NULL,
+            subject TEXT NOT NULL,
+            error TEXT,
+            created_at TEXT NOT NULL,
+            FOREIGN KEY (invoice_id) REFERENCES invoices (id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    
+    // Invoices emailed from the app, with the message as sent
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS invoice_emails (
+            id TEXT PRIMARY KEY,
+            invoice_id TEXT NOT NULL,
+            to_addresses TEXT NOT NULL,
+            cc_addresses TEXT,
+            bcc_addresses TEXT,
+            subject TEXT NOT NULL,
+            message TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            FOREIGN KEY (invoice_id) REFERENCES invoices (id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
     
     // Create indexes for better performance
     conn.execute("CREATE INDEX IF NOT EXISTS idx_projects_artist_id ON projects(artist_id)", [])?;
@@ -179,97 +1246,272 @@ pub fn init() -> Result<()> {
     conn.execute("CREATE INDEX IF NOT EXISTS idx_invoices_status ON invoices(status)", [])?;
     conn.execute("CREATE INDEX IF NOT EXISTS idx_invoices_created_at ON invoices(created_at DESC)", [])?;
     conn.execute("CREATE INDEX IF NOT EXISTS idx_invoices_due_date ON invoices(due_date)", [])?;
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_invoices_client_id ON invoices(client_id)", [])?;
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_invoice_reminders_invoice_id ON invoice_reminders(invoice_id)", [])?;
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_invoice_emails_invoice_id ON invoice_emails(invoice_id)", [])?;
+    
+    // Version 2 reads dates and timestamps as chrono types
+    if schema_version < 2 {
+        normalize_dates(conn)?;
+    }
+    
+    conn.pragma_update(None, "user_version", SCHEMA_VERSION)?;
     
     Ok(())
 }
 
-// Artist functions remain the same...
-pub fn get_all_artists() -> Result<Vec<Artist>> {
-    let conn = get_connection()?;
-    let mut stmt = conn.prepare(
-        "SELECT id, name, company_name, email, phone, address, wire_details, notes, created_at, updated_at 
-         FROM artists ORDER BY name"
-    )?;
-    
-    let artists = stmt.query_map([], |row| {
-        Ok(Artist {
-            id: row.get(0)?,
-            name: row.get(1)?,
-            company_name: row.get(2)?,
-            email: row.get(3)?,
-            phone: row.get(4)?,
-            address: row.get(5)?,
-            wire_details: row.get(6)?,
-            notes: row.get(7)?,
-            created_at: row.get(8)?,
-            updated_at: row.get(9)?,
-        })
-    })?
-    .collect::<Result<Vec<_>>>()?;
-    
-    Ok(artists)
-}
-
-pub fn create_artist(
-    name: String,
-    company_name: Option<String>,
-    email: Option<String>,
-    phone: Option<String>,
-    address: Option<String>,
-    wire_details: Option<String>,
-    notes: Option<String>,
-) -> Result<Artist> {
-    let conn = get_connection()?;
-    let id = Uuid::new_v4().to_string();
-    let now = Utc::now().to_rfc3339();
-    
-    conn.execute(
-        "INSERT INTO artists (id, name, company_name, email, phone, address, wire_details, notes, created_at, updated_at) 
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
-        params![&id, &name, &company_name, &email, &phone, &address, &wire_details, &notes, &now, &now],
-    )?;
-    
-    Ok(Artist {
-        id,
-        name,
-        company_name,
-        email,
-        phone,
-        address,
-        wire_details,
-        notes,
-        created_at: now.clone(),
-        updated_at: now,
-    })
+// Calendar date columns, and whether they may be empty
+const DATE_COLUMNS: [(&str, &str, bool); 5] = [
+    ("projects", "start_date", true),
+    ("projects", "end_date", true),
+    ("invoices", "issue_date", false),
+    ("invoices", "due_date", false),
+    ("invoices", "paid_date", true),
+];
+
+const TIMESTAMP_TABLES: [&str; 4] = ["artists", "projects", "invoices", "branding_profiles"];
+
+/// Rewrites dates stored before they were typed as YYYY-MM-DD and timestamps
+/// as RFC 3339; blank optional dates become NULL. Values that can't be read
+/// are all listed in the error and nothing is rewritten, so they can be fixed
+/// by hand instead of being guessed at. Also run on restored backups, which
+/// may come from an older version.
+pub fn normalize_dates(conn: &Connection) -> Result<()> {
+    let dates = DATE_COLUMNS.iter().map(|&(table, column, optional)| (table, column, optional, false));
+    let timestamps = TIMESTAMP_TABLES
+        .iter()
+        .flat_map(|&table| [(table, "created_at", false, true), (table, "updated_at", false, true)]);
+
+    let mut updates = Vec::new();
+    let mut problems = Vec::new();
+    for (table, column, optional, timestamp) in dates.chain(timestamps) {
+        let normalize: fn(&str) -> Option<String> = if timestamp { normalize_timestamp } else { normalize_date };
+        let mut stmt = conn.prepare(&format!("SELECT id, {} FROM {} WHERE {} IS NOT NULL", column, table, column))?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?
+            .collect::<Result<Vec<_>>>()?;
+        for (id, value) in rows {
+            let normalized = match value.trim() {
+                "" if optional => None,
+                trimmed => match normalize(trimmed) {
+                    Some(normalized) => Some(normalized),
+                    None => {
+                        problems.push(format!("{} {} has an unreadable {} '{}'", table, id, column, value));
+                        continue;
+                    }
+                },
+            };
+            if normalized.as_deref() != Some(value.as_str()) {
+                updates.push((table, column, id, normalized));
+            }
+        }
+    }
+    check(problems)?;
+
+    for (table, column, id, value) in updates {
+        conn.execute(&format!("UPDATE {} SET {} = ?2 WHERE id = ?1", table, column), params![id, value])?;
+    }
+    Ok(())
 }
 
-pub fn update_artist(
-    artist_id: String,
-    name: String,
-    company_name: Option<String>,
-    email: Option<String>,
-    phone: Option<String>,
-    address: Option<String>,
-    wire_details: Option<String>,
-    notes: Option<String>,
-) -> Result<Artist> {
-    let conn = get_connection()?;
-    let now = Utc::now().to_rfc3339();
-    
-    conn.execute(
-        "UPDATE artists SET name = ?2, company_name = ?3, email = ?4, phone = ?5, address = ?6, 
-         wire_details = ?7, notes = ?8, updated_at = ?9 WHERE id = ?1",
-        params![&artist_id, &name, &company_name, &email, &phone, &address, &wire_details, &notes, &now],
-    )?;
+// Dates were entered as YYYY-MM-DD, but payments recorded a full timestamp;
+// those keep the day as written rather than the UTC day
+fn normalize_date(value: &str) -> Option<String> {
+    let date = NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .or_else(|_| NaiveDate::parse_from_str(value, "%Y/%m/%d"))
+        .ok()
+        .or_else(|| parse_stored_timestamp(value).map(|time| time.date_naive()))?;
+    Some(date.format("%Y-%m-%d").to_string())
+}
+
+fn normalize_timestamp(value: &str) -> Option<String> {
+    parse_stored_timestamp(value).map(|time| time.with_timezone(&Utc).to_rfc3339())
+}
+
+// RFC 3339, SQLite's "YYYY-MM-DD HH:MM:SS" with or without an offset, or a
+// bare date taken as midnight UTC
+fn parse_stored_timestamp(value: &str) -> Option<DateTime<FixedOffset>> {
+    let utc = |time: NaiveDateTime| time.and_utc().fixed_offset();
+    DateTime::parse_from_rfc3339(value)
+        .or_else(|_| DateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S%.f%:z"))
+        .or_else(|_| NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S%.f").map(utc))
+        .or_else(|_| NaiveDate::parse_from_str(value, "%Y-%m-%d").map(|date| utc(date.and_time(NaiveTime::MIN))))
+        .ok()
+}
+
+// Field checks behind the validate() methods; each returns every problem found
+fn validate_artist(
+    name: &str,
+    email: Option<&str>,
+    phone: Option<&str>,
+    text: [(&str, Option<&str>, usize); 4],
+) -> Vec<String> {
+    let mut v = Validator::new();
+    v.required("name", name, MAX_NAME);
+    v.email("email", email);
+    v.phone("phone", phone);
+    for (field, value, max) in text {
+        v.max_length(field, value, max);
+    }
+    v.into_errors()
+}
+
+fn validate_project(
+    artist_id: &str,
+    name: &str,
+    description: Option<&str>,
+    status: &str,
+    start_date: Option<NaiveDate>,
+    end_date: Option<NaiveDate>,
+    budget: f64,
+) -> Vec<String> {
+    let mut v = Validator::new();
+    if artist_id.trim().is_empty() {
+        v.error("artist is required".to_string());
+    }
+    v.required("name", name, MAX_NAME);
+    v.max_length("description", description, MAX_NOTES);
+    v.required("status", status, MAX_SHORT);
+    v.not_before("end_date", end_date, "start_date", start_date);
+    v.non_negative("budget", budget);
+    v.into_errors()
+}
+
+fn validate_invoice(
+    artist_id: &str,
+    invoice_number: &str,
+    amount: f64,
+    currency: Option<&str>,
+    bill_to_email: Option<&str>,
+    items: &str,
+    text: [(&str, Option<&str>, usize); 2],
+) -> Vec<String> {
+    let mut v = Validator::new();
+    if artist_id.trim().is_empty() {
+        v.error("artist is required".to_string());
+    }
+    v.required("invoice number", invoice_number, MAX_SHORT);
+    v.non_negative("amount", amount);
+    validate_currency(&mut v, "currency", currency);
+    v.email("bill_to_email", bill_to_email);
+    if serde_json::from_str::<Vec<serde_json::Value>>(items).is_err() {
+        v.error("line items must be a JSON array".to_string());
+    }
+    for (field, value, max) in text {
+        v.max_length(field, value, max);
+    }
+    v.into_errors()
+}
+
+// An invoice needs a due date or terms to compute it from; with both they have to agree
+fn validate_due_date(
+    issue_date: NaiveDate,
+    due_date: Option<NaiveDate>,
+    payment_terms: Option<&PaymentTerms>,
+) -> Vec<String> {
+    let mut v = Validator::new();
+    match (due_date, payment_terms) {
+        (None, None) => v.error("due_date or payment terms are required".to_string()),
+        (Some(due_date), Some(terms)) if due_date != terms.due_date(issue_date) => v.error(format!(
+            "due_date {} does not match the payment terms '{}', which give {}",
+            due_date,
+            terms.label(),
+            terms.due_date(issue_date)
+        )),
+        _ => {}
+    }
+    v.not_before("due_date", due_date, "issue_date", Some(issue_date));
+    for error in payment_terms.map(|terms| terms.validate(issue_date)).unwrap_or_default() {
+        v.error(error);
+    }
+    v.into_errors()
+}
+
+// ISO 3166-1 alpha-2, as used on e-invoices
+fn validate_country_code(v: &mut Validator, field: &str, value: Option<&str>) {
+    if let Some(code) = value.map(str::trim).filter(|c| !c.is_empty()) {
+        if code.len() != 2 || !code.chars().all(|c| c.is_ascii_alphabetic()) {
+            v.error(format!("{} '{}' is not a two-letter country code", field, code));
+        }
+    }
+}
+
+// ISO 4217
+fn validate_currency(v: &mut Validator, field: &str, value: Option<&str>) {
+    if let Some(code) = value.map(str::trim).filter(|c| !c.is_empty()) {
+        if code.len() != 3 || !code.chars().all(|c| c.is_ascii_alphabetic()) {
+            v.error(format!("{} '{}' is not a three-letter ISO 4217 code", field, code));
+        }
+    }
+}
+
+fn check(errors: Vec<String>) -> Result<()> {
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(rusqlite::Error::ToSqlConversionFailure(errors.join("; ").into()))
+    }
+}
+
+// Artist columns, in the order read by row_to_artist
+const ARTIST_COLUMNS: &str = "id, name, company_name, email, phone, address, wire_details, notes, 
+     created_at, updated_at, pdf_template, tax_id, country_code, account_holder, iban, bic, 
+     routing_number, account_number, payment_qr, payment_uri, peppol_id, payment_terms, reminder_schedule";
+
+fn row_to_artist(row: &rusqlite::Row) -> Result<Artist> {
+    Ok(Artist {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        company_name: row.get(2)?,
+        email: row.get(3)?,
+        phone: row.get(4)?,
+        address: row.get(5)?,
+        wire_details: row.get(6)?,
+        notes: row.get(7)?,
+        tax_id: row.get(11)?,
+        country_code: row.get(12)?,
+        peppol_id: row.get(20)?,
+        pdf_template: row.get(10)?,
+        payment_terms: row.get(21)?,
+        reminder_schedule: row.get(22)?,
+        bank_details: BankDetails {
+            account_holder: row.get(13)?,
+            iban: row.get(14)?,
+            bic: row.get(15)?,
+            routing_number: row.get(16)?,
+            account_number: row.get(17)?,
+            payment_qr: row.get(18)?,
+            payment_uri: row.get(19)?,
+        },
+        created_at: row.get(8)?,
+        updated_at: row.get(9)?,
+    })
+}
+
+fn query_artist(conn: &Connection, artist_id: &str) -> Result<Artist> {
+    conn.query_row(
+        &format!("SELECT {} FROM artists WHERE id = ?1", ARTIST_COLUMNS),
+        params![artist_id],
+        row_to_artist,
+    )
+}
+
+// Validates and inserts an artist on `conn`, which may be an import transaction
+pub fn insert_artist(conn: &Connection, artist: NewArtist) -> Result<Artist> {
+    check(artist.validate())?;
+    let NewArtist { name, company_name, email, phone, address, wire_details, notes } = artist;
+    let phone = phone.as_deref().and_then(validation::normalize_phone);
+    let id = Uuid::new_v4().to_string();
+    let now = Utc::now();
     
-    let created_at: String = conn.query_row(
-        "SELECT created_at FROM artists WHERE id = ?1",
-        params![&artist_id],
-        |row| row.get(0),
+    conn.execute(
+        "INSERT INTO artists (id, name, company_name, email, phone, address, wire_details, notes, created_at, updated_at) 
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?9)",
+        params![&id, &name, &company_name, &email, &phone, &address, &wire_details, &notes, &now.to_rfc3339()],
     )?;
     
     Ok(Artist {
-        id: artist_id,
+        id,
         name,
         company_name,
         email,
@@ -277,91 +1519,245 @@ pub fn update_artist(
         address,
         wire_details,
         notes,
-        created_at,
+        tax_id: None,
+        country_code: None,
+        peppol_id: None,
+        pdf_template: None,
+        payment_terms: None,
+        reminder_schedule: None,
+        bank_details: BankDetails::default(),
+        created_at: now,
         updated_at: now,
     })
 }
 
-pub fn delete_artist(artist_id: String) -> Result<()> {
-    let conn = get_connection()?;
-    conn.execute("DELETE FROM artists WHERE id = ?1", params![&artist_id])?;
-    Ok(())
-}
+// Artist functions remain the same...
+impl Database {
+    pub fn get_all_artists(&self) -> Result<Vec<Artist>> {
+        let conn = self.connection()?;
+        let mut stmt = conn.prepare(&format!("SELECT {} FROM artists ORDER BY name", ARTIST_COLUMNS))?;
 
-// Project functions remain the same...
-pub fn get_all_projects() -> Result<Vec<Project>> {
-    let conn = get_connection()?;
-    let mut stmt = conn.prepare(
-        "SELECT id, artist_id, name, description, status, start_date, end_date, 
-         budget, created_at, updated_at FROM projects ORDER BY created_at DESC"
-    )?;
-    
-    let projects = stmt.query_map([], |row| {
-        Ok(Project {
-            id: row.get(0)?,
-            artist_id: row.get(1)?,
-            name: row.get(2)?,
-            description: row.get(3)?,
-            status: row.get(4)?,
-            start_date: row.get(5)?,
-            end_date: row.get(6)?,
-            budget: row.get(7)?,
-            created_at: row.get(8)?,
-            updated_at: row.get(9)?,
+        let artists = stmt.query_map([], row_to_artist)?
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(artists)
+    }
+
+    pub fn get_artist(&self, artist_id: &str) -> Result<Artist> {
+        let conn = self.connection()?;
+        query_artist(&conn, artist_id)
+    }
+
+    pub fn create_artist(&self, artist: NewArtist) -> Result<Artist> {
+        let conn = self.connection()?;
+        insert_artist(&conn, artist)
+    }
+
+    pub fn update_artist(&self, artist_id: &str, patch: ArtistPatch) -> Result<Artist> {
+        self.with_transaction(|conn| {
+            let mut artist = query_artist(conn, artist_id)?;
+            patch.apply(&mut artist);
+            check(artist.validate())?;
+            artist.phone = artist.phone.as_deref().and_then(validation::normalize_phone);
+            artist.updated_at = Utc::now();
+
+            conn.execute(
+                "UPDATE artists SET name = ?2, company_name = ?3, email = ?4, phone = ?5, address = ?6, 
+                 wire_details = ?7, notes = ?8, updated_at = ?9 WHERE id = ?1",
+                params![
+                    &artist.id, &artist.name, &artist.company_name, &artist.email, &artist.phone,
+                    &artist.address, &artist.wire_details, &artist.notes, &artist.updated_at.to_rfc3339()
+                ],
+            )?;
+            Ok(artist)
         })
-    })?
-    .collect::<Result<Vec<_>>>()?;
-    
-    Ok(projects)
+    }
+
+    pub fn set_artist_bank_details(&self, artist_id: String, bank_details: BankDetails) -> Result<()> {
+        check(bank_details.validate())?;
+        let conn = self.connection()?;
+        let clean = |value: &Option<String>| value.as_deref().map(str::trim).filter(|s| !s.is_empty()).map(str::to_string);
+        // IBANs are often copied with spaces; store them compact and upper-case
+        let iban = clean(&bank_details.iban).map(|iban| iban.replace(' ', "").to_uppercase());
+        let bic = clean(&bank_details.bic).map(|bic| bic.to_uppercase());
+
+        conn.execute(
+            "UPDATE artists SET account_holder = ?2, iban = ?3, bic = ?4, routing_number = ?5, 
+             account_number = ?6, payment_qr = ?7, payment_uri = ?8, updated_at = ?9 WHERE id = ?1",
+            params![
+                &artist_id, &clean(&bank_details.account_holder), &iban, &bic,
+                &clean(&bank_details.routing_number), &clean(&bank_details.account_number),
+                &clean(&bank_details.payment_qr), &clean(&bank_details.payment_uri), &Utc::now().to_rfc3339()
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn set_artist_tax_details(&self, artist_id: String, tax_details: ArtistTaxDetails) -> Result<()> {
+        check(tax_details.validate())?;
+        let conn = self.connection()?;
+        let tax_id = tax_details.tax_id.map(|s| s.trim().to_string()).filter(|s| !s.is_empty());
+        let country_code = tax_details.country_code.map(|s| s.trim().to_uppercase()).filter(|s| !s.is_empty());
+        let peppol_id = tax_details.peppol_id.map(|s| s.trim().to_string()).filter(|s| !s.is_empty());
+
+        conn.execute(
+            "UPDATE artists SET tax_id = ?2, country_code = ?3, peppol_id = ?4, updated_at = ?5 WHERE id = ?1",
+            params![&artist_id, &tax_id, &country_code, &peppol_id, &Utc::now().to_rfc3339()],
+        )?;
+        Ok(())
+    }
+
+    pub fn delete_artist(&self, artist_id: String) -> Result<()> {
+        let conn = self.connection()?;
+        conn.execute("DELETE FROM artists WHERE id = ?1", params![&artist_id])?;
+        Ok(())
+    }
 }
 
-pub fn get_projects_by_artist(artist_id: String) -> Result<Vec<Project>> {
-    let conn = get_connection()?;
-    let mut stmt = conn.prepare(
-        "SELECT id, artist_id, name, description, status, start_date, end_date, 
-         budget, created_at, updated_at FROM projects 
-         WHERE artist_id = ?1 ORDER BY created_at DESC"
-    )?;
-    
-    let projects = stmt.query_map(params![&artist_id], |row| {
-        Ok(Project {
-            id: row.get(0)?,
-            artist_id: row.get(1)?,
-            name: row.get(2)?,
-            description: row.get(3)?,
-            status: row.get(4)?,
-            start_date: row.get(5)?,
-            end_date: row.get(6)?,
-            budget: row.get(7)?,
-            created_at: row.get(8)?,
-            updated_at: row.get(9)?,
+
+// Client columns, in the order read by row_to_client
+const CLIENT_COLUMNS: &str = "id, legal_name, contact_name, email, cc_emails, address_line1, address_line2, 
+     postal_code, city, region, country_code, tax_id, peppol_id, currency, payment_terms, notes, 
+     created_at, updated_at";
+
+fn row_to_client(row: &rusqlite::Row) -> Result<Client> {
+    Ok(Client {
+        id: row.get(0)?,
+        legal_name: row.get(1)?,
+        contact_name: row.get(2)?,
+        email: row.get(3)?,
+        cc_emails: row.get(4)?,
+        address_line1: row.get(5)?,
+        address_line2: row.get(6)?,
+        postal_code: row.get(7)?,
+        city: row.get(8)?,
+        region: row.get(9)?,
+        country_code: row.get(10)?,
+        tax_id: row.get(11)?,
+        peppol_id: row.get(12)?,
+        currency: row.get(13)?,
+        payment_terms: row.get(14)?,
+        notes: row.get(15)?,
+        created_at: row.get(16)?,
+        updated_at: row.get(17)?,
+    })
+}
+
+fn query_client(conn: &Connection, client_id: &str) -> Result<Client> {
+    conn.query_row(
+        &format!("SELECT {} FROM clients WHERE id = ?1", CLIENT_COLUMNS),
+        params![client_id],
+        row_to_client,
+    )
+}
+
+impl Database {
+    pub fn get_all_clients(&self) -> Result<Vec<Client>> {
+        let conn = self.connection()?;
+        let mut stmt = conn.prepare(&format!("SELECT {} FROM clients ORDER BY legal_name", CLIENT_COLUMNS))?;
+        let clients = stmt.query_map([], row_to_client)?.collect::<Result<Vec<_>>>()?;
+        Ok(clients)
+    }
+
+    pub fn get_client(&self, client_id: &str) -> Result<Client> {
+        let conn = self.connection()?;
+        query_client(&conn, client_id)
+    }
+
+    pub fn create_client(&self, client: NewClient) -> Result<Client> {
+        let mut client = client.into_client();
+        check(client.validate())?;
+        client.normalize();
+
+        let conn = self.connection()?;
+        conn.execute(
+            &format!(
+                "INSERT INTO clients ({}) 
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?17)",
+                CLIENT_COLUMNS
+            ),
+            params![
+                &client.id, &client.legal_name, &client.contact_name, &client.email, &client.cc_emails,
+                &client.address_line1, &client.address_line2, &client.postal_code, &client.city, &client.region,
+                &client.country_code, &client.tax_id, &client.peppol_id, &client.currency, &client.payment_terms,
+                &client.notes, &client.created_at.to_rfc3339()
+            ],
+        )?;
+        Ok(client)
+    }
+
+    // Drafts billed to the client pick up the changes; issued invoices keep the
+    // details they were issued with
+    pub fn update_client(&self, client_id: &str, patch: ClientPatch) -> Result<Client> {
+        self.with_transaction(|conn| {
+            let mut client = query_client(conn, client_id)?;
+            patch.apply(&mut client);
+            check(client.validate())?;
+            client.normalize();
+            client.updated_at = Utc::now();
+
+            conn.execute(
+                "UPDATE clients SET legal_name = ?2, contact_name = ?3, email = ?4, cc_emails = ?5, 
+                 address_line1 = ?6, address_line2 = ?7, postal_code = ?8, city = ?9, region = ?10, 
+                 country_code = ?11, tax_id = ?12, peppol_id = ?13, currency = ?14, payment_terms = ?15, 
+                 notes = ?16, updated_at = ?17 WHERE id = ?1",
+                params![
+                    &client.id, &client.legal_name, &client.contact_name, &client.email, &client.cc_emails,
+                    &client.address_line1, &client.address_line2, &client.postal_code, &client.city, &client.region,
+                    &client.country_code, &client.tax_id, &client.peppol_id, &client.currency,
+                    &client.payment_terms, &client.notes, &client.updated_at.to_rfc3339()
+                ],
+            )?;
+            let mut stmt = conn.prepare(&format!("SELECT {} FROM invoices WHERE client_id = ?1", INVOICE_COLUMNS))?;
+            let invoices = stmt.query_map(params![&client.id], row_to_invoice)?.collect::<Result<Vec<_>>>()?;
+            for mut invoice in invoices.into_iter().filter(|i| !i.is_issued()) {
+                refresh_client_details(conn, &mut invoice, &client)?;
+            }
+            Ok(client)
         })
-    })?
-    .collect::<Result<Vec<_>>>()?;
-    
-    Ok(projects)
+    }
+
+    // Invoices billed to the client keep their copied details and lose the link
+    pub fn delete_client(&self, client_id: String) -> Result<()> {
+        let conn = self.connection()?;
+        conn.execute("DELETE FROM clients WHERE id = ?1", params![&client_id])?;
+        Ok(())
+    }
 }
 
-pub fn create_project(
-    artist_id: String,
-    name: String,
-    description: Option<String>,
-    status: String,
-    start_date: Option<String>,
-    end_date: Option<String>,
-    budget: f64,
-) -> Result<Project> {
-    let conn = get_connection()?;
+
+// Project columns, in the order read by row_to_project
+const PROJECT_COLUMNS: &str = "id, artist_id, name, description, status, start_date, end_date, 
+     budget, created_at, updated_at";
+
+fn row_to_project(row: &rusqlite::Row) -> Result<Project> {
+    Ok(Project {
+        id: row.get(0)?,
+        artist_id: row.get(1)?,
+        name: row.get(2)?,
+        description: row.get(3)?,
+        status: row.get(4)?,
+        start_date: row.get(5)?,
+        end_date: row.get(6)?,
+        budget: row.get(7)?,
+        created_at: row.get(8)?,
+        updated_at: row.get(9)?,
+    })
+}
+
+pub fn insert_project(conn: &Connection, project: NewProject) -> Result<Project> {
+    check(project.validate())?;
+    let NewProject { artist_id, name, description, status, start_date, end_date, budget } = project;
     let id = Uuid::new_v4().to_string();
-    let now = Utc::now().to_rfc3339();
+    let now = Utc::now();
     
     conn.execute(
         "INSERT INTO projects (id, artist_id, name, description, status, 
          start_date, end_date, budget, created_at, updated_at) 
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?9)",
         params![
             &id, &artist_id, &name, &description, &status,
-            &start_date, &end_date, &budget, &now, &now
+            &start_date, &end_date, &budget, &now.to_rfc3339()
         ],
     )?;
     
@@ -374,148 +1770,219 @@ pub fn create_project(
         start_date,
         end_date,
         budget,
-        created_at: now.clone(),
+        created_at: now,
         updated_at: now,
     })
 }
 
-pub fn update_project(
-    project_id: String,
-    name: String,
-    description: Option<String>,
-    status: String,
-    start_date: Option<String>,
-    end_date: Option<String>,
-    budget: f64,
-) -> Result<Project> {
-    let conn = get_connection()?;
-    let now = Utc::now().to_rfc3339();
-    
+// Project functions remain the same...
+impl Database {
+    pub fn get_all_projects(&self) -> Result<Vec<Project>> {
+        let conn = self.connection()?;
+        let mut stmt = conn.prepare(&format!("SELECT {} FROM projects ORDER BY created_at DESC", PROJECT_COLUMNS))?;
+
+        let projects = stmt.query_map([], row_to_project)?
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(projects)
+    }
+
+    pub fn get_projects_by_artist(&self, artist_id: String) -> Result<Vec<Project>> {
+        let conn = self.connection()?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM projects WHERE artist_id = ?1 ORDER BY created_at DESC",
+            PROJECT_COLUMNS
+        ))?;
+
+        let projects = stmt.query_map(params![&artist_id], row_to_project)?
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(projects)
+    }
+
+    pub fn create_project(&self, project: NewProject) -> Result<Project> {
+        let conn = self.connection()?;
+        insert_project(&conn, project)
+    }
+
+    pub fn update_project(&self, project_id: &str, patch: ProjectPatch) -> Result<Project> {
+        self.with_transaction(|conn| {
+            let mut project = conn.query_row(
+                &format!("SELECT {} FROM projects WHERE id = ?1", PROJECT_COLUMNS),
+                params![project_id],
+                row_to_project,
+            )?;
+            patch.apply(&mut project);
+            check(project.validate())?;
+            project.updated_at = Utc::now();
+
+            conn.execute(
+                "UPDATE projects SET name = ?2, description = ?3, status = ?4, 
+                 start_date = ?5, end_date = ?6, budget = ?7, updated_at = ?8 
+                 WHERE id = ?1",
+                params![
+                    &project.id, &project.name, &project.description, &project.status,
+                    &project.start_date, &project.end_date, &project.budget, &project.updated_at.to_rfc3339()
+                ],
+            )?;
+            Ok(project)
+        })
+    }
+
+    pub fn delete_project(&self, project_id: String) -> Result<()> {
+        let conn = self.connection()?;
+        conn.execute("DELETE FROM projects WHERE id = ?1", params![&project_id])?;
+        Ok(())
+    }
+}
+
+
+// Invoice columns, in the order read by row_to_invoice
+const INVOICE_COLUMNS: &str = "id, artist_id, project_id, invoice_number, amount, status, 
+     issue_date, due_date, paid_date, bill_to, items, notes, created_at, updated_at, pdf_template, 
+     payment_reference, currency, tax_rate, tax_category, buyer_tax_id, buyer_country_code, 
+     buyer_peppol_id, document_type, credited_invoice_id, payment_terms, bill_to_email, sent_at, 
+     client_id";
+
+// Invoices still waiting for payment; drafts, paid, archived and trashed invoices are left alone
+const OPEN_INVOICE: &str = "document_type = 'invoice' AND status IN ('pending', 'sent', 'overdue')";
+
+fn row_to_invoice(row: &rusqlite::Row) -> Result<Invoice> {
+    Ok(Invoice {
+        id: row.get(0)?,
+        artist_id: row.get(1)?,
+        project_id: row.get(2)?,
+        invoice_number: row.get(3)?,
+        amount: row.get(4)?,
+        status: row.get(5)?,
+        issue_date: row.get(6)?,
+        due_date: row.get(7)?,
+        payment_terms: row.get(24)?,
+        paid_date: row.get(8)?,
+        payment_reference: row.get(15)?,
+        sent_at: row.get(26)?,
+        currency: row.get(16)?,
+        tax_rate: row.get(17)?,
+        tax_category: row.get(18)?,
+        buyer_tax_id: row.get(19)?,
+        buyer_country_code: row.get(20)?,
+        buyer_peppol_id: row.get(21)?,
+        document_type: row.get(22)?,
+        credited_invoice_id: row.get(23)?,
+        client_id: row.get(27)?,
+        bill_to: row.get(9)?,
+        bill_to_email: row.get(25)?,
+        items: row.get(10)?,
+        notes: row.get(11)?,
+        pdf_template: row.get(14)?,
+        created_at: row.get(12)?,
+        updated_at: row.get(13)?,
+    })
+}
+
+fn query_invoice(conn: &Connection, invoice_id: &str) -> Result<Invoice> {
+    conn.query_row(
+        &format!("SELECT {} FROM invoices WHERE id = ?1", INVOICE_COLUMNS),
+        params![invoice_id],
+        row_to_invoice,
+    )
+}
+
+// The client details an invoice is issued with
+fn copy_client_details(invoice: &mut Invoice, client: &Client) {
+    invoice.bill_to = Some(client.bill_to());
+    invoice.bill_to_email = client.email.clone();
+    invoice.buyer_tax_id = client.tax_id.clone();
+    invoice.buyer_country_code = client.country_code.clone();
+    invoice.buyer_peppol_id = client.peppol_id.clone();
+}
+
+// Copies the client's current details onto an unissued invoice and saves them
+fn refresh_client_details(conn: &Connection, invoice: &mut Invoice, client: &Client) -> Result<()> {
+    copy_client_details(invoice, client);
     conn.execute(
-        "UPDATE projects SET name = ?2, description = ?3, status = ?4, 
-         start_date = ?5, end_date = ?6, budget = ?7, updated_at = ?8 
-         WHERE id = ?1",
+        "UPDATE invoices SET bill_to = ?2, bill_to_email = ?3, buyer_tax_id = ?4, buyer_country_code = ?5, 
+         buyer_peppol_id = ?6 WHERE id = ?1",
         params![
-            &project_id, &name, &description, &status,
-            &start_date, &end_date, &budget, &now
+            &invoice.id, &invoice.bill_to, &invoice.bill_to_email, &invoice.buyer_tax_id,
+            &invoice.buyer_country_code, &invoice.buyer_peppol_id
         ],
     )?;
-    
-    let (artist_id, created_at): (String, String) = conn.query_row(
-        "SELECT artist_id, created_at FROM projects WHERE id = ?1",
-        params![&project_id],
-        |row| Ok((row.get(0)?, row.get(1)?)),
-    )?;
-    
-    Ok(Project {
-        id: project_id,
-        artist_id,
-        name,
-        description,
-        status,
-        start_date,
-        end_date,
-        budget,
-        created_at,
-        updated_at: now,
-    })
-}
-
-pub fn delete_project(project_id: String) -> Result<()> {
-    let conn = get_connection()?;
-    conn.execute("DELETE FROM projects WHERE id = ?1", params![&project_id])?;
     Ok(())
 }
 
-// Updated Invoice functions with bill_to field
-pub fn get_all_invoices() -> Result<Vec<Invoice>> {
-    let conn = get_connection()?;
-    let mut stmt = conn.prepare(
-        "SELECT id, artist_id, project_id, invoice_number, amount, status, 
-         issue_date, due_date, paid_date, bill_to, items, notes, created_at, updated_at 
-         FROM invoices ORDER BY created_at DESC"
-    )?;
-    
-    let invoices = stmt.query_map([], |row| {
-        Ok(Invoice {
-            id: row.get(0)?,
-            artist_id: row.get(1)?,
-            project_id: row.get(2)?,
-            invoice_number: row.get(3)?,
-            amount: row.get(4)?,
-            status: row.get(5)?,
-            issue_date: row.get(6)?,
-            due_date: row.get(7)?,
-            paid_date: row.get(8)?,
-            bill_to: row.get(9)?,
-            items: row.get(10)?,
-            notes: row.get(11)?,
-            created_at: row.get(12)?,
-            updated_at: row.get(13)?,
-        })
-    })?
-    .collect::<Result<Vec<_>>>()?;
-    
-    Ok(invoices)
+fn is_blank(value: &Option<String>) -> bool {
+    value.as_deref().is_none_or(|s| s.trim().is_empty())
 }
 
-pub fn get_invoices_by_artist(artist_id: String) -> Result<Vec<Invoice>> {
-    let conn = get_connection()?;
-    let mut stmt = conn.prepare(
-        "SELECT id, artist_id, project_id, invoice_number, amount, status, 
-         issue_date, due_date, paid_date, bill_to, items, notes, created_at, updated_at 
-         FROM invoices WHERE artist_id = ?1 ORDER BY created_at DESC"
-    )?;
-    
-    let invoices = stmt.query_map(params![&artist_id], |row| {
-        Ok(Invoice {
-            id: row.get(0)?,
-            artist_id: row.get(1)?,
-            project_id: row.get(2)?,
-            invoice_number: row.get(3)?,
-            amount: row.get(4)?,
-            status: row.get(5)?,
-            issue_date: row.get(6)?,
-            due_date: row.get(7)?,
-            paid_date: row.get(8)?,
-            bill_to: row.get(9)?,
-            items: row.get(10)?,
-            notes: row.get(11)?,
-            created_at: row.get(12)?,
-            updated_at: row.get(13)?,
-        })
-    })?
-    .collect::<Result<Vec<_>>>()?;
-    
-    Ok(invoices)
+// The client an invoice is billed to; a missing one is reported like a validation error
+fn invoice_client(conn: &Connection, client_id: &str) -> Result<Client> {
+    query_client(conn, client_id)
+        .optional()?
+        .ok_or_else(|| rusqlite::Error::ToSqlConversionFailure(format!("client '{}' doesn't exist", client_id).into()))
 }
 
-pub fn create_invoice(
-    artist_id: String,
-    project_id: Option<String>,
-    invoice_number: String,
-    amount: f64,
-    status: String,
-    issue_date: String,
-    due_date: String,
-    bill_to: Option<String>,
-    items: String,
-    notes: Option<String>,
-) -> Result<Invoice> {
-    let conn = get_connection()?;
+pub fn insert_invoice(conn: &Connection, mut invoice: NewInvoice) -> Result<Invoice> {
+    let client = match invoice.client_id.as_deref().filter(|s| !s.is_empty()) {
+        Some(client_id) => Some(invoice_client(conn, client_id)?),
+        None => None,
+    };
+    // The client's details, which follow its edits until the invoice is issued
+    if let Some(client) = &client {
+        if is_blank(&invoice.bill_to) {
+            invoice.bill_to = Some(client.bill_to());
+        }
+        if is_blank(&invoice.bill_to_email) {
+            invoice.bill_to_email = client.email.clone();
+        }
+        if is_blank(&invoice.currency) {
+            invoice.currency = client.currency.clone();
+        }
+    }
+    // The client's default terms, else the artist's
+    if invoice.due_date.is_none() && invoice.payment_terms.is_none() {
+        invoice.payment_terms = match client.as_ref().and_then(|c| c.payment_terms.clone()) {
+            Some(terms) => Some(terms),
+            None => conn
+                .query_row("SELECT payment_terms FROM artists WHERE id = ?1", params![&invoice.artist_id], |row| {
+                    row.get(0)
+                })
+                .optional()?
+                .flatten(),
+        };
+    }
+    check(invoice.validate())?;
+    let NewInvoice {
+        artist_id, project_id, invoice_number, amount, status, issue_date, due_date, payment_terms, currency, bill_to,
+        bill_to_email, items, notes, ..
+    } = invoice;
     let id = Uuid::new_v4().to_string();
-    let now = Utc::now().to_rfc3339();
+    let now = Utc::now();
+    // validate() has checked that one of the two is set and that they agree
+    let due_date = match &payment_terms {
+        Some(terms) => terms.due_date(issue_date),
+        None => due_date.unwrap_or(issue_date),
+    };
     
     // Handle empty project_id
     let project_id_value = project_id.filter(|s| !s.is_empty());
+    let currency = currency.filter(|c| !c.trim().is_empty()).unwrap_or_else(default_currency).trim().to_uppercase();
+    let bill_to_email = bill_to_email.map(|e| e.trim().to_string()).filter(|e| !e.is_empty());
+    let client_id = client.as_ref().map(|c| c.id.clone());
+    let buyer_tax_id = client.as_ref().and_then(|c| c.tax_id.clone());
+    let buyer_country_code = client.as_ref().and_then(|c| c.country_code.clone());
+    let buyer_peppol_id = client.as_ref().and_then(|c| c.peppol_id.clone());
     
     conn.execute(
         "INSERT INTO invoices (id, artist_id, project_id, invoice_number, amount, 
-         status, issue_date, due_date, paid_date, bill_to, items, notes, created_at, updated_at) 
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+         status, issue_date, due_date, paid_date, bill_to, items, notes, currency, payment_terms, 
+         bill_to_email, client_id, buyer_tax_id, buyer_country_code, buyer_peppol_id, created_at, updated_at) 
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?20)",
         params![
             &id, &artist_id, &project_id_value, &invoice_number, &amount,
-            &status, &issue_date, &due_date, &None::<String>, &bill_to, &items, &notes, &now, &now
+            &status, &issue_date, &due_date, &None::<NaiveDate>, &bill_to, &items, &notes, &currency,
+            &payment_terms, &bill_to_email, &client_id, &buyer_tax_id, &buyer_country_code, &buyer_peppol_id,
+            &now.to_rfc3339()
         ],
     )?;
     
@@ -528,72 +1995,1207 @@ pub fn create_invoice(
         status,
         issue_date,
         due_date,
+        payment_terms,
         paid_date: None,
+        payment_reference: None,
+        sent_at: None,
+        currency,
+        tax_rate: None,
+        tax_category: None,
+        buyer_tax_id,
+        buyer_country_code,
+        buyer_peppol_id,
+        document_type: "invoice".to_string(),
+        credited_invoice_id: None,
+        client_id,
         bill_to,
+        bill_to_email,
         items,
         notes,
-        created_at: now.clone(),
+        pdf_template: None,
+        created_at: now,
         updated_at: now,
     })
 }
 
-pub fn update_invoice(
-    invoice_id: String,
-    invoice_number: String,
-    amount: f64,
-    status: String,
-    issue_date: String,
-    due_date: String,
-    bill_to: Option<String>,
-    items: String,
-    notes: Option<String>,
-) -> Result<Invoice> {
-    let conn = get_connection()?;
-    let now = Utc::now().to_rfc3339();
-    
-    // If status changed to paid, set paid_date
-    let paid_date = if status == "paid" {
-        Some(now.clone())
-    } else {
-        None
-    };
-    
-    conn.execute(
-        "UPDATE invoices SET invoice_number = ?2, amount = ?3, status = ?4, 
-         issue_date = ?5, due_date = ?6, paid_date = ?7, bill_to = ?8, items = ?9, 
-         notes = ?10, updated_at = ?11 WHERE id = ?1",
-        params![
-            &invoice_id, &invoice_number, &amount, &status,
-            &issue_date, &due_date, &paid_date, &bill_to, &items, &notes, &now
-        ],
-    )?;
-    
-    let (artist_id, project_id, created_at): (String, Option<String>, String) = conn.query_row(
-        "SELECT artist_id, project_id, created_at FROM invoices WHERE id = ?1",
-        params![&invoice_id],
-        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
-    )?;
-    
-    Ok(Invoice {
-        id: invoice_id,
-        artist_id,
-        project_id,
-        invoice_number,
-        amount,
-        status,
-        issue_date,
-        due_date,
-        paid_date,
-        bill_to,
-        items,
-        notes,
-        created_at,
-        updated_at: now,
+// The payment date of an imported invoice, which create_invoice leaves unset
+pub fn set_imported_paid_date(conn: &Connection, invoice_id: &str, paid_date: Option<NaiveDate>) -> Result<()> {
+    conn.execute("UPDATE invoices SET paid_date = ?2 WHERE id = ?1", params![invoice_id, &paid_date])?;
+    Ok(())
+}
+
+// Updated Invoice functions with bill_to field
+impl Database {
+    pub fn get_all_invoices(&self) -> Result<Vec<Invoice>> {
+        let conn = self.connection()?;
+        let mut stmt = conn.prepare(&format!("SELECT {} FROM invoices ORDER BY created_at DESC", INVOICE_COLUMNS))?;
+
+        let invoices = stmt.query_map([], row_to_invoice)?
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(invoices)
+    }
+
+    pub fn get_invoices_by_artist(&self, artist_id: String) -> Result<Vec<Invoice>> {
+        let conn = self.connection()?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM invoices WHERE artist_id = ?1 ORDER BY created_at DESC",
+            INVOICE_COLUMNS
+        ))?;
+
+        let invoices = stmt.query_map(params![&artist_id], row_to_invoice)?
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(invoices)
+    }
+
+    // Unpaid invoices, earliest due first; credit notes aren't receivables
+    pub fn get_open_invoices(&self) -> Result<Vec<Invoice>> {
+        let conn = self.connection()?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM invoices WHERE {} ORDER BY due_date",
+            INVOICE_COLUMNS, OPEN_INVOICE
+        ))?;
+        let invoices = stmt.query_map([], row_to_invoice)?.collect::<Result<Vec<_>>>()?;
+        Ok(invoices)
+    }
+
+    // Unpaid invoices due before `as_of`, earliest due first
+    pub fn get_overdue_invoices(&self, as_of: NaiveDate) -> Result<Vec<Invoice>> {
+        let conn = self.connection()?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM invoices WHERE due_date < ?1 AND {} ORDER BY due_date",
+            INVOICE_COLUMNS, OPEN_INVOICE
+        ))?;
+        let invoices = stmt.query_map(params![&as_of], row_to_invoice)?.collect::<Result<Vec<_>>>()?;
+        Ok(invoices)
+    }
+
+    // Sets unpaid invoices due before `as_of` to "overdue", and back to "sent" or "pending"
+    // once their due date has moved past it. Returns how many were newly marked overdue.
+    pub fn mark_overdue_invoices(&self, as_of: NaiveDate) -> Result<usize> {
+        self.with_transaction(|conn| {
+            let now = Utc::now().to_rfc3339();
+            let marked = conn.execute(
+                &format!(
+                    "UPDATE invoices SET status = 'overdue', updated_at = ?2 
+                     WHERE due_date < ?1 AND status != 'overdue' AND {}",
+                    OPEN_INVOICE
+                ),
+                params![&as_of, &now],
+            )?;
+            conn.execute(
+                "UPDATE invoices SET status = CASE WHEN sent_at IS NULL THEN 'pending' ELSE 'sent' END, 
+                 updated_at = ?2 WHERE due_date >= ?1 AND status = 'overdue'",
+                params![&as_of, &now],
+            )?;
+            Ok(marked)
+        })
+    }
+
+    pub fn get_invoice(&self, invoice_id: &str) -> Result<Invoice> {
+        let conn = self.connection()?;
+        query_invoice(&conn, invoice_id)
+    }
+
+    pub fn create_invoice(&self, invoice: NewInvoice) -> Result<Invoice> {
+        let conn = self.connection()?;
+        insert_invoice(&conn, invoice)
+    }
+
+    pub fn update_invoice(&self, invoice_id: &str, patch: InvoicePatch) -> Result<Invoice> {
+        self.with_transaction(|conn| {
+            let mut invoice = query_invoice(conn, invoice_id)?;
+            let (previous_client_id, was_issued) = (invoice.client_id.clone(), invoice.is_issued());
+            let (keep_bill_to, keep_bill_to_email) = (patch.bill_to.is_some(), patch.bill_to_email.is_some());
+            patch.apply(&mut invoice);
+            let client_changed = invoice.client_id != previous_client_id;
+            if client_changed && was_issued {
+                check(vec!["the client of an issued invoice can't be changed".to_string()])?;
+            }
+            // A different client, or issuing the invoice, copies the client's current details
+            if client_changed || (!was_issued && invoice.is_issued()) {
+                let (bill_to, bill_to_email) = (invoice.bill_to.clone(), invoice.bill_to_email.clone());
+                match invoice.client_id.clone() {
+                    Some(client_id) => copy_client_details(&mut invoice, &invoice_client(conn, &client_id)?),
+                    None if client_changed => {
+                        invoice.buyer_tax_id = None;
+                        invoice.buyer_country_code = None;
+                        invoice.buyer_peppol_id = None;
+                    }
+                    None => {}
+                }
+                // Details given with the change win over the client's
+                if keep_bill_to {
+                    invoice.bill_to = bill_to;
+                }
+                if keep_bill_to_email {
+                    invoice.bill_to_email = bill_to_email;
+                }
+            }
+            check(invoice.validate())?;
+
+            // If status changed to paid, set paid_date (keeping the original date on later edits)
+            invoice.paid_date = if invoice.status == "paid" {
+                invoice.paid_date.or_else(|| Some(Local::now().date_naive()))
+            } else {
+                None
+            };
+            invoice.updated_at = Utc::now();
+
+            conn.execute(
+                "UPDATE invoices SET project_id = ?2, invoice_number = ?3, amount = ?4, status = ?5, 
+                 issue_date = ?6, due_date = ?7, paid_date = ?8, bill_to = ?9, items = ?10, 
+                 notes = ?11, payment_terms = ?12, bill_to_email = ?13, client_id = ?14, buyer_tax_id = ?15, 
+                 buyer_country_code = ?16, buyer_peppol_id = ?17, updated_at = ?18 WHERE id = ?1",
+                params![
+                    &invoice.id, &invoice.project_id, &invoice.invoice_number, &invoice.amount, &invoice.status,
+                    &invoice.issue_date, &invoice.due_date, &invoice.paid_date, &invoice.bill_to, &invoice.items,
+                    &invoice.notes, &invoice.payment_terms, &invoice.bill_to_email, &invoice.client_id,
+                    &invoice.buyer_tax_id, &invoice.buyer_country_code, &invoice.buyer_peppol_id,
+                    &invoice.updated_at.to_rfc3339()
+                ],
+            )?;
+            Ok(invoice)
+        })
+    }
+
+    // Marks an invoice paid on `paid_date` (today when omitted) with an optional reference
+    pub fn record_invoice_payment(
+        &self,
+        invoice_id: String,
+        paid_date: Option<NaiveDate>,
+        payment_reference: Option<String>,
+    ) -> Result<()> {
+        let mut v = Validator::new();
+        v.max_length("payment_reference", payment_reference.as_deref(), MAX_SHORT);
+        check(v.into_errors())?;
+        let conn = self.connection()?;
+        let now = Utc::now().to_rfc3339();
+        let paid_date = paid_date.unwrap_or_else(|| Local::now().date_naive());
+        let payment_reference = payment_reference.filter(|s| !s.trim().is_empty());
+
+        conn.execute(
+            "UPDATE invoices SET status = 'paid', paid_date = ?2, payment_reference = ?3, 
+             updated_at = ?4 WHERE id = ?1",
+            params![&invoice_id, &paid_date, &payment_reference, &now],
+        )?;
+        Ok(())
+    }
+
+    pub fn set_invoice_tax_details(&self, invoice_id: String, tax_details: InvoiceTaxDetails) -> Result<()> {
+        check(tax_details.validate())?;
+        let conn = self.connection()?;
+        let clean = |value: Option<String>| value.map(|s| s.trim().to_uppercase()).filter(|s| !s.is_empty());
+        let trim = |value: Option<String>| value.map(|s| s.trim().to_string()).filter(|s| !s.is_empty());
+        let InvoiceTaxDetails { currency, tax_rate, tax_category, buyer_tax_id, buyer_country_code, buyer_peppol_id } =
+            tax_details;
+        let currency = currency.trim().to_uppercase();
+
+        conn.execute(
+            "UPDATE invoices SET currency = ?2, tax_rate = ?3, tax_category = ?4, buyer_tax_id = ?5, 
+             buyer_country_code = ?6, buyer_peppol_id = ?7, updated_at = ?8 WHERE id = ?1",
+            params![
+                &invoice_id, &currency, &tax_rate, &clean(tax_category), &trim(buyer_tax_id),
+                &clean(buyer_country_code), &trim(buyer_peppol_id), &Utc::now().to_rfc3339()
+            ],
+        )?;
+        Ok(())
+    }
+
+    // Marks an invoice as a regular invoice or as a credit note against `credited_invoice_id`
+    pub fn set_invoice_document_type(
+        &self,
+        invoice_id: String,
+        document_type: String,
+        credited_invoice_id: Option<String>,
+    ) -> Result<()> {
+        let conn = self.connection()?;
+        // Only credit notes point at another invoice
+        let credited_invoice_id = credited_invoice_id
+            .filter(|s| !s.is_empty() && document_type == "credit_note");
+
+        conn.execute(
+            "UPDATE invoices SET document_type = ?2, credited_invoice_id = ?3, updated_at = ?4 WHERE id = ?1",
+            params![&invoice_id, &document_type, &credited_invoice_id, &Utc::now().to_rfc3339()],
+        )?;
+        Ok(())
+    }
+
+    pub fn delete_invoice(&self, invoice_id: String) -> Result<()> {
+        let conn = self.connection()?;
+        conn.execute("DELETE FROM invoices WHERE id = ?1", params![&invoice_id])?;
+        Ok(())
+    }
+
+    // PDF template selection (None falls back to the artist's, then the default template)
+    pub fn set_artist_pdf_template(&self, artist_id: String, pdf_template: Option<String>) -> Result<()> {
+        let conn = self.connection()?;
+        conn.execute(
+            "UPDATE artists SET pdf_template = ?2, updated_at = ?3 WHERE id = ?1",
+            params![&artist_id, &pdf_template, &Utc::now().to_rfc3339()],
+        )?;
+        Ok(())
+    }
+
+    // Default terms for the artist's new invoices; existing invoices keep theirs
+    pub fn set_artist_payment_terms(&self, artist_id: String, payment_terms: Option<PaymentTerms>) -> Result<()> {
+        if let Some(terms) = &payment_terms {
+            let mut errors = terms.validate(Local::now().date_naive());
+            if matches!(terms, PaymentTerms::FixedDate { .. }) {
+                errors.push("a fixed date can't be an artist's default payment terms".to_string());
+            }
+            check(errors)?;
+        }
+        let conn = self.connection()?;
+        conn.execute(
+            "UPDATE artists SET payment_terms = ?2, updated_at = ?3 WHERE id = ?1",
+            params![&artist_id, &payment_terms, &Utc::now().to_rfc3339()],
+        )?;
+        Ok(())
+    }
+
+    // Payment reminders for the artist's unpaid invoices; None stops them
+    pub fn set_artist_reminder_schedule(&self, artist_id: String, schedule: Option<ReminderSchedule>) -> Result<()> {
+        if let Some(schedule) = &schedule {
+            check(schedule.validate())?;
+        }
+        let conn = self.connection()?;
+        conn.execute(
+            "UPDATE artists SET reminder_schedule = ?2, updated_at = ?3 WHERE id = ?1",
+            params![&artist_id, &schedule, &Utc::now().to_rfc3339()],
+        )?;
+        Ok(())
+    }
+
+    pub fn record_invoice_reminder(
+        &self,
+        invoice_id: &str,
+        offset_days: i64,
+        recipient: &str,
+        subject: &str,
+        error: Option<String>,
+    ) -> Result<InvoiceReminder> {
+        let conn = self.connection()?;
+        let reminder = InvoiceReminder {
+            id: Uuid::new_v4().to_string(),
+            invoice_id: invoice_id.to_string(),
+            offset_days,
+            recipient: recipient.to_string(),
+            subject: subject.to_string(),
+            error,
+            created_at: Utc::now(),
+        };
+        conn.execute(
+            "INSERT INTO invoice_reminders (id, invoice_id, offset_days, recipient, subject, error, created_at) 
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                &reminder.id, &reminder.invoice_id, &reminder.offset_days, &reminder.recipient, &reminder.subject,
+                &reminder.error, &reminder.created_at.to_rfc3339()
+            ],
+        )?;
+        Ok(reminder)
+    }
+
+    // Oldest first
+    pub fn get_invoice_reminders(&self, invoice_id: &str) -> Result<Vec<InvoiceReminder>> {
+        let conn = self.connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT id, invoice_id, offset_days, recipient, subject, error, created_at 
+             FROM invoice_reminders WHERE invoice_id = ?1 ORDER BY created_at, id"
+        )?;
+        let reminders = stmt.query_map(params![invoice_id], |row| {
+            Ok(InvoiceReminder {
+                id: row.get(0)?,
+                invoice_id: row.get(1)?,
+                offset_days: row.get(2)?,
+                recipient: row.get(3)?,
+                subject: row.get(4)?,
+                error: row.get(5)?,
+                created_at: row.get(6)?,
+            })
+        })?;
+        reminders.collect()
+    }
+
+    // Logs an emailed invoice and marks it sent with the client's current details;
+    // invoices already overdue, paid or cancelled keep their status and details
+    pub fn record_invoice_email(
+        &self,
+        invoice_id: &str,
+        to: &[String],
+        cc: &[String],
+        bcc: &[String],
+        subject: &str,
+        message: &str,
+    ) -> Result<InvoiceEmail> {
+        let join = |addresses: &[String]| Some(addresses.join(", ")).filter(|a| !a.is_empty());
+        let email = InvoiceEmail {
+            id: Uuid::new_v4().to_string(),
+            invoice_id: invoice_id.to_string(),
+            to_addresses: to.join(", "),
+            cc_addresses: join(cc),
+            bcc_addresses: join(bcc),
+            subject: subject.to_string(),
+            created_at: Utc::now(),
+        };
+        let now = email.created_at.to_rfc3339();
+        self.with_transaction(|conn| {
+            let mut invoice = query_invoice(conn, invoice_id)?;
+            if let (false, Some(client_id)) = (invoice.is_issued(), invoice.client_id.clone()) {
+                refresh_client_details(conn, &mut invoice, &query_client(conn, &client_id)?)?;
+            }
+            conn.execute(
+                "INSERT INTO invoice_emails 
+                 (id, invoice_id, to_addresses, cc_addresses, bcc_addresses, subject, message, created_at) 
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                params![
+                    &email.id, &email.invoice_id, &email.to_addresses, &email.cc_addresses, &email.bcc_addresses,
+                    &email.subject, message, &now
+                ],
+            )?;
+            conn.execute(
+                "UPDATE invoices SET sent_at = ?2, updated_at = ?2, 
+                 status = CASE WHEN status IN ('draft', 'pending') THEN 'sent' ELSE status END WHERE id = ?1",
+                params![invoice_id, &now],
+            )?;
+            Ok(())
+        })?;
+        Ok(email)
+    }
+
+    // Oldest first
+    pub fn get_invoice_emails(&self, invoice_id: &str) -> Result<Vec<InvoiceEmail>> {
+        let conn = self.connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT id, invoice_id, to_addresses, cc_addresses, bcc_addresses, subject, created_at 
+             FROM invoice_emails WHERE invoice_id = ?1 ORDER BY created_at, id"
+        )?;
+        let emails = stmt.query_map(params![invoice_id], |row| {
+            Ok(InvoiceEmail {
+                id: row.get(0)?,
+                invoice_id: row.get(1)?,
+                to_addresses: row.get(2)?,
+                cc_addresses: row.get(3)?,
+                bcc_addresses: row.get(4)?,
+                subject: row.get(5)?,
+                created_at: row.get(6)?,
+            })
+        })?;
+        emails.collect()
+    }
+
+    // The message as sent, in EML format
+    pub fn get_invoice_email_message(&self, email_id: &str) -> Result<String> {
+        let conn = self.connection()?;
+        conn.query_row("SELECT message FROM invoice_emails WHERE id = ?1", params![email_id], |row| row.get(0))
+    }
+
+    pub fn set_invoice_pdf_template(&self, invoice_id: String, pdf_template: Option<String>) -> Result<()> {
+        let conn = self.connection()?;
+        conn.execute(
+            "UPDATE invoices SET pdf_template = ?2, updated_at = ?3 WHERE id = ?1",
+            params![&invoice_id, &pdf_template, &Utc::now().to_rfc3339()],
+        )?;
+        Ok(())
+    }
+}
+
+
+// Branding profile functions
+fn row_to_branding_profile(row: &rusqlite::Row) -> Result<BrandingProfile> {
+    Ok(BrandingProfile {
+        id: row.get(0)?,
+        artist_id: row.get(1)?,
+        name: row.get(2)?,
+        logo_path: row.get(3)?,
+        accent_color: row.get(4)?,
+        font_path: row.get(5)?,
+        footer_text: row.get(6)?,
+        created_at: row.get(7)?,
+        updated_at: row.get(8)?,
     })
 }
 
-pub fn delete_invoice(invoice_id: String) -> Result<()> {
-    let conn = get_connection()?;
-    conn.execute("DELETE FROM invoices WHERE id = ?1", params![&invoice_id])?;
-    Ok(())
-}
\ No newline at end of file
+impl Database {
+    pub fn get_all_branding_profiles(&self) -> Result<Vec<BrandingProfile>> {
+        let conn = self.connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT id, artist_id, name, logo_path, accent_color, font_path, footer_text, created_at, updated_at 
+             FROM branding_profiles ORDER BY name"
+        )?;
+
+        let profiles = stmt.query_map([], row_to_branding_profile)?
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(profiles)
+    }
+
+    /// Returns the artist's own profile, falling back to the default profile.
+    pub fn get_branding_profile_for_artist(&self, artist_id: &str) -> Result<Option<BrandingProfile>> {
+        let conn = self.connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT id, artist_id, name, logo_path, accent_color, font_path, footer_text, created_at, updated_at 
+             FROM branding_profiles WHERE artist_id = ?1 OR artist_id IS NULL 
+             ORDER BY artist_id IS NULL, created_at LIMIT 1"
+        )?;
+
+        let mut profiles = stmt.query_map(params![artist_id], row_to_branding_profile)?;
+        profiles.next().transpose()
+    }
+
+    pub fn create_branding_profile(&self, profile: NewBrandingProfile) -> Result<BrandingProfile> {
+        let conn = self.connection()?;
+        let now = Utc::now();
+        let profile = BrandingProfile {
+            id: Uuid::new_v4().to_string(),
+            artist_id: profile.artist_id,
+            name: profile.name,
+            logo_path: profile.logo_path,
+            accent_color: profile.accent_color,
+            font_path: profile.font_path,
+            footer_text: profile.footer_text,
+            created_at: now,
+            updated_at: now,
+        };
+
+        conn.execute(
+            "INSERT INTO branding_profiles (id, artist_id, name, logo_path, accent_color, font_path, footer_text, created_at, updated_at) 
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?8)",
+            params![
+                &profile.id, &profile.artist_id, &profile.name, &profile.logo_path, &profile.accent_color,
+                &profile.font_path, &profile.footer_text, &now.to_rfc3339()
+            ],
+        )?;
+        Ok(profile)
+    }
+
+    pub fn update_branding_profile(&self, profile_id: &str, patch: BrandingProfilePatch) -> Result<BrandingProfile> {
+        self.with_transaction(|conn| {
+            let mut profile = conn.query_row(
+                "SELECT id, artist_id, name, logo_path, accent_color, font_path, footer_text, created_at, updated_at 
+                 FROM branding_profiles WHERE id = ?1",
+                params![profile_id],
+                row_to_branding_profile,
+            )?;
+            patch.apply(&mut profile);
+            profile.updated_at = Utc::now();
+
+            conn.execute(
+                "UPDATE branding_profiles SET name = ?2, logo_path = ?3, accent_color = ?4, font_path = ?5, 
+                 footer_text = ?6, updated_at = ?7 WHERE id = ?1",
+                params![
+                    &profile.id, &profile.name, &profile.logo_path, &profile.accent_color, &profile.font_path,
+                    &profile.footer_text, &profile.updated_at.to_rfc3339()
+                ],
+            )?;
+            Ok(profile)
+        })
+    }
+
+    pub fn delete_branding_profile(&self, profile_id: String) -> Result<()> {
+        let conn = self.connection()?;
+        conn.execute("DELETE FROM branding_profiles WHERE id = ?1", params![&profile_id])?;
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn database() -> Database {
+        Database::in_memory().expect("in-memory database")
+    }
+
+    fn artist(db: &Database, name: &str) -> Artist {
+        db.create_artist(NewArtist {
+            name: name.to_string(),
+            email: Some("billing@example.com".to_string()),
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    fn project(db: &Database, artist_id: &str, name: &str) -> Project {
+        db.create_project(NewProject {
+            artist_id: artist_id.to_string(),
+            name: name.to_string(),
+            description: None,
+            status: "active".to_string(),
+            start_date: Some(date("2024-01-01")),
+            end_date: None,
+            budget: 1000.0,
+        })
+        .unwrap()
+    }
+
+    fn invoice(db: &Database, artist_id: &str, project_id: Option<&str>, number: &str) -> Result<Invoice> {
+        db.create_invoice(NewInvoice {
+            artist_id: artist_id.to_string(),
+            project_id: project_id.map(str::to_string),
+            invoice_number: number.to_string(),
+            amount: 250.0,
+            status: "pending".to_string(),
+            issue_date: date("2024-03-01"),
+            due_date: Some(date("2024-03-31")),
+            payment_terms: None,
+            currency: Some("usd".to_string()),
+            client_id: None,
+            bill_to: Some("Acme Ltd".to_string()),
+            bill_to_email: None,
+            items: r#"[{"description":"Mixing","amount":250.0}]"#.to_string(),
+            notes: None,
+        })
+    }
+
+    fn date(value: &str) -> NaiveDate {
+        value.parse().unwrap()
+    }
+
+    /// A scratch folder for file-backed databases, removed when dropped.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new() -> TempDir {
+            let path = std::env::temp_dir().join(format!("project-invoicer-test-{}", Uuid::new_v4()));
+            std::fs::create_dir_all(&path).unwrap();
+            TempDir(path)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn artist_crud() {
+        let db = database();
+        let created = artist(&db, "Nova");
+        assert_eq!(db.get_artist(&created.id).unwrap().name, "Nova");
+
+        let patch = ArtistPatch {
+            name: Some("Nova Sound".to_string()),
+            company_name: Some(Some("Nova Sound LLC".to_string())),
+            email: Some(None),
+            ..Default::default()
+        };
+        let updated = db.update_artist(&created.id, patch).unwrap();
+        assert_eq!(updated.name, "Nova Sound");
+        assert_eq!(updated.company_name.as_deref(), Some("Nova Sound LLC"));
+        assert_eq!(updated.email, None);
+        assert_eq!(db.get_artist(&created.id).unwrap().company_name.as_deref(), Some("Nova Sound LLC"));
+        assert_eq!(db.get_all_artists().unwrap().len(), 1);
+
+        db.delete_artist(created.id.clone()).unwrap();
+        assert!(matches!(db.get_artist(&created.id), Err(rusqlite::Error::QueryReturnedNoRows)));
+    }
+
+    #[test]
+    fn invalid_artist_is_rejected() {
+        let db = database();
+        let new_artist = NewArtist {
+            name: " ".to_string(),
+            email: Some("not-an-email".to_string()),
+            ..Default::default()
+        };
+        let error = db.create_artist(new_artist).unwrap_err().to_string();
+        assert!(error.contains("name is required"), "{}", error);
+        assert!(error.contains("not a valid email"), "{}", error);
+        assert!(db.get_all_artists().unwrap().is_empty());
+
+        // A patch is checked against the merged artist and changes nothing when invalid
+        let nova = artist(&db, "Nova");
+        let patch = ArtistPatch { name: Some(String::new()), ..Default::default() };
+        assert!(db.update_artist(&nova.id, patch).is_err());
+        assert_eq!(db.get_artist(&nova.id).unwrap().name, "Nova");
+    }
+
+    #[test]
+    fn project_crud() {
+        let db = database();
+        let nova = artist(&db, "Nova");
+        let other = artist(&db, "Other");
+        let created = project(&db, &nova.id, "Album");
+        project(&db, &other.id, "Single");
+        assert_eq!(db.get_all_projects().unwrap().len(), 2);
+
+        let patch = ProjectPatch {
+            name: Some("Album (deluxe)".to_string()),
+            status: Some("completed".to_string()),
+            end_date: Some(Some(date("2024-06-30"))),
+            budget: Some(1500.0),
+            ..Default::default()
+        };
+        let updated = db.update_project(&created.id, patch).unwrap();
+        assert_eq!(updated.status, "completed");
+        assert_eq!(updated.start_date, Some(date("2024-01-01")));
+        let by_artist = db.get_projects_by_artist(nova.id.clone()).unwrap();
+        assert_eq!(by_artist.len(), 1);
+        assert_eq!(by_artist[0].name, "Album (deluxe)");
+        assert_eq!(by_artist[0].budget, 1500.0);
+
+        db.delete_project(created.id).unwrap();
+        assert!(db.get_projects_by_artist(nova.id).unwrap().is_empty());
+    }
+
+    #[test]
+    fn invoice_crud() {
+        let db = database();
+        let nova = artist(&db, "Nova");
+        let album = project(&db, &nova.id, "Album");
+        let created = invoice(&db, &nova.id, Some(&album.id), "INV-001").unwrap();
+        assert_eq!(created.bill_to.as_deref(), Some("Acme Ltd"));
+        assert_eq!(created.currency, "USD");
+        assert_eq!(db.get_invoice(&created.id).unwrap().project_id.as_deref(), Some(album.id.as_str()));
+
+        let patch = InvoicePatch {
+            amount: Some(300.0),
+            status: Some("paid".to_string()),
+            bill_to: Some(None),
+            notes: Some(Some("Thanks".to_string())),
+            ..Default::default()
+        };
+        let paid = db.update_invoice(&created.id, patch).unwrap();
+        assert_eq!(paid.amount, 300.0);
+        assert!(paid.paid_date.is_some());
+        assert_eq!(paid.bill_to, None);
+        assert_eq!(paid.invoice_number, "INV-001");
+        assert_eq!(paid.project_id.as_deref(), Some(album.id.as_str()));
+        assert_eq!(db.get_invoice(&created.id).unwrap().notes.as_deref(), Some("Thanks"));
+
+        db.record_invoice_payment(created.id.clone(), Some(date("2024-03-15")), Some("TX-9".to_string()))
+            .unwrap();
+        let recorded = db.get_invoice(&created.id).unwrap();
+        assert_eq!(recorded.paid_date, Some(date("2024-03-15")));
+        assert_eq!(recorded.payment_reference.as_deref(), Some("TX-9"));
+        assert_eq!(db.get_invoices_by_artist(nova.id).unwrap().len(), 1);
+
+        db.delete_invoice(created.id.clone()).unwrap();
+        assert!(db.get_all_invoices().unwrap().is_empty());
+    }
+
+    #[test]
+    fn payment_terms_set_the_due_date() {
+        let db = database();
+        let nova = artist(&db, "Nova");
+        let new_invoice = |number: &str, due_date, payment_terms| NewInvoice {
+            artist_id: nova.id.clone(),
+            project_id: None,
+            invoice_number: number.to_string(),
+            amount: 100.0,
+            status: "pending".to_string(),
+            issue_date: date("2024-03-10"),
+            due_date,
+            payment_terms,
+            currency: Some("USD".to_string()),
+            client_id: None,
+            bill_to: None,
+            bill_to_email: None,
+            items: "[]".to_string(),
+            notes: None,
+        };
+
+        // Neither a due date nor terms, and no artist default to fall back on
+        let error = db.create_invoice(new_invoice("INV-001", None, None)).unwrap_err().to_string();
+        assert!(error.contains("due_date or payment terms are required"), "{}", error);
+
+        db.set_artist_payment_terms(nova.id.clone(), Some(PaymentTerms::EndOfMonth { days: 15 })).unwrap();
+        assert_eq!(db.get_artist(&nova.id).unwrap().payment_terms, Some(PaymentTerms::EndOfMonth { days: 15 }));
+        let defaulted = db.create_invoice(new_invoice("INV-001", None, None)).unwrap();
+        assert_eq!(defaulted.due_date, date("2024-04-15"));
+        assert_eq!(db.get_invoice(&defaulted.id).unwrap().payment_terms, Some(PaymentTerms::EndOfMonth { days: 15 }));
+
+        // Explicit terms win over the artist's, and a due date given with them has to agree
+        let net = Some(PaymentTerms::Net { days: 30 });
+        let error = db
+            .create_invoice(new_invoice("INV-002", Some(date("2024-04-01")), net.clone()))
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("does not match the payment terms 'Net 30', which give 2024-04-09"), "{}", error);
+        let net_30 = db.create_invoice(new_invoice("INV-002", None, net)).unwrap();
+        assert_eq!(net_30.due_date, date("2024-04-09"));
+
+        // Moving the issue date moves the due date; dropping the terms keeps the last one
+        let patch = InvoicePatch { issue_date: Some(date("2024-03-20")), ..Default::default() };
+        assert_eq!(db.update_invoice(&net_30.id, patch).unwrap().due_date, date("2024-04-19"));
+        let patch = InvoicePatch { payment_terms: Some(None), ..Default::default() };
+        let manual = db.update_invoice(&net_30.id, patch).unwrap();
+        assert_eq!((manual.payment_terms, manual.due_date), (None, date("2024-04-19")));
+
+        let invalid = PaymentTerms::EarlyDiscount { discount_percent: 2.0, discount_days: 30, net_days: 30 };
+        assert!(db.set_artist_payment_terms(nova.id.clone(), Some(invalid)).is_err());
+    }
+
+    #[test]
+    fn overdue_invoices_are_marked() {
+        let db = database();
+        let nova = artist(&db, "Nova");
+        let due = invoice(&db, &nova.id, None, "INV-001").unwrap();
+        let paid = invoice(&db, &nova.id, None, "INV-002").unwrap();
+        db.record_invoice_payment(paid.id.clone(), None, None).unwrap();
+        let credit = invoice(&db, &nova.id, None, "CN-001").unwrap();
+        db.set_invoice_document_type(credit.id.clone(), "credit_note".to_string(), Some(due.id.clone())).unwrap();
+        let put_away = ["archived", "trashed"].map(|status| {
+            let invoice = invoice(&db, &nova.id, None, &format!("INV-{}", status)).unwrap();
+            let patch = InvoicePatch { status: Some(status.to_string()), ..Default::default() };
+            db.update_invoice(&invoice.id, patch).unwrap()
+        });
+
+        // Due on 2024-03-31, so not yet overdue on that day
+        assert_eq!(db.mark_overdue_invoices(date("2024-03-31")).unwrap(), 0);
+        assert_eq!(db.get_open_invoices().unwrap().len(), 1);
+        assert_eq!(db.mark_overdue_invoices(date("2024-04-01")).unwrap(), 1);
+        assert_eq!(db.mark_overdue_invoices(date("2024-04-02")).unwrap(), 0);
+        assert_eq!(db.get_invoice(&due.id).unwrap().status, "overdue");
+        assert_eq!(db.get_invoice(&paid.id).unwrap().status, "paid");
+        for invoice in &put_away {
+            assert_eq!(db.get_invoice(&invoice.id).unwrap().status, invoice.status);
+        }
+        let overdue = db.get_overdue_invoices(date("2024-04-01")).unwrap();
+        assert_eq!(overdue.iter().map(|i| i.id.as_str()).collect::<Vec<_>>(), [due.id.as_str()]);
+
+        // Moving the due date out makes it pending again
+        let patch = InvoicePatch { due_date: Some(date("2024-05-01")), ..Default::default() };
+        db.update_invoice(&due.id, patch).unwrap();
+        db.mark_overdue_invoices(date("2024-04-02")).unwrap();
+        assert_eq!(db.get_invoice(&due.id).unwrap().status, "pending");
+    }
+
+    #[test]
+    fn emailed_invoices_are_marked_sent() {
+        let db = database();
+        let nova = artist(&db, "Nova");
+        let pending = invoice(&db, &nova.id, None, "INV-001").unwrap();
+        let paid = invoice(&db, &nova.id, None, "INV-002").unwrap();
+        db.record_invoice_payment(paid.id.clone(), None, None).unwrap();
+
+        let to = ["client@example.com".to_string()];
+        let bcc = ["archive@example.com".to_string()];
+        let email = db.record_invoice_email(&pending.id, &to, &[], &bcc, "Invoice INV-001", "Subject: x\r\n").unwrap();
+        db.record_invoice_email(&paid.id, &to, &[], &[], "Invoice INV-002", "Subject: y\r\n").unwrap();
+
+        let sent = db.get_invoice(&pending.id).unwrap();
+        assert_eq!(sent.status, "sent");
+        assert!(sent.sent_at.is_some());
+        assert_eq!(db.get_invoice(&paid.id).unwrap().status, "paid");
+        let emails = db.get_invoice_emails(&pending.id).unwrap();
+        assert_eq!(emails.len(), 1);
+        assert_eq!(emails[0].cc_addresses, None);
+        assert_eq!(emails[0].bcc_addresses.as_deref(), Some("archive@example.com"));
+        assert_eq!(db.get_invoice_email_message(&email.id).unwrap(), "Subject: x\r\n");
+
+        // A sent invoice goes back to "sent", not "pending", when it stops being overdue
+        db.mark_overdue_invoices(date("2024-04-01")).unwrap();
+        assert_eq!(db.get_invoice(&pending.id).unwrap().status, "overdue");
+        db.mark_overdue_invoices(date("2024-03-01")).unwrap();
+        assert_eq!(db.get_invoice(&pending.id).unwrap().status, "sent");
+    }
+
+    #[test]
+    fn invoices_keep_the_client_details_they_were_issued_with() {
+        let db = database();
+        let nova = artist(&db, "Nova");
+        let acme = db
+            .create_client(NewClient {
+                legal_name: " Acme GmbH ".to_string(),
+                email: Some("billing@acme.example".to_string()),
+                address_line1: Some("Hauptstraße 1".to_string()),
+                postal_code: Some("10115".to_string()),
+                city: Some("Berlin".to_string()),
+                country_code: Some("de".to_string()),
+                tax_id: Some("DE123456789".to_string()),
+                currency: Some("eur".to_string()),
+                payment_terms: Some(PaymentTerms::Net { days: 14 }),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!((acme.legal_name.as_str(), acme.country_code.as_deref()), ("Acme GmbH", Some("DE")));
+
+        let new_invoice = NewInvoice {
+            status: "draft".to_string(),
+            client_id: Some(acme.id.clone()),
+            bill_to: None,
+            currency: None,
+            due_date: None,
+            ..serde_json::from_str(r#"{"artist_id": "", "invoice_number": "INV-001", "amount": 100,
+                "issue_date": "2024-03-01"}"#).unwrap()
+        };
+        let issued = db.create_invoice(NewInvoice { artist_id: nova.id.clone(), ..new_invoice }).unwrap();
+        assert_eq!(issued.bill_to.as_deref(), Some("Acme GmbH\nHauptstraße 1\n10115 Berlin"));
+        assert_eq!(issued.bill_to_email.as_deref(), Some("billing@acme.example"));
+        assert_eq!((issued.currency.as_str(), issued.due_date), ("EUR", date("2024-03-15")));
+        assert_eq!(issued.buyer_country_code.as_deref(), Some("DE"));
+
+        // Switching clients copies the new client's details
+        let other = db.create_client(NewClient { legal_name: "Other Ltd".to_string(), ..Default::default() }).unwrap();
+        let patch = InvoicePatch { client_id: Some(Some(other.id.clone())), ..Default::default() };
+        let switched = db.update_invoice(&issued.id, patch).unwrap();
+        assert_eq!((switched.bill_to.as_deref(), switched.buyer_tax_id), (Some("Other Ltd"), None));
+        let unknown = InvoicePatch { client_id: Some(Some("missing".to_string())), ..Default::default() };
+        assert!(db.update_invoice(&issued.id, unknown).unwrap_err().to_string().contains("client 'missing'"));
+
+        // Deleting the client keeps the invoice's copy
+        db.delete_client(other.id.clone()).unwrap();
+        let kept = db.get_invoice(&issued.id).unwrap();
+        assert_eq!((kept.client_id, kept.bill_to.as_deref()), (None, Some("Other Ltd")));
+        assert_eq!(db.get_all_clients().unwrap().len(), 1);
+
+        let invalid = NewClient {
+            legal_name: " ".to_string(),
+            cc_emails: Some("a@example.com, nope".to_string()),
+            payment_terms: Some(PaymentTerms::FixedDate { date: date("2099-01-01") }),
+            ..Default::default()
+        };
+        assert_eq!(invalid.validate().len(), 3, "{:?}", invalid.validate());
+    }
+
+    #[test]
+    fn drafts_follow_client_edits_until_sent() {
+        let db = database();
+        let nova = artist(&db, "Nova");
+        let acme = db
+            .create_client(NewClient {
+                legal_name: "Acme GmbH".to_string(),
+                email: Some("billing@acme.example".to_string()),
+                city: Some("Berlin".to_string()),
+                ..Default::default()
+            })
+            .unwrap();
+        let new_invoice = |number: &str, status: &str| NewInvoice {
+            artist_id: nova.id.clone(),
+            invoice_number: number.to_string(),
+            status: status.to_string(),
+            client_id: Some(acme.id.clone()),
+            ..serde_json::from_str(r#"{"artist_id": "", "invoice_number": "", "amount": 100,
+                "issue_date": "2024-03-01", "due_date": "2024-03-31"}"#).unwrap()
+        };
+        let draft = db.create_invoice(new_invoice("INV-001", "draft")).unwrap();
+        let sent = db.create_invoice(new_invoice("INV-002", "draft")).unwrap();
+        let pending = db.create_invoice(new_invoice("INV-003", "pending")).unwrap();
+        let to = ["billing@acme.example".to_string()];
+        db.record_invoice_email(&sent.id, &to, &[], &[], "Invoice INV-002", "Attached.").unwrap();
+        assert_eq!(db.get_invoice(&sent.id).unwrap().status, "sent");
+
+        let patch = ClientPatch {
+            city: Some(Some("Hamburg".to_string())),
+            email: Some(Some("accounts@acme.example".to_string())),
+            tax_id: Some(Some("DE123456789".to_string())),
+            ..Default::default()
+        };
+        db.update_client(&acme.id, patch).unwrap();
+        let draft = db.get_invoice(&draft.id).unwrap();
+        assert_eq!(draft.bill_to.as_deref(), Some("Acme GmbH\nHamburg"));
+        assert_eq!(draft.bill_to_email.as_deref(), Some("accounts@acme.example"));
+        assert_eq!(draft.buyer_tax_id.as_deref(), Some("DE123456789"));
+        let kept = db.get_invoice(&sent.id).unwrap();
+        assert_eq!((kept.bill_to.as_deref(), kept.buyer_tax_id), (Some("Acme GmbH\nBerlin"), None));
+        assert_eq!(kept.bill_to_email.as_deref(), Some("billing@acme.example"));
+        let outstanding = db.get_invoice(&pending.id).unwrap();
+        assert_eq!((outstanding.bill_to.as_deref(), outstanding.buyer_tax_id), (Some("Acme GmbH\nBerlin"), None));
+        assert_eq!(outstanding.bill_to_email.as_deref(), Some("billing@acme.example"));
+
+        // Sending a draft issues it with the client's details at that moment
+        let patch = ClientPatch { city: Some(Some("Munich".to_string())), ..Default::default() };
+        db.update_client(&acme.id, patch).unwrap();
+        let issued = db
+            .update_invoice(&draft.id, InvoicePatch { status: Some("sent".to_string()), ..Default::default() })
+            .unwrap();
+        assert_eq!(issued.bill_to.as_deref(), Some("Acme GmbH\nMunich"));
+
+        // An issued invoice keeps its client
+        let other = db.create_client(NewClient { legal_name: "Other Ltd".to_string(), ..Default::default() }).unwrap();
+        let patch = InvoicePatch { client_id: Some(Some(other.id)), ..Default::default() };
+        let error = db.update_invoice(&sent.id, patch).unwrap_err().to_string();
+        assert!(error.contains("the client of an issued invoice can't be changed"), "{}", error);
+    }
+
+    #[test]
+    fn invoice_for_unknown_artist_is_rejected() {
+        let db = database();
+        assert!(invoice(&db, "missing", None, "INV-001").is_err());
+        assert!(db.get_all_invoices().unwrap().is_empty());
+    }
+
+    #[test]
+    fn invalid_invoice_reports_every_problem() {
+        let db = database();
+        let nova = artist(&db, "Nova");
+        let error = db
+            .create_invoice(NewInvoice {
+                artist_id: nova.id.clone(),
+                project_id: None,
+                invoice_number: "INV-001".to_string(),
+                amount: -5.0,
+                status: "pending".to_string(),
+                issue_date: date("2024-03-31"),
+                due_date: Some(date("2024-03-01")),
+                payment_terms: None,
+                currency: Some("US$".to_string()),
+                client_id: None,
+                bill_to: None,
+                bill_to_email: None,
+                items: "[]".to_string(),
+                notes: Some("x".repeat(MAX_NOTES + 1)),
+            })
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("amount -5 must be zero or more"), "{}", error);
+        assert!(error.contains("currency 'US$' is not a three-letter ISO 4217 code"), "{}", error);
+        assert!(error.contains("due_date 2024-03-01 is before issue_date 2024-03-31"), "{}", error);
+        assert!(error.contains("notes is longer than"), "{}", error);
+        assert!(db.get_all_invoices().unwrap().is_empty());
+
+        let patch = InvoicePatch { due_date: Some(date("2024-02-01")), ..Default::default() };
+        let created = invoice(&db, &nova.id, None, "INV-002").unwrap();
+        let error = db.update_invoice(&created.id, patch).unwrap_err().to_string();
+        assert!(error.contains("due_date 2024-02-01 is before issue_date 2024-03-01"), "{}", error);
+    }
+
+    #[test]
+    fn artist_contact_and_bank_details_are_checked() {
+        let db = database();
+        let nova = db
+            .create_artist(NewArtist {
+                name: "Nova".to_string(),
+                phone: Some("+1 (415) 555-0123".to_string()),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(nova.phone.as_deref(), Some("+14155550123"));
+        assert_eq!(db.get_artist(&nova.id).unwrap().phone.as_deref(), Some("+14155550123"));
+
+        let patch = ArtistPatch { phone: Some(Some("555 0123".to_string())), ..Default::default() };
+        assert!(db.update_artist(&nova.id, patch).is_err());
+
+        let bank_details = BankDetails {
+            iban: Some("DE89 3704 0044 0532 0130 01".to_string()),
+            bic: Some("DEUT1EFF".to_string()),
+            ..Default::default()
+        };
+        let error = db.set_artist_bank_details(nova.id.clone(), bank_details).unwrap_err().to_string();
+        assert!(error.contains("not a valid IBAN") && error.contains("not a valid BIC"), "{}", error);
+
+        let bank_details = BankDetails { iban: Some("DE89 3704 0044 0532 0130 00".to_string()), ..Default::default() };
+        db.set_artist_bank_details(nova.id.clone(), bank_details).unwrap();
+        assert_eq!(db.get_artist(&nova.id).unwrap().bank_details.iban.as_deref(), Some("DE89370400440532013000"));
+    }
+
+    #[test]
+    fn invoice_numbers_are_unique() {
+        let db = database();
+        let nova = artist(&db, "Nova");
+        let other = artist(&db, "Other");
+        invoice(&db, &nova.id, None, "INV-001").unwrap();
+        let second = invoice(&db, &other.id, None, "INV-002").unwrap();
+
+        let duplicate = invoice(&db, &other.id, None, "INV-001").unwrap_err();
+        assert!(duplicate.to_string().contains("UNIQUE"), "{}", duplicate);
+
+        let patch = InvoicePatch { invoice_number: Some("INV-001".to_string()), ..Default::default() };
+        assert!(db.update_invoice(&second.id, patch).is_err());
+        assert_eq!(db.get_invoice(&second.id).unwrap().invoice_number, "INV-002");
+        assert_eq!(db.get_all_invoices().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn patches_tell_omitted_fields_from_nulls() {
+        let patch: InvoicePatch = serde_json::from_str(r#"{"amount": 12.5, "bill_to": null}"#).unwrap();
+        assert_eq!(patch.amount, Some(12.5));
+        assert_eq!(patch.bill_to, Some(None));
+        assert_eq!(patch.notes, None);
+        assert_eq!(patch.status, None);
+
+        let new_invoice: NewInvoice = serde_json::from_str(
+            r#"{"artist_id": "a1", "invoice_number": "INV-1", "amount": 10, "issue_date": "2024-01-01",
+                "due_date": "2024-01-31"}"#,
+        )
+        .unwrap();
+        assert_eq!(new_invoice.status, "pending");
+        assert_eq!(new_invoice.currency, None);
+        assert_eq!(new_invoice.items, "[]");
+        assert!(new_invoice.validate().is_empty());
+    }
+
+    #[test]
+    fn branding_profile_patches_keep_omitted_fields() {
+        let db = database();
+        let profile = db
+            .create_branding_profile(NewBrandingProfile {
+                name: "Studio".to_string(),
+                accent_color: Some("#336699".to_string()),
+                footer_text: Some("Thanks!".to_string()),
+                ..Default::default()
+            })
+            .unwrap();
+
+        let patch: BrandingProfilePatch = serde_json::from_str(r#"{"name": "Studio B", "footer_text": null}"#).unwrap();
+        let updated = db.update_branding_profile(&profile.id, patch).unwrap();
+        assert_eq!(updated.name, "Studio B");
+        assert_eq!(updated.accent_color.as_deref(), Some("#336699"));
+        assert_eq!(updated.footer_text, None);
+        assert_eq!(db.get_all_branding_profiles().unwrap()[0].name, "Studio B");
+        assert!(db.update_branding_profile("missing", BrandingProfilePatch::default()).is_err());
+    }
+
+    #[test]
+    fn deleting_an_artist_cascades() {
+        let db = database();
+        let nova = artist(&db, "Nova");
+        let other = artist(&db, "Other");
+        let album = project(&db, &nova.id, "Album");
+        invoice(&db, &nova.id, Some(&album.id), "INV-001").unwrap();
+        invoice(&db, &other.id, None, "INV-002").unwrap();
+        let profile = NewBrandingProfile { artist_id: Some(nova.id.clone()), ..Default::default() };
+        db.create_branding_profile(profile).unwrap();
+
+        db.delete_artist(nova.id.clone()).unwrap();
+        assert!(db.get_all_projects().unwrap().is_empty());
+        let invoices = db.get_all_invoices().unwrap();
+        assert_eq!(invoices.len(), 1);
+        assert_eq!(invoices[0].invoice_number, "INV-002");
+        assert!(db.get_branding_profile_for_artist(&nova.id).unwrap().is_none());
+    }
+
+    #[test]
+    fn deleting_a_project_keeps_its_invoices() {
+        let db = database();
+        let nova = artist(&db, "Nova");
+        let album = project(&db, &nova.id, "Album");
+        let created = invoice(&db, &nova.id, Some(&album.id), "INV-001").unwrap();
+
+        db.delete_project(album.id).unwrap();
+        assert_eq!(db.get_invoice(&created.id).unwrap().project_id, None);
+    }
+
+    #[test]
+    fn deleting_a_credited_invoice_unlinks_its_credit_notes() {
+        let db = database();
+        let nova = artist(&db, "Nova");
+        let original = invoice(&db, &nova.id, None, "INV-001").unwrap();
+        let credit = invoice(&db, &nova.id, None, "CN-001").unwrap();
+        db.set_invoice_document_type(credit.id.clone(), "credit_note".to_string(), Some(original.id.clone()))
+            .unwrap();
+        assert_eq!(db.get_invoice(&credit.id).unwrap().credited_invoice_id, Some(original.id.clone()));
+
+        db.delete_invoice(original.id).unwrap();
+        let credit = db.get_invoice(&credit.id).unwrap();
+        assert_eq!(credit.document_type, "credit_note");
+        assert_eq!(credit.credited_invoice_id, None);
+    }
+
+    #[test]
+    fn unreadable_dates_stop_the_migration() {
+        let db = database();
+        let nova = artist(&db, "Nova");
+        let first = invoice(&db, &nova.id, None, "INV-001").unwrap();
+        let second = invoice(&db, &nova.id, None, "INV-002").unwrap();
+        let conn = db.connection().unwrap();
+        conn.execute("UPDATE invoices SET due_date = 'next week' WHERE id = ?1", params![&first.id]).unwrap();
+        conn.execute("UPDATE invoices SET issue_date = '2024/03/01' WHERE id = ?1", params![&second.id]).unwrap();
+
+        let error = normalize_dates(&conn).unwrap_err().to_string();
+        assert!(error.contains(&format!("invoices {} has an unreadable due_date 'next week'", first.id)), "{}", error);
+        // Nothing is rewritten until every value can be read
+        let issue_date: String = conn
+            .query_row("SELECT issue_date FROM invoices WHERE id = ?1", params![&second.id], |row| row.get(0))
+            .unwrap();
+        assert_eq!(issue_date, "2024/03/01");
+    }
+
+    #[test]
+    fn migrates_the_original_schema() {
+        let dir = TempDir::new();
+        // Migrating snapshots the database first; settings and snapshots stay in the scratch folder
+        TEST_DATA_DIR.with(|data_dir| *data_dir.borrow_mut() = Some(dir.0.clone()));
+        let path = dir.0.join("legacy.db");
+        // The first released schema, before company_name, wire_details and bill_to, with dates as the
+        // frontend and payments used to write them
+        let legacy = Connection::open(&path).unwrap();
+        legacy
+            .execute_batch(
+                "CREATE TABLE artists (
+                    id TEXT PRIMARY KEY,
+                    name TEXT NOT NULL,
+                    email TEXT,
+                    phone TEXT,
+                    address TEXT,
+                    notes TEXT,
+                    created_at TEXT NOT NULL,
+                    updated_at TEXT NOT NULL
+                );
+                CREATE TABLE projects (
+                    id TEXT PRIMARY KEY,
+                    artist_id TEXT NOT NULL,
+                    name TEXT NOT NULL,
+                    description TEXT,
+                    status TEXT NOT NULL DEFAULT 'active',
+                    start_date TEXT,
+                    end_date TEXT,
+                    budget REAL DEFAULT 0,
+                    created_at TEXT NOT NULL,
+                    updated_at TEXT NOT NULL,
+                    FOREIGN KEY (artist_id) REFERENCES artists (id) ON DELETE CASCADE
+                );
+                CREATE TABLE invoices (
+                    id TEXT PRIMARY KEY,
+                    artist_id TEXT NOT NULL,
+                    project_id TEXT,
+                    invoice_number TEXT NOT NULL UNIQUE,
+                    amount REAL NOT NULL DEFAULT 0,
+                    status TEXT NOT NULL DEFAULT 'pending',
+                    issue_date TEXT NOT NULL,
+                    due_date TEXT NOT NULL,
+                    paid_date TEXT,
+                    items TEXT NOT NULL DEFAULT '[]',
+                    notes TEXT,
+                    created_at TEXT NOT NULL,
+                    updated_at TEXT NOT NULL,
+                    FOREIGN KEY (artist_id) REFERENCES artists (id) ON DELETE CASCADE,
+                    FOREIGN KEY (project_id) REFERENCES projects (id) ON DELETE SET NULL
+                );
+                INSERT INTO artists VALUES
+                    ('a1', 'Nova', 'nova@example.com', NULL, NULL, NULL, '2023-01-01', '2023-01-01');
+                INSERT INTO projects VALUES
+                    ('p1', 'a1', 'Album', NULL, 'active', NULL, '', 500, '2023-01-02', '2023-01-02');
+                INSERT INTO invoices VALUES
+                    ('i1', 'a1', 'p1', 'INV-001', 500, 'paid', '2023/02/01', '2023-03-01',
+                     '2023-03-05T23:30:00-05:00', '[]', NULL, '2023-02-01 09:15:00', '2023-03-05T23:30:00-05:00');",
+            )
+            .unwrap();
+        drop(legacy);
+
+        let db = Database::open(&path).unwrap();
+        assert_eq!(std::fs::read_dir(dir.0.join("snapshots").join("legacy")).unwrap().count(), 1);
+        let nova = db.get_artist("a1").unwrap();
+        assert_eq!(nova.email.as_deref(), Some("nova@example.com"));
+        assert_eq!(nova.company_name, None);
+        assert_eq!(nova.wire_details, None);
+        let migrated = db.get_invoice("i1").unwrap();
+        assert_eq!(migrated.project_id.as_deref(), Some("p1"));
+        assert_eq!(migrated.bill_to, None);
+        assert_eq!(migrated.currency, "USD");
+        assert_eq!(migrated.document_type, "invoice");
+        assert_eq!(migrated.issue_date, date("2023-02-01"));
+        // Payment timestamps keep the day they were recorded on
+        assert_eq!(migrated.paid_date, Some(date("2023-03-05")));
+        assert_eq!(migrated.updated_at.to_rfc3339(), "2023-03-06T04:30:00+00:00");
+        assert_eq!(migrated.created_at.to_rfc3339(), "2023-02-01T09:15:00+00:00");
+        assert_eq!(db.get_all_projects().unwrap()[0].end_date, None);
+
+        let version: i64 = db.connection().unwrap().query_row("PRAGMA user_version", [], |row| row.get(0)).unwrap();
+        assert_eq!(version, SCHEMA_VERSION);
+        // New columns are writable and the data is still linked up
+        let created = invoice(&db, "a1", Some("p1"), "INV-002").unwrap();
+        assert_eq!(created.bill_to.as_deref(), Some("Acme Ltd"));
+        db.delete_artist("a1".to_string()).unwrap();
+        assert!(db.get_all_invoices().unwrap().is_empty());
+
+        // Opening again is a no-op
+        drop(db);
+        Database::open(&path).unwrap();
+    }
+}
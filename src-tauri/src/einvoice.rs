@@ -0,0 +1,168 @@
+// src-tauri/src/einvoice.rs
+use crate::database::{Artist, Invoice};
+use crate::pdf_generator::InvoiceData;
+
+/// VAT categories (UNCL5305) accepted by EN 16931, with the exemption reason
+/// printed for the ones that don't charge VAT.
+pub const TAX_CATEGORIES: [(&str, Option<&str>); 7] = [
+    ("S", None),
+    ("Z", None),
+    ("E", Some("Exempt from VAT")),
+    ("AE", Some("Reverse charge")),
+    ("K", Some("Intra-community supply")),
+    ("G", Some("Export outside the EU")),
+    ("O", Some("Not subject to VAT")),
+];
+
+/// The VAT category for an invoice: the stored one, or standard-rated when
+/// a positive rate is set and zero-rated otherwise.
+pub fn tax_category(invoice: &Invoice) -> String {
+    match non_empty(&invoice.tax_category) {
+        Some(category) => category.to_uppercase(),
+        None if invoice.tax_rate.unwrap_or(0.0) > 0.0 => "S".to_string(),
+        None => "Z".to_string(),
+    }
+}
+
+pub fn exemption_reason(category: &str) -> Option<&'static str> {
+    TAX_CATEGORIES.iter().find(|(code, _)| *code == category).and_then(|(_, reason)| *reason)
+}
+
+pub fn is_credit_note(invoice: &Invoice) -> bool {
+    invoice.document_type == "credit_note"
+}
+
+/// Everything EN 16931 requires that the app doesn't enforce on its own.
+/// Returns a description of each missing field, empty when complete.
+pub fn missing_fields(data: &InvoiceData) -> Vec<String> {
+    let invoice = &data.invoice;
+    let artist = &data.artist;
+    let mut errors = Vec::new();
+
+    if non_empty(&artist.tax_id).is_none() {
+        errors.push("seller tax ID (artist VAT number)".to_string());
+    }
+    if !is_country_code(artist.country_code.as_deref()) {
+        errors.push("seller country code".to_string());
+    }
+
+    if bill_to_lines(invoice).len() < 2 {
+        errors.push("buyer name and address (Bill To needs a name line followed by the address)".to_string());
+    }
+    if !is_country_code(invoice.buyer_country_code.as_deref()) {
+        errors.push("buyer country code".to_string());
+    }
+
+    if invoice.currency.len() != 3 || !invoice.currency.chars().all(|c| c.is_ascii_uppercase()) {
+        errors.push(format!("valid ISO 4217 currency (got '{}')", invoice.currency));
+    }
+
+    // Tax breakdown
+    match invoice.tax_rate {
+        None => errors.push("tax breakdown (VAT rate)".to_string()),
+        Some(rate) => {
+            let category = tax_category(invoice);
+            match category.as_str() {
+                "S" if rate <= 0.0 => errors.push("a positive VAT rate for standard-rated invoices".to_string()),
+                "S" => {}
+                _ if !TAX_CATEGORIES.iter().any(|(code, _)| *code == category) => {
+                    errors.push(format!("known VAT category (got '{}')", category))
+                }
+                _ if rate != 0.0 => errors.push(format!("a 0% VAT rate for category {}", category)),
+                _ => {}
+            }
+        }
+    }
+
+    if data.line_items.is_empty() {
+        errors.push("at least one line item".to_string());
+    }
+    if data.line_items.iter().any(|item| item.description.trim().is_empty()) {
+        errors.push("a description on every line item".to_string());
+    }
+    let line_total: f64 = data.line_items.iter().map(|item| item.amount).sum();
+    if !data.line_items.is_empty() && (line_total - invoice.amount).abs() >= 0.005 {
+        errors.push(format!(
+            "line items adding up to the invoice amount ({:.2} vs {:.2})",
+            line_total, invoice.amount
+        ));
+    }
+
+    errors
+}
+
+/// Bill To split into lines: the buyer's name, then their address.
+pub fn bill_to_lines(invoice: &Invoice) -> Vec<&str> {
+    split_lines(invoice.bill_to.as_deref())
+}
+
+pub fn seller_address_lines(artist: &Artist) -> Vec<&str> {
+    split_lines(artist.address.as_deref())
+}
+
+pub fn seller_name(artist: &Artist) -> &str {
+    non_empty(&artist.company_name).unwrap_or(&artist.name)
+}
+
+fn split_lines(text: Option<&str>) -> Vec<&str> {
+    text.unwrap_or("").lines().map(str::trim).filter(|l| !l.is_empty()).collect()
+}
+
+pub fn is_country_code(code: Option<&str>) -> bool {
+    matches!(code, Some(code) if code.len() == 2 && code.chars().all(|c| c.is_ascii_uppercase()))
+}
+
+pub fn non_empty(value: &Option<String>) -> Option<&str> {
+    value.as_deref().map(str::trim).filter(|s| !s.is_empty())
+}
+
+pub fn amount(value: f64) -> String {
+    format!("{:.2}", value)
+}
+
+pub fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// Minimal indenting XML writer for e-invoice documents. The syntax-specific
+/// helpers live next to each format.
+#[derive(Default)]
+pub struct XmlWriter {
+    output: String,
+    depth: usize,
+}
+
+impl XmlWriter {
+    pub fn line(&mut self, content: &str) {
+        self.output.push_str(&"  ".repeat(self.depth));
+        self.output.push_str(content);
+        self.output.push('\n');
+    }
+
+    pub fn open(&mut self, tag: &str) {
+        self.line(&format!("<{}>", tag));
+        self.depth += 1;
+    }
+
+    pub fn close(&mut self, tag: &str) {
+        self.depth -= 1;
+        self.line(&format!("</{}>", tag));
+    }
+
+    pub fn element(&mut self, tag: &str, value: &str) {
+        self.line(&format!("<{tag}>{}</{tag}>", escape(value)));
+    }
+
+    pub fn element_with(&mut self, tag: &str, attributes: &str, value: &str) {
+        self.line(&format!("<{tag} {attributes}>{}</{tag}>", escape(value)));
+    }
+
+    pub fn finish(self) -> String {
+        self.output
+    }
+}
@@ -0,0 +1,362 @@
+// src-tauri/src/email.rs
+use chrono::NaiveDate;
+use lettre::message::header::ContentType;
+use lettre::message::{Attachment, Mailbox, MultiPart, SinglePart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{Message, SmtpTransport, Transport};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::database::{Artist, Database, Invoice};
+use crate::pdf_generator::{format_currency, format_date, Totals};
+use crate::validation::{Validator, MAX_NAME, MAX_NOTES};
+
+const SEND_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Outgoing mail server. A local catcher such as MailHog or smtp4dev works
+/// with `security = "none"` and port 1025 (MailHog) or 25 (smtp4dev).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SmtpSettings {
+    pub host: String,
+    #[serde(default = "default_port")]
+    pub port: u16,
+    #[serde(default)]
+    pub security: SmtpSecurity,
+    #[serde(default)]
+    pub username: Option<String>,
+    // Never sent back to the webview; see `redacted`
+    #[serde(default)]
+    pub password: Option<String>,
+    pub from_address: String,
+    #[serde(default)]
+    pub from_name: Option<String>,
+    #[serde(default)]
+    pub reply_to: Option<String>,
+}
+
+fn default_port() -> u16 { 587 }
+
+/// SMTP settings as shown in the app, with the password left out.
+#[derive(Debug, Clone, Serialize)]
+pub struct RedactedSmtpSettings {
+    #[serde(flatten)]
+    pub settings: SmtpSettings,
+    pub has_password: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpSecurity {
+    // Plain connection upgraded with STARTTLS, usually port 587
+    #[default]
+    StartTls,
+    // TLS from the start, usually port 465
+    Tls,
+    // Unencrypted; only for local test servers
+    None,
+}
+
+impl SmtpSettings {
+    pub fn redacted(self) -> RedactedSmtpSettings {
+        let has_password = self.password.as_deref().is_some_and(|p| !p.is_empty());
+        RedactedSmtpSettings { settings: SmtpSettings { password: None, ..self }, has_password }
+    }
+
+    /// Keeps the saved password when an update leaves it out, as updates from
+    /// the app do since it never sees it. It's only kept for the same server
+    /// and username; an empty password clears it.
+    pub fn keep_password(&mut self, saved: Option<&SmtpSettings>) {
+        match self.password.as_deref() {
+            Some("") => self.password = None,
+            Some(_) => {}
+            None => {
+                self.password = saved
+                    .filter(|saved| saved.host.trim() == self.host.trim() && saved.username == self.username)
+                    .and_then(|saved| saved.password.clone());
+            }
+        }
+    }
+
+    pub fn validate(&self) -> Vec<String> {
+        let mut v = Validator::new();
+        v.required("SMTP host", &self.host, MAX_NAME);
+        if self.port == 0 {
+            v.error("SMTP port is required".to_string());
+        }
+        v.required("sender address", &self.from_address, MAX_NAME);
+        v.email("sender address", Some(&self.from_address));
+        v.max_length("sender name", self.from_name.as_deref(), MAX_NAME);
+        v.email("reply-to address", self.reply_to.as_deref());
+        v.into_errors()
+    }
+
+    fn transport(&self) -> Result<SmtpTransport, String> {
+        let host = self.host.trim();
+        let builder = match self.security {
+            SmtpSecurity::StartTls => SmtpTransport::starttls_relay(host).map_err(|e| e.to_string())?,
+            SmtpSecurity::Tls => SmtpTransport::relay(host).map_err(|e| e.to_string())?,
+            SmtpSecurity::None => SmtpTransport::builder_dangerous(host),
+        };
+        let builder = builder.port(self.port).timeout(Some(SEND_TIMEOUT));
+        let builder = match self.username.as_deref().filter(|u| !u.is_empty()) {
+            Some(username) => {
+                builder.credentials(Credentials::new(username.to_string(), self.password.clone().unwrap_or_default()))
+            }
+            None => builder,
+        };
+        Ok(builder.build())
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Email {
+    pub to: Vec<String>,
+    pub cc: Vec<String>,
+    pub bcc: Vec<String>,
+    pub subject: String,
+    // Plain text
+    pub body: String,
+    pub attachments: Vec<EmailAttachment>,
+}
+
+#[derive(Debug, Clone)]
+pub struct EmailAttachment {
+    pub file_name: String,
+    pub content_type: String,
+    pub data: Vec<u8>,
+}
+
+impl EmailAttachment {
+    pub fn pdf(file_name: String, data: Vec<u8>) -> EmailAttachment {
+        EmailAttachment { file_name, content_type: "application/pdf".to_string(), data }
+    }
+}
+
+/// Renders the invoice PDF to attach to an email.
+pub type PdfRenderer = dyn Fn(&Database, &Invoice) -> Result<EmailAttachment, String> + Send + Sync;
+
+pub const VARIABLES: [&str; 9] = [
+    "invoice_number", "artist_name", "bill_to", "amount", "currency", "issue_date", "due_date", "days_overdue",
+    "days_until_due",
+];
+
+/// Subject and body of an invoice email; both may use the `{placeholders}` in `VARIABLES`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EmailTemplate {
+    pub subject: String,
+    pub body: String,
+}
+
+impl Default for EmailTemplate {
+    fn default() -> Self {
+        EmailTemplate {
+            subject: "Invoice {invoice_number} from {artist_name}".to_string(),
+            body: "Hello,\n\nPlease find attached invoice {invoice_number} for {amount}, due on {due_date}.\n\n\
+                   Kind regards,\n{artist_name}\n"
+                .to_string(),
+        }
+    }
+}
+
+impl EmailTemplate {
+    pub fn validate(&self) -> Vec<String> {
+        let mut v = Validator::new();
+        v.required("email subject", &self.subject, MAX_NAME);
+        v.max_length("email body", Some(&self.body), MAX_NOTES);
+        for name in unknown_placeholders(&self.subject).chain(unknown_placeholders(&self.body)) {
+            v.error(format!("email template uses unknown placeholder {{{}}}", name));
+        }
+        v.into_errors()
+    }
+}
+
+/// The `{placeholders}` in `text` that aren't in `VARIABLES`.
+pub fn unknown_placeholders(text: &str) -> impl Iterator<Item = &str> {
+    text.split('{')
+        .skip(1)
+        .filter_map(|rest| rest.split_once('}').map(|(name, _)| name))
+        .filter(|name| !VARIABLES.contains(name))
+}
+
+/// The values for a template's placeholders.
+pub fn variables(invoice: &Invoice, artist: &Artist, as_of: NaiveDate) -> HashMap<&'static str, String> {
+    let days = (as_of - invoice.due_date).num_days();
+    let bill_to = invoice.bill_to.as_deref().and_then(|b| b.lines().next()).unwrap_or_default();
+    let sender = artist.company_name.as_deref().filter(|c| !c.trim().is_empty()).unwrap_or(&artist.name);
+    HashMap::from([
+        ("invoice_number", invoice.invoice_number.clone()),
+        ("artist_name", sender.to_string()),
+        ("bill_to", bill_to.trim().to_string()),
+        ("amount", format_currency(Totals::for_invoice(invoice).gross, &invoice.currency)),
+        ("currency", invoice.currency.clone()),
+        ("issue_date", format_date(invoice.issue_date)),
+        ("due_date", format_date(invoice.due_date)),
+        ("days_overdue", days.max(0).to_string()),
+        ("days_until_due", (-days).max(0).to_string()),
+    ])
+}
+
+/// Replaces each `{name}` in `template` in one pass, so values are never
+/// expanded again; unknown names are left as they are.
+pub fn render(template: &str, variables: &HashMap<&str, String>) -> String {
+    let mut text = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        text.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        match after.split_once('}').and_then(|(name, tail)| Some((variables.get(name)?, tail))) {
+            Some((value, tail)) => {
+                text.push_str(value);
+                rest = tail;
+            }
+            None => {
+                text.push('{');
+                rest = after;
+            }
+        }
+    }
+    text.push_str(rest);
+    text
+}
+
+/// Builds the MIME message for `email`, sent from the configured address.
+pub fn build_message(settings: &SmtpSettings, email: &Email) -> Result<Message, String> {
+    if email.to.is_empty() {
+        return Err("The email has no recipient".to_string());
+    }
+    let mut builder = Message::builder()
+        .from(mailbox(settings.from_address.trim(), settings.from_name.as_deref())?)
+        .subject(email.subject.as_str());
+    if let Some(reply_to) = settings.reply_to.as_deref().map(str::trim).filter(|r| !r.is_empty()) {
+        builder = builder.reply_to(mailbox(reply_to, None)?);
+    }
+    for address in &email.to {
+        builder = builder.to(mailbox(address, None)?);
+    }
+    for address in &email.cc {
+        builder = builder.cc(mailbox(address, None)?);
+    }
+    for address in &email.bcc {
+        builder = builder.bcc(mailbox(address, None)?);
+    }
+
+    let text = SinglePart::plain(email.body.clone());
+    let message = if email.attachments.is_empty() {
+        builder.singlepart(text)
+    } else {
+        let mut parts = MultiPart::mixed().singlepart(text);
+        for attachment in &email.attachments {
+            let content_type = ContentType::parse(&attachment.content_type)
+                .map_err(|e| format!("Invalid content type '{}': {}", attachment.content_type, e))?;
+            parts = parts.singlepart(
+                Attachment::new(attachment.file_name.clone()).body(attachment.data.clone(), content_type),
+            );
+        }
+        builder.multipart(parts)
+    };
+    message.map_err(|e| format!("Failed to build the email: {}", e))
+}
+
+fn mailbox(address: &str, name: Option<&str>) -> Result<Mailbox, String> {
+    let address = address.trim();
+    let parsed = address.parse().map_err(|_| format!("'{}' is not a valid email address", address))?;
+    let name = name.map(str::trim).filter(|n| !n.is_empty()).map(str::to_string);
+    Ok(Mailbox::new(name, parsed))
+}
+
+/// Sends `message` over SMTP. Blocks until the server has accepted or refused it.
+pub fn send(settings: &SmtpSettings, message: &Message) -> Result<(), String> {
+    let transport = settings.transport()?;
+    transport
+        .send(message)
+        .map(|_| ())
+        .map_err(|e| format!("Failed to send email via {}:{}: {}", settings.host, settings.port, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings() -> SmtpSettings {
+        SmtpSettings {
+            host: "localhost".to_string(),
+            port: 1025,
+            security: SmtpSecurity::None,
+            username: None,
+            password: None,
+            from_address: "billing@example.com".to_string(),
+            from_name: Some("Nova Billing".to_string()),
+            reply_to: None,
+        }
+    }
+
+    #[test]
+    fn message_with_attachment() {
+        let email = Email {
+            to: vec!["client@example.com".to_string()],
+            bcc: vec!["archive@example.com".to_string()],
+            subject: "Invoice INV-001".to_string(),
+            body: "Please find the invoice attached.".to_string(),
+            attachments: vec![EmailAttachment::pdf("INV-001.pdf".to_string(), b"%PDF-1.7".to_vec())],
+            ..Default::default()
+        };
+        let message = String::from_utf8(build_message(&settings(), &email).unwrap().formatted()).unwrap();
+        assert!(message.contains("From: \"Nova Billing\" <billing@example.com>"), "{}", message);
+        assert!(message.contains("To: client@example.com"), "{}", message);
+        assert!(message.contains("Subject: Invoice INV-001"), "{}", message);
+        assert!(message.contains("filename=\"INV-001.pdf\""), "{}", message);
+        // Bcc recipients get the message without seeing each other
+        assert!(!message.contains("archive@example.com"), "{}", message);
+    }
+
+    #[test]
+    fn bad_addresses_are_reported() {
+        let email = Email { to: vec!["not an address".to_string()], ..Default::default() };
+        assert!(build_message(&settings(), &email).unwrap_err().contains("'not an address'"));
+        assert!(build_message(&settings(), &Email::default()).is_err());
+
+        let invalid = SmtpSettings { host: " ".to_string(), from_address: "billing".to_string(), ..settings() };
+        assert_eq!(invalid.validate().len(), 2);
+    }
+
+    #[test]
+    fn passwords_stay_out_of_the_app() {
+        let saved = SmtpSettings {
+            username: Some("billing".to_string()),
+            password: Some("secret".to_string()),
+            ..settings()
+        };
+        let redacted = serde_json::to_value(saved.clone().redacted()).unwrap();
+        assert_eq!(redacted["password"], serde_json::Value::Null);
+        assert_eq!(redacted["has_password"], true);
+        assert_eq!(redacted["host"], "localhost");
+
+        // Saving what the app was shown keeps the password
+        let mut update: SmtpSettings = serde_json::from_value(redacted).unwrap();
+        update.keep_password(Some(&saved));
+        assert_eq!(update.password.as_deref(), Some("secret"));
+
+        let mut other_server = SmtpSettings { host: "smtp.example.com".to_string(), ..update.clone() };
+        other_server.password = None;
+        other_server.keep_password(Some(&saved));
+        assert_eq!(other_server.password, None);
+
+        let mut cleared = SmtpSettings { password: Some(String::new()), ..update.clone() };
+        cleared.keep_password(Some(&saved));
+        assert_eq!(cleared.password, None);
+        assert!(!cleared.redacted().has_password);
+    }
+
+    #[test]
+    fn templates_fill_invoice_variables() {
+        let vars = HashMap::from([("invoice_number", "{days_overdue}".to_string()), ("days_overdue", "7".to_string())]);
+        let text = render("{invoice_number} is {days_overdue} days late {unknown}", &vars);
+        assert_eq!(text, "{days_overdue} is 7 days late {unknown}");
+        assert_eq!(unknown_placeholders("Invoice {invoice_number} {number}").collect::<Vec<_>>(), ["number"]);
+
+        assert!(EmailTemplate::default().validate().is_empty());
+        let template = EmailTemplate { subject: "Invoice {number}".to_string(), ..Default::default() };
+        assert_eq!(template.validate(), ["email template uses unknown placeholder {number}"]);
+    }
+}
@@ -0,0 +1,438 @@
+// src-tauri/src/facturx.rs
+use chrono::{NaiveDate, Utc};
+use printpdf::lopdf::{self, Dictionary, Object, Stream, StringFormat};
+use std::error::Error;
+
+use crate::einvoice::{
+    self, amount, bill_to_lines, escape, exemption_reason, is_credit_note, non_empty, seller_address_lines,
+    seller_name, tax_category, XmlWriter,
+};
+use crate::pdf_generator::{InvoiceData, Totals};
+
+// Output intent profile required by PDF/A
+const SRGB_ICC: &[u8] = include_bytes!("../assets/sRGB.icc");
+
+pub const XML_FILE_NAME: &str = "factur-x.xml";
+const EN16931_GUIDELINE: &str = "urn:cen.eu:en16931:2017";
+
+/// Checks everything EN 16931 requires that the app doesn't enforce on its
+/// own, and reports every missing field at once.
+pub fn validate(data: &InvoiceData) -> Result<(), String> {
+    let errors = einvoice::missing_fields(data);
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("Factur-X invoice is missing: {}", errors.join("; ")))
+    }
+}
+
+/// Builds the Cross Industry Invoice XML for the EN 16931 profile.
+/// Call `validate` first; this doesn't re-check mandatory fields.
+pub fn build_cii_xml(data: &InvoiceData) -> String {
+    let invoice = &data.invoice;
+    let artist = &data.artist;
+    let totals = Totals::for_invoice(invoice);
+    let currency = &invoice.currency;
+    let category = tax_category(invoice);
+    let rate = invoice.tax_rate.unwrap_or(0.0);
+    let exemption = exemption_reason(&category);
+    let mut xml = XmlWriter::default();
+
+    xml.line("<?xml version=\"1.0\" encoding=\"UTF-8\"?>");
+    xml.open(
+        "rsm:CrossIndustryInvoice \
+         xmlns:rsm=\"urn:un:unece:uncefact:data:standard:CrossIndustryInvoice:100\" \
+         xmlns:ram=\"urn:un:unece:uncefact:data:standard:ReusableAggregateBusinessInformationEntity:100\" \
+         xmlns:qdt=\"urn:un:unece:uncefact:data:standard:QualifiedDataType:100\" \
+         xmlns:udt=\"urn:un:unece:uncefact:data:standard:UnqualifiedDataType:100\"",
+    );
+
+    xml.open("rsm:ExchangedDocumentContext");
+    xml.open("ram:GuidelineSpecifiedDocumentContextParameter");
+    xml.element("ram:ID", EN16931_GUIDELINE);
+    xml.close("ram:GuidelineSpecifiedDocumentContextParameter");
+    xml.close("rsm:ExchangedDocumentContext");
+
+    xml.open("rsm:ExchangedDocument");
+    xml.element("ram:ID", &invoice.invoice_number);
+    // Commercial invoice or credit note
+    xml.element("ram:TypeCode", if is_credit_note(invoice) { "381" } else { "380" });
+    xml.date("ram:IssueDateTime", invoice.issue_date);
+    if let Some(notes) = non_empty(&invoice.notes) {
+        xml.open("ram:IncludedNote");
+        xml.element("ram:Content", notes);
+        xml.close("ram:IncludedNote");
+    }
+    xml.close("rsm:ExchangedDocument");
+
+    xml.open("rsm:SupplyChainTradeTransaction");
+
+    // Every line is a single unit; negative lines are credited as -1 units
+    for (index, item) in data.line_items.iter().enumerate() {
+        let quantity = if item.amount < 0.0 { "-1" } else { "1" };
+        xml.open("ram:IncludedSupplyChainTradeLineItem");
+        xml.open("ram:AssociatedDocumentLineDocument");
+        xml.element("ram:LineID", &(index + 1).to_string());
+        xml.close("ram:AssociatedDocumentLineDocument");
+        xml.open("ram:SpecifiedTradeProduct");
+        xml.element("ram:Name", item.description.trim());
+        let details = item.details();
+        if !details.is_empty() {
+            xml.element("ram:Description", &details.join("; "));
+        }
+        xml.close("ram:SpecifiedTradeProduct");
+        xml.open("ram:SpecifiedLineTradeAgreement");
+        xml.open("ram:NetPriceProductTradePrice");
+        xml.element("ram:ChargeAmount", &amount(item.amount.abs()));
+        xml.close("ram:NetPriceProductTradePrice");
+        xml.close("ram:SpecifiedLineTradeAgreement");
+        xml.open("ram:SpecifiedLineTradeDelivery");
+        xml.element_with("ram:BilledQuantity", "unitCode=\"C62\"", quantity);
+        xml.close("ram:SpecifiedLineTradeDelivery");
+        xml.open("ram:SpecifiedLineTradeSettlement");
+        xml.open("ram:ApplicableTradeTax");
+        xml.element("ram:TypeCode", "VAT");
+        xml.element("ram:CategoryCode", &category);
+        if category != "O" {
+            xml.element("ram:RateApplicablePercent", &rate.to_string());
+        }
+        xml.close("ram:ApplicableTradeTax");
+        xml.open("ram:SpecifiedTradeSettlementLineMonetarySummation");
+        xml.element("ram:LineTotalAmount", &amount(item.amount));
+        xml.close("ram:SpecifiedTradeSettlementLineMonetarySummation");
+        xml.close("ram:SpecifiedLineTradeSettlement");
+        xml.close("ram:IncludedSupplyChainTradeLineItem");
+    }
+
+    xml.open("ram:ApplicableHeaderTradeAgreement");
+    xml.open("ram:SellerTradeParty");
+    xml.element("ram:Name", seller_name(artist));
+    xml.address(&seller_address_lines(artist), artist.country_code.as_deref().unwrap_or(""));
+    if let Some(email) = non_empty(&artist.email) {
+        xml.open("ram:URIUniversalCommunication");
+        xml.element_with("ram:URIID", "schemeID=\"EM\"", email);
+        xml.close("ram:URIUniversalCommunication");
+    }
+    xml.tax_registration(non_empty(&artist.tax_id).unwrap_or(""));
+    xml.close("ram:SellerTradeParty");
+
+    let bill_to = bill_to_lines(invoice);
+    xml.open("ram:BuyerTradeParty");
+    xml.element("ram:Name", bill_to.first().copied().unwrap_or(""));
+    xml.address(bill_to.get(1..).unwrap_or(&[]), invoice.buyer_country_code.as_deref().unwrap_or(""));
+    if let Some(buyer_tax_id) = non_empty(&invoice.buyer_tax_id) {
+        xml.tax_registration(buyer_tax_id);
+    }
+    xml.close("ram:BuyerTradeParty");
+    xml.close("ram:ApplicableHeaderTradeAgreement");
+
+    xml.line("<ram:ApplicableHeaderTradeDelivery/>");
+
+    xml.open("ram:ApplicableHeaderTradeSettlement");
+    xml.element("ram:PaymentReference", &invoice.invoice_number);
+    xml.element("ram:InvoiceCurrencyCode", currency);
+
+    let bank = &artist.bank_details;
+    if let Some(iban) = non_empty(&bank.iban) {
+        xml.open("ram:SpecifiedTradeSettlementPaymentMeans");
+        xml.element("ram:TypeCode", "58"); // SEPA credit transfer
+        xml.open("ram:PayeePartyCreditorFinancialAccount");
+        xml.element("ram:IBANID", &iban.replace(' ', ""));
+        if let Some(holder) = non_empty(&bank.account_holder) {
+            xml.element("ram:AccountName", holder);
+        }
+        xml.close("ram:PayeePartyCreditorFinancialAccount");
+        if let Some(bic) = non_empty(&bank.bic) {
+            xml.open("ram:PayeeSpecifiedCreditorFinancialInstitution");
+            xml.element("ram:BICID", bic);
+            xml.close("ram:PayeeSpecifiedCreditorFinancialInstitution");
+        }
+        xml.close("ram:SpecifiedTradeSettlementPaymentMeans");
+    } else if let Some(account_number) = non_empty(&bank.account_number) {
+        xml.open("ram:SpecifiedTradeSettlementPaymentMeans");
+        xml.element("ram:TypeCode", "30"); // Credit transfer
+        xml.open("ram:PayeePartyCreditorFinancialAccount");
+        xml.element("ram:ProprietaryID", account_number);
+        if let Some(holder) = non_empty(&bank.account_holder) {
+            xml.element("ram:AccountName", holder);
+        }
+        xml.close("ram:PayeePartyCreditorFinancialAccount");
+        xml.close("ram:SpecifiedTradeSettlementPaymentMeans");
+    }
+
+    xml.open("ram:ApplicableTradeTax");
+    xml.element("ram:CalculatedAmount", &amount(totals.tax.unwrap_or(0.0)));
+    xml.element("ram:TypeCode", "VAT");
+    if let Some(reason) = exemption {
+        xml.element("ram:ExemptionReason", reason);
+    }
+    xml.element("ram:BasisAmount", &amount(totals.net));
+    xml.element("ram:CategoryCode", &category);
+    if category != "O" {
+        xml.element("ram:RateApplicablePercent", &rate.to_string());
+    }
+    xml.close("ram:ApplicableTradeTax");
+
+    xml.open("ram:SpecifiedTradePaymentTerms");
+    if let Some(terms) = &invoice.payment_terms {
+        xml.element("ram:Description", &terms.description(invoice.issue_date));
+    }
+    xml.date("ram:DueDateDateTime", invoice.due_date);
+    xml.close("ram:SpecifiedTradePaymentTerms");
+
+    let paid = if invoice.status == "paid" { totals.gross } else { 0.0 };
+    xml.open("ram:SpecifiedTradeSettlementHeaderMonetarySummation");
+    xml.element("ram:LineTotalAmount", &amount(totals.net));
+    xml.element("ram:TaxBasisTotalAmount", &amount(totals.net));
+    xml.element_with(
+        "ram:TaxTotalAmount",
+        &format!("currencyID=\"{}\"", escape(currency)),
+        &amount(totals.tax.unwrap_or(0.0)),
+    );
+    xml.element("ram:GrandTotalAmount", &amount(totals.gross));
+    if paid != 0.0 {
+        xml.element("ram:TotalPrepaidAmount", &amount(paid));
+    }
+    xml.element("ram:DuePayableAmount", &amount(totals.gross - paid));
+    xml.close("ram:SpecifiedTradeSettlementHeaderMonetarySummation");
+
+    xml.close("ram:ApplicableHeaderTradeSettlement");
+    xml.close("rsm:SupplyChainTradeTransaction");
+    xml.close("rsm:CrossIndustryInvoice");
+    xml.finish()
+}
+
+/// Turns a rendered PDF into a PDF/A-3b Factur-X document: sRGB output
+/// intent, Factur-X XMP metadata and the CII XML as an associated file.
+pub fn to_pdfa3(pdf: &[u8], xml: &str, title: &str, author: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+    use lopdf::Object::{Array, Integer, Name, Reference};
+
+    let mut doc = lopdf::Document::load_mem(pdf)?;
+    let now = Utc::now();
+    let pdf_date = now.format("D:%Y%m%d%H%M%S+00'00'").to_string();
+    let xmp_date = now.format("%Y-%m-%dT%H:%M:%S+00:00").to_string();
+
+    // PDF/A wants a binary comment right after the header. lopdf writes the
+    // version verbatim, and these characters are all above 127 in UTF-8
+    doc.version = "1.7\n%\u{e2}\u{e3}\u{cf}\u{d3}".to_string();
+
+    // CID fonts must map CIDs to glyphs explicitly; printpdf uses glyph IDs as CIDs
+    for object in doc.objects.values_mut() {
+        add_cid_to_gid_maps(object);
+    }
+
+    // Embedded XML
+    let mut params = Dictionary::new();
+    params.set("ModDate", Object::String(pdf_date.clone().into_bytes(), StringFormat::Literal));
+    params.set("Size", Integer(xml.len() as i64));
+    let mut file_dict = Dictionary::new();
+    file_dict.set("Type", Name(b"EmbeddedFile".to_vec()));
+    file_dict.set("Subtype", Name(b"text/xml".to_vec()));
+    file_dict.set("Params", Object::Dictionary(params));
+    let file_id = doc.add_object(Stream::new(file_dict, xml.as_bytes().to_vec()));
+
+    let mut ef = Dictionary::new();
+    ef.set("F", Reference(file_id));
+    ef.set("UF", Reference(file_id));
+    let mut filespec = Dictionary::new();
+    filespec.set("Type", Name(b"Filespec".to_vec()));
+    filespec.set("F", text(XML_FILE_NAME));
+    filespec.set("UF", text(XML_FILE_NAME));
+    filespec.set("Desc", text("Factur-X invoice"));
+    filespec.set("AFRelationship", Name(b"Alternative".to_vec()));
+    filespec.set("EF", Object::Dictionary(ef));
+    let filespec_id = doc.add_object(filespec);
+
+    // Output intent
+    let mut icc = Dictionary::new();
+    icc.set("N", Integer(3));
+    let icc_id = doc.add_object(Stream::new(icc, SRGB_ICC.to_vec()));
+    let mut intent = Dictionary::new();
+    intent.set("Type", Name(b"OutputIntent".to_vec()));
+    intent.set("S", Name(b"GTS_PDFA1".to_vec()));
+    intent.set("OutputConditionIdentifier", text("sRGB IEC61966-2.1"));
+    intent.set("RegistryName", text("http://www.color.org"));
+    intent.set("Info", text("sRGB IEC61966-2.1"));
+    intent.set("DestinationOutputProfile", Reference(icc_id));
+
+    // XMP metadata must stay uncompressed
+    let mut metadata = Dictionary::new();
+    metadata.set("Type", Name(b"Metadata".to_vec()));
+    metadata.set("Subtype", Name(b"XML".to_vec()));
+    let xmp = xmp_metadata(title, author, &xmp_date);
+    let metadata_id = doc.add_object(Stream::new(metadata, xmp.into_bytes()).with_compression(false));
+
+    let mut embedded_files = Dictionary::new();
+    embedded_files.set("Names", Array(vec![text(XML_FILE_NAME), Reference(filespec_id)]));
+    let mut names = Dictionary::new();
+    names.set("EmbeddedFiles", Object::Dictionary(embedded_files));
+
+    let catalog = doc.catalog_mut()?;
+    catalog.set("Names", Object::Dictionary(names));
+    catalog.set("AF", Array(vec![Reference(filespec_id)]));
+    catalog.set("OutputIntents", Array(vec![Object::Dictionary(intent)]));
+    catalog.set("Metadata", Reference(metadata_id));
+    // Optional content configurations need a name under PDF/A-2 and later
+    if let Ok(Object::Dictionary(oc_properties)) = catalog.get_mut(b"OCProperties") {
+        if let Ok(Object::Dictionary(default_config)) = oc_properties.get_mut(b"D") {
+            default_config.set("Name", text("Default"));
+        }
+    }
+
+    // Document info has to match the XMP metadata
+    let mut info = Dictionary::new();
+    info.set("Title", text(title));
+    info.set("Author", text(author));
+    info.set("Creator", text("Project Invoicer"));
+    info.set("Producer", text("Project Invoicer"));
+    info.set("CreationDate", Object::String(pdf_date.clone().into_bytes(), StringFormat::Literal));
+    info.set("ModDate", Object::String(pdf_date.into_bytes(), StringFormat::Literal));
+    let info_id = doc.add_object(info);
+    doc.trailer.set("Info", Reference(info_id));
+
+    doc.prune_objects();
+    let mut output = Vec::new();
+    doc.save_to(&mut output)?;
+    Ok(output)
+}
+
+/// Sets an identity CIDToGIDMap on every CIDFontType2 dictionary, including
+/// the ones nested inside a Type0 font's DescendantFonts.
+fn add_cid_to_gid_maps(object: &mut Object) {
+    match object {
+        Object::Dictionary(dict) => {
+            let is_cid_font = matches!(dict.get(b"Subtype"), Ok(Object::Name(name)) if name == b"CIDFontType2");
+            if is_cid_font && !dict.has(b"CIDToGIDMap") {
+                dict.set("CIDToGIDMap", Object::Name(b"Identity".to_vec()));
+            }
+            for (_, value) in dict.iter_mut() {
+                add_cid_to_gid_maps(value);
+            }
+        }
+        Object::Array(items) => items.iter_mut().for_each(add_cid_to_gid_maps),
+        _ => {}
+    }
+}
+
+fn xmp_metadata(title: &str, author: &str, date: &str) -> String {
+    format!(
+        r#"<?xpacket begin="{bom}" id="W5M0MpCehiHzreSzNTczkc9d"?>
+<x:xmpmeta xmlns:x="adobe:ns:meta/">
+  <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+    <rdf:Description rdf:about="" xmlns:pdfaid="http://www.aiim.org/pdfa/ns/id/">
+      <pdfaid:part>3</pdfaid:part>
+      <pdfaid:conformance>B</pdfaid:conformance>
+    </rdf:Description>
+    <rdf:Description rdf:about="" xmlns:dc="http://purl.org/dc/elements/1.1/">
+      <dc:format>application/pdf</dc:format>
+      <dc:title><rdf:Alt><rdf:li xml:lang="x-default">{title}</rdf:li></rdf:Alt></dc:title>
+      <dc:creator><rdf:Seq><rdf:li>{author}</rdf:li></rdf:Seq></dc:creator>
+    </rdf:Description>
+    <rdf:Description rdf:about="" xmlns:pdf="http://ns.adobe.com/pdf/1.3/">
+      <pdf:Producer>Project Invoicer</pdf:Producer>
+    </rdf:Description>
+    <rdf:Description rdf:about="" xmlns:xmp="http://ns.adobe.com/xap/1.0/">
+      <xmp:CreatorTool>Project Invoicer</xmp:CreatorTool>
+      <xmp:CreateDate>{date}</xmp:CreateDate>
+      <xmp:ModifyDate>{date}</xmp:ModifyDate>
+      <xmp:MetadataDate>{date}</xmp:MetadataDate>
+    </rdf:Description>
+    <rdf:Description rdf:about="" xmlns:fx="urn:factur-x:pdfa:CrossIndustryDocument:invoice:1p0#">
+      <fx:DocumentType>INVOICE</fx:DocumentType>
+      <fx:DocumentFileName>{file_name}</fx:DocumentFileName>
+      <fx:Version>1.0</fx:Version>
+      <fx:ConformanceLevel>EN 16931</fx:ConformanceLevel>
+    </rdf:Description>
+    <rdf:Description rdf:about=""
+        xmlns:pdfaExtension="http://www.aiim.org/pdfa/ns/extension/"
+        xmlns:pdfaSchema="http://www.aiim.org/pdfa/ns/schema#"
+        xmlns:pdfaProperty="http://www.aiim.org/pdfa/ns/property#">
+      <pdfaExtension:schemas>
+        <rdf:Bag>
+          <rdf:li rdf:parseType="Resource">
+            <pdfaSchema:schema>Factur-X PDFA Extension Schema</pdfaSchema:schema>
+            <pdfaSchema:namespaceURI>urn:factur-x:pdfa:CrossIndustryDocument:invoice:1p0#</pdfaSchema:namespaceURI>
+            <pdfaSchema:prefix>fx</pdfaSchema:prefix>
+            <pdfaSchema:property>
+              <rdf:Seq>
+                {properties}
+              </rdf:Seq>
+            </pdfaSchema:property>
+          </rdf:li>
+        </rdf:Bag>
+      </pdfaExtension:schemas>
+    </rdf:Description>
+  </rdf:RDF>
+</x:xmpmeta>
+<?xpacket end="w"?>"#,
+        bom = '\u{feff}',
+        title = escape(title),
+        author = escape(author),
+        date = date,
+        file_name = XML_FILE_NAME,
+        properties = [
+            ("DocumentFileName", "The name of the embedded XML document"),
+            ("DocumentType", "The type of the hybrid document in capital letters, e.g. INVOICE or ORDER"),
+            ("Version", "The actual version of the standard applying to the embedded XML document"),
+            ("ConformanceLevel", "The conformance level of the embedded XML document"),
+        ]
+        .iter()
+        .map(|(name, description)| {
+            format!(
+                "<rdf:li rdf:parseType=\"Resource\"><pdfaProperty:name>{}</pdfaProperty:name>\
+                 <pdfaProperty:valueType>Text</pdfaProperty:valueType>\
+                 <pdfaProperty:category>external</pdfaProperty:category>\
+                 <pdfaProperty:description>{}</pdfaProperty:description></rdf:li>",
+                name, description
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n                "),
+    )
+}
+
+fn text(value: &str) -> Object {
+    // PDF text strings are PDFDocEncoding or UTF-16BE with a byte order mark
+    if value.is_ascii() {
+        Object::String(value.as_bytes().to_vec(), StringFormat::Literal)
+    } else {
+        let mut bytes = vec![0xFE, 0xFF];
+        bytes.extend(value.encode_utf16().flat_map(|unit| unit.to_be_bytes()));
+        Object::String(bytes, StringFormat::Hexadecimal)
+    }
+}
+
+/// CII-specific elements
+trait CiiElements {
+    fn date(&mut self, tag: &str, date: NaiveDate);
+    fn address(&mut self, lines: &[&str], country_code: &str);
+    fn tax_registration(&mut self, tax_id: &str);
+}
+
+impl CiiElements for XmlWriter {
+    /// A date in UN/CEFACT format 102 (YYYYMMDD).
+    fn date(&mut self, tag: &str, date: NaiveDate) {
+        self.open(tag);
+        self.element_with("udt:DateTimeString", "format=\"102\"", &date.format("%Y%m%d").to_string());
+        self.close(tag);
+    }
+
+    /// Free-form address lines go into LineOne to LineThree; CII has no more.
+    fn address(&mut self, lines: &[&str], country_code: &str) {
+        self.open("ram:PostalTradeAddress");
+        for (tag, line) in ["ram:LineOne", "ram:LineTwo"].iter().zip(lines) {
+            self.element(tag, line);
+        }
+        if lines.len() > 2 {
+            self.element("ram:LineThree", &lines[2..].join(", "));
+        }
+        self.element("ram:CountryID", country_code);
+        self.close("ram:PostalTradeAddress");
+    }
+
+    fn tax_registration(&mut self, tax_id: &str) {
+        self.open("ram:SpecifiedTaxRegistration");
+        self.element_with("ram:ID", "schemeID=\"VA\"", tax_id);
+        self.close("ram:SpecifiedTaxRegistration");
+    }
+}
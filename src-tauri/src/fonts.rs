@@ -0,0 +1,223 @@
+// src-tauri/src/fonts.rs
+use owned_ttf_parser::{AsFaceRef, OwnedFace};
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
+
+// Bundled Unicode fonts (Latin, Greek, Cyrillic, Hebrew, Arabic, symbols), compiled in
+// rather than shipped as resources. They have no CJK glyphs.
+const DEJAVU_SANS: &[u8] = include_bytes!("../assets/fonts/DejaVuSans.ttf");
+const DEJAVU_SANS_BOLD: &[u8] = include_bytes!("../assets/fonts/DejaVuSans-Bold.ttf");
+
+/// A parsed TrueType/OpenType font used both for embedding and for measuring text.
+pub struct FontFace {
+    face: OwnedFace,
+}
+
+impl FontFace {
+    pub fn from_bytes(data: Vec<u8>) -> Result<Self, String> {
+        let face = OwnedFace::from_vec(data, 0)
+            .map_err(|e| format!("Failed to parse font: {}", e))?;
+        Ok(FontFace { face })
+    }
+
+    // Parsed on first use and shared by every render after that
+    pub fn bundled_regular() -> Arc<Self> {
+        static FACE: OnceLock<Arc<FontFace>> = OnceLock::new();
+        let face = FACE.get_or_init(|| {
+            Arc::new(Self::from_bytes(DEJAVU_SANS.to_vec()).expect("Bundled regular font is invalid"))
+        });
+        face.clone()
+    }
+
+    pub fn bundled_bold() -> Arc<Self> {
+        static FACE: OnceLock<Arc<FontFace>> = OnceLock::new();
+        let face = FACE.get_or_init(|| {
+            Arc::new(Self::from_bytes(DEJAVU_SANS_BOLD.to_vec()).expect("Bundled bold font is invalid"))
+        });
+        face.clone()
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        self.face.as_slice()
+    }
+
+    pub fn has_glyph(&self, c: char) -> bool {
+        c.is_whitespace() || self.face.as_face_ref().glyph_index(c).is_some()
+    }
+
+    /// Advance width of `text` in points when drawn at `size`.
+    pub fn text_width(&self, text: &str, size: f32) -> f32 {
+        let face = self.face.as_face_ref();
+        let units_per_em = face.units_per_em() as f32;
+        let units: u32 = text
+            .chars()
+            .filter_map(|c| face.glyph_index(c))
+            .map(|glyph| face.glyph_hor_advance(glyph).unwrap_or(0) as u32)
+            .sum();
+        units as f32 * size / units_per_em
+    }
+}
+
+/// Loads every `.ttf`/`.otf` file in `dir` to use as fallbacks for glyphs the
+/// bundled fonts don't cover. No CJK font is bundled, so Chinese, Japanese and
+/// Korean text only renders once a font such as Noto Sans CJK is dropped into `dir`.
+pub fn load_fallback_faces(dir: &Path) -> Vec<Arc<FontFace>> {
+    let mut paths: Vec<_> = match std::fs::read_dir(dir) {
+        Ok(entries) => entries.filter_map(|e| e.ok()).map(|e| e.path()).collect(),
+        Err(_) => return Vec::new(),
+    };
+    paths.sort();
+
+    paths
+        .into_iter()
+        .filter(|p| {
+            let ext = p.extension().and_then(|e| e.to_str()).unwrap_or("").to_lowercase();
+            ext == "ttf" || ext == "otf"
+        })
+        .filter_map(|p| std::fs::read(&p).ok())
+        .filter_map(|data| FontFace::from_bytes(data).ok())
+        .map(Arc::new)
+        .collect()
+}
+
+/// A primary font followed by fallbacks, tried in order for each character.
+#[derive(Clone)]
+pub struct FontStack {
+    faces: Vec<Arc<FontFace>>,
+}
+
+impl FontStack {
+    pub fn new(primary: Arc<FontFace>, fallbacks: &[Arc<FontFace>]) -> Self {
+        let mut faces = vec![primary];
+        faces.extend(fallbacks.iter().cloned());
+        FontStack { faces }
+    }
+
+    pub fn faces(&self) -> &[Arc<FontFace>] {
+        &self.faces
+    }
+
+    fn face_index_for(&self, c: char) -> usize {
+        self.faces.iter().position(|f| f.has_glyph(c)).unwrap_or(0)
+    }
+
+    /// Splits `text` into runs that can each be drawn with a single face.
+    /// Returns the index of the face in the stack together with the run.
+    pub fn runs<'a>(&self, text: &'a str) -> Vec<(usize, &'a str)> {
+        let mut runs = Vec::new();
+        let mut run_start = 0;
+        let mut run_face = None;
+
+        for (offset, c) in text.char_indices() {
+            // Whitespace stays with whatever run it is in
+            if c.is_whitespace() && run_face.is_some() {
+                continue;
+            }
+            let face = self.face_index_for(c);
+            match run_face {
+                Some(current) if current != face => {
+                    runs.push((current, &text[run_start..offset]));
+                    run_start = offset;
+                    run_face = Some(face);
+                }
+                None => run_face = Some(face),
+                _ => {}
+            }
+        }
+
+        if let Some(face) = run_face {
+            runs.push((face, &text[run_start..]));
+        }
+        runs
+    }
+
+    pub fn text_width(&self, text: &str, size: f32) -> f32 {
+        self.runs(text)
+            .into_iter()
+            .map(|(face, run)| self.faces[face].text_width(run, size))
+            .sum()
+    }
+
+    /// Word-wraps `text` so no line is wider than `max_width` points.
+    /// Explicit newlines are kept and words wider than a full line are broken
+    /// between characters, which is also how CJK text without spaces wraps.
+    pub fn wrap(&self, text: &str, size: f32, max_width: f32) -> Vec<String> {
+        let space = self.text_width(" ", size);
+        let mut lines = Vec::new();
+
+        for paragraph in text.lines() {
+            let mut line = String::new();
+            // Width of `line` so far, so each word is only measured once
+            let mut width = 0.0;
+
+            for word in paragraph.split_whitespace() {
+                let word_width = self.text_width(word, size);
+                let joined = if line.is_empty() { word_width } else { width + space + word_width };
+                if joined <= max_width {
+                    if !line.is_empty() {
+                        line.push(' ');
+                    }
+                    line.push_str(word);
+                    width = joined;
+                    continue;
+                }
+
+                if !line.is_empty() {
+                    lines.push(std::mem::take(&mut line));
+                }
+
+                if word_width <= max_width {
+                    line = word.to_string();
+                    width = word_width;
+                } else {
+                    width = 0.0;
+                    for c in word.chars() {
+                        let char_width = self.text_width(c.encode_utf8(&mut [0; 4]), size);
+                        if width + char_width > max_width && !line.is_empty() {
+                            lines.push(std::mem::take(&mut line));
+                            width = 0.0;
+                        }
+                        line.push(c);
+                        width += char_width;
+                    }
+                }
+            }
+
+            lines.push(line);
+        }
+
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stack() -> FontStack {
+        FontStack::new(FontFace::bundled_regular(), &[])
+    }
+
+    #[test]
+    fn wrap_keeps_lines_within_the_width() {
+        let stack = stack();
+        let text = "The quick brown fox jumps over the lazy dog ".repeat(20);
+        let width = stack.text_width("The quick brown fox", 10.0);
+
+        let lines = stack.wrap(&text, 10.0, width);
+        assert_eq!(lines[0], "The quick brown fox");
+        assert!(lines.iter().all(|line| stack.text_width(line, 10.0) <= width));
+        assert_eq!(lines.join(" "), text.trim_end());
+    }
+
+    #[test]
+    fn wrap_breaks_long_words_and_keeps_newlines() {
+        let stack = stack();
+        let width = stack.text_width("abcd", 10.0);
+
+        assert_eq!(stack.wrap("abcdefghij xy\nz", 10.0, width), ["abcd", "efgh", "ij xy", "z"]);
+        assert_eq!(stack.wrap("", 10.0, width), Vec::<String>::new());
+        // A single character wider than the line still goes somewhere
+        assert_eq!(stack.wrap("WW", 10.0, 1.0), ["W", "W"]);
+    }
+}
@@ -0,0 +1,668 @@
+// src-tauri/src/import.rs
+use calamine::{open_workbook_auto, Data, DataType, Reader};
+use chrono::NaiveDate;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use crate::database::{self, Artist, Database, NewArtist, NewInvoice, NewProject, Project};
+use crate::pdf_generator::LineItem;
+
+const ARTIST_FIELDS: [&str; 7] = ["name", "company_name", "email", "phone", "address", "wire_details", "notes"];
+const PROJECT_FIELDS: [&str; 7] = ["artist", "name", "description", "status", "start_date", "end_date", "budget"];
+const INVOICE_FIELDS: [&str; 14] = [
+    "artist", "project", "invoice_number", "amount", "status", "issue_date", "due_date", "paid_date", "currency",
+    "bill_to", "bill_to_email", "notes", "items", "description",
+];
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ImportRequest {
+    // A .csv file or an .xlsx/.xls/.ods workbook
+    pub path: String,
+    // Worksheet to read; the first one when unset
+    pub sheet: Option<String>,
+    // Field -> column header in the file. Fields left out are read from a
+    // column named after the field, when there is one.
+    #[serde(default)]
+    pub mapping: HashMap<String, String>,
+    // Validate and report without writing anything
+    #[serde(default)]
+    pub dry_run: bool,
+    #[serde(default = "default_delimiter")]
+    pub delimiter: char,
+    #[serde(default = "default_decimal_separator")]
+    pub decimal_separator: char,
+    // chrono format for dates stored as text; YYYY-MM-DD is always accepted
+    #[serde(default = "default_date_format")]
+    pub date_format: String,
+}
+
+fn default_delimiter() -> char {
+    ','
+}
+
+fn default_decimal_separator() -> char {
+    '.'
+}
+
+fn default_date_format() -> String {
+    "%Y-%m-%d".to_string()
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RowError {
+    // As numbered in the file, the header being row 1
+    pub row: usize,
+    pub messages: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DuplicateRow {
+    pub row: usize,
+    pub key: String,
+    // The earlier row with the same key; None when the record already exists
+    pub duplicate_of_row: Option<usize>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ImportReport {
+    pub total_rows: usize,
+    pub valid_rows: usize,
+    pub imported: usize,
+    pub errors: Vec<RowError>,
+    // Skipped rows; they don't block the import
+    pub duplicates: Vec<DuplicateRow>,
+    // False for dry runs and whenever any row failed validation
+    pub committed: bool,
+}
+
+impl ImportReport {
+    // Records the row's errors; returns true when it has any
+    fn reject(&mut self, row: usize, messages: Vec<String>) -> bool {
+        if messages.is_empty() {
+            return false;
+        }
+        self.errors.push(RowError { row, messages });
+        true
+    }
+
+    // Returns true when `key` is already in the database or in an earlier row
+    fn duplicate(
+        &mut self,
+        row: usize,
+        key: String,
+        display: String,
+        existing: &HashSet<String>,
+        seen: &mut HashMap<String, usize>,
+    ) -> bool {
+        let duplicate_of_row = if existing.contains(&key) {
+            None
+        } else if let Some(first) = seen.get(&key) {
+            Some(*first)
+        } else {
+            seen.insert(key, row);
+            return false;
+        };
+        self.duplicates.push(DuplicateRow { row, key: display, duplicate_of_row });
+        true
+    }
+}
+
+enum Cell {
+    Text(String),
+    Number(f64),
+    Date(NaiveDate),
+    Empty,
+}
+
+struct Table {
+    headers: Vec<String>,
+    // Row number in the file and its cells; blank rows are left out
+    rows: Vec<(usize, Vec<Cell>)>,
+}
+
+pub fn import_artists(db: &Database, request: &ImportRequest) -> Result<ImportReport, String> {
+    let table = read_table(request)?;
+    let columns = resolve_columns(&table.headers, &ARTIST_FIELDS, &["name"], &request.mapping)?;
+    let existing: HashSet<String> = db.get_all_artists()
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .filter_map(|a| a.email.map(|e| e.trim().to_lowercase()))
+        .collect();
+
+    let mut report = ImportReport { total_rows: table.rows.len(), ..Default::default() };
+    let mut seen = HashMap::new();
+    let mut pending = Vec::new();
+    for (number, cells) in &table.rows {
+        let mut row = RowReader::new(cells, &columns, request);
+        let artist = NewArtist {
+            name: row.text("name").unwrap_or_default(),
+            company_name: row.text("company_name"),
+            email: row.text("email"),
+            phone: row.text("phone"),
+            address: row.text("address"),
+            wire_details: row.text("wire_details"),
+            notes: row.text("notes"),
+        };
+        row.errors.extend(artist.validate());
+        if report.reject(*number, row.errors) {
+            continue;
+        }
+        if let Some(email) = &artist.email {
+            if report.duplicate(*number, email.to_lowercase(), email.clone(), &existing, &mut seen) {
+                continue;
+            }
+        }
+        pending.push((*number, artist));
+    }
+
+    commit(db, report, request, pending, |conn, artist| database::insert_artist(conn, artist).map(|_| ()))
+}
+
+pub fn import_projects(db: &Database, request: &ImportRequest) -> Result<ImportReport, String> {
+    let table = read_table(request)?;
+    let columns = resolve_columns(&table.headers, &PROJECT_FIELDS, &["artist", "name"], &request.mapping)?;
+    let artists = db.get_all_artists().map_err(|e| e.to_string())?;
+    let existing: HashSet<String> = db.get_all_projects()
+        .map_err(|e| e.to_string())?
+        .iter()
+        .map(|p| project_key(&p.artist_id, &p.name))
+        .collect();
+
+    let mut report = ImportReport { total_rows: table.rows.len(), ..Default::default() };
+    let mut seen = HashMap::new();
+    let mut pending = Vec::new();
+    for (number, cells) in &table.rows {
+        let mut row = RowReader::new(cells, &columns, request);
+        let artist_value = row.text("artist");
+        let artist = artist_value.as_deref().map(|a| find_artist(&artists, a));
+        let mut project = NewProject {
+            artist_id: String::new(),
+            name: row.text("name").unwrap_or_default(),
+            description: row.text("description"),
+            status: row.text("status").unwrap_or_else(|| "active".to_string()),
+            start_date: row.date("start_date"),
+            end_date: row.date("end_date"),
+            budget: row.number("budget").unwrap_or(0.0),
+        };
+        let artist = match artist {
+            Some(Ok(artist)) => Some(artist),
+            Some(Err(e)) => {
+                row.errors.push(e);
+                None
+            }
+            None => None,
+        };
+        // An unmatched artist was reported above, not as a missing one
+        project.artist_id = artist.map(|a| a.id.clone()).or(artist_value).unwrap_or_default();
+        row.errors.extend(project.validate());
+        if report.reject(*number, row.errors) {
+            continue;
+        }
+        let Some(artist) = artist else { continue };
+        let display = format!("{} / {}", artist.name, project.name);
+        let key = project_key(&project.artist_id, &project.name);
+        if report.duplicate(*number, key, display, &existing, &mut seen) {
+            continue;
+        }
+        pending.push((*number, project));
+    }
+
+    commit(db, report, request, pending, |conn, project| database::insert_project(conn, project).map(|_| ()))
+}
+
+fn project_key(artist_id: &str, name: &str) -> String {
+    format!("{}\n{}", artist_id, name.trim().to_lowercase())
+}
+
+pub fn import_invoices(db: &Database, request: &ImportRequest) -> Result<ImportReport, String> {
+    let table = read_table(request)?;
+    let required = ["artist", "invoice_number", "amount", "issue_date", "due_date"];
+    let columns = resolve_columns(&table.headers, &INVOICE_FIELDS, &required, &request.mapping)?;
+    let artists = db.get_all_artists().map_err(|e| e.to_string())?;
+    let projects = db.get_all_projects().map_err(|e| e.to_string())?;
+    let existing: HashSet<String> = db.get_all_invoices()
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .map(|i| i.invoice_number)
+        .collect();
+
+    let mut report = ImportReport { total_rows: table.rows.len(), ..Default::default() };
+    let mut seen = HashMap::new();
+    let mut pending = Vec::new();
+    for (number, cells) in &table.rows {
+        let mut row = RowReader::new(cells, &columns, request);
+        let amount = row.required_number("amount");
+        let issue_date = row.required_date("issue_date");
+        let due_date = row.required_date("due_date");
+        let paid_date = row.date("paid_date");
+
+        let mut artist_found = false;
+        let artist_id = match row.text("artist").map(|a| (find_artist(&artists, &a), a)) {
+            Some((Ok(artist), _)) => {
+                artist_found = true;
+                artist.id.clone()
+            }
+            // Reported here rather than as a missing artist
+            Some((Err(e), value)) => {
+                row.errors.push(e);
+                value
+            }
+            None => String::new(),
+        };
+        let project_id = match row.text("project") {
+            Some(project) if artist_found => match find_project(&projects, &artist_id, &project) {
+                Ok(project) => Some(project.id.clone()),
+                Err(e) => {
+                    row.errors.push(e);
+                    None
+                }
+            },
+            _ => None,
+        };
+
+        let currency = row.text("currency").map(|c| c.to_uppercase());
+        if let Some(currency) = currency.as_deref() {
+            if currency.len() != 3 || !currency.chars().all(|c| c.is_ascii_alphabetic()) {
+                row.errors.push(format!("'{}' is not an ISO 4217 currency code", currency));
+            }
+        }
+
+        // Missing or unreadable values were reported by the reader, which rejects the row;
+        // the placeholders below keep validation from reporting them a second time
+        let amount = amount.unwrap_or_default();
+        let items = row.items(amount);
+        let invoice = NewInvoice {
+            artist_id,
+            project_id,
+            invoice_number: row.text("invoice_number").unwrap_or_default(),
+            amount,
+            status: row.text("status").unwrap_or_else(|| {
+                if paid_date.is_some() { "paid" } else { "pending" }.to_string()
+            }),
+            issue_date: issue_date.or(due_date).unwrap_or_default(),
+            due_date: Some(due_date.or(issue_date).unwrap_or_default()),
+            payment_terms: None,
+            currency,
+            client_id: None,
+            bill_to: row.text("bill_to"),
+            bill_to_email: row.text("bill_to_email"),
+            items,
+            notes: row.text("notes"),
+        };
+        row.errors.extend(invoice.validate());
+        if report.reject(*number, row.errors) {
+            continue;
+        }
+        let key = invoice.invoice_number.clone();
+        if report.duplicate(*number, key.clone(), key, &existing, &mut seen) {
+            continue;
+        }
+        pending.push((*number, (invoice, paid_date)));
+    }
+
+    commit(db, report, request, pending, |conn, (invoice, paid_date)| {
+        let invoice = database::insert_invoice(conn, invoice)?;
+        database::set_imported_paid_date(conn, &invoice.id, paid_date)
+    })
+}
+
+/// Writes every pending row in one transaction, unless this is a dry run or
+/// a row failed validation. Nothing is kept when an insert fails.
+fn commit<T>(
+    db: &Database,
+    mut report: ImportReport,
+    request: &ImportRequest,
+    pending: Vec<(usize, T)>,
+    insert: impl Fn(&Connection, T) -> rusqlite::Result<()>,
+) -> Result<ImportReport, String> {
+    report.valid_rows = pending.len();
+    if request.dry_run || !report.errors.is_empty() || pending.is_empty() {
+        return Ok(report);
+    }
+
+    let mut failed_row = None;
+    let imported = db.with_transaction(|conn| {
+        let count = pending.len();
+        for (number, row) in pending {
+            failed_row = Some(number);
+            insert(conn, row)?;
+        }
+        Ok(count)
+    });
+    match imported {
+        Ok(count) => {
+            report.imported = count;
+            report.committed = true;
+            Ok(report)
+        }
+        Err(e) => match failed_row {
+            Some(row) => Err(format!("Import failed at row {}, nothing was imported: {}", row, e)),
+            None => Err(format!("Import failed, nothing was imported: {}", e)),
+        },
+    }
+}
+
+/// Matches an artist by ID, name or email.
+fn find_artist<'a>(artists: &'a [Artist], value: &str) -> Result<&'a Artist, String> {
+    if let Some(artist) = artists.iter().find(|a| a.id == value) {
+        return Ok(artist);
+    }
+    let matches: Vec<&Artist> = artists
+        .iter()
+        .filter(|a| {
+            a.name.eq_ignore_ascii_case(value) || a.email.as_deref().is_some_and(|e| e.eq_ignore_ascii_case(value))
+        })
+        .collect();
+    match matches.as_slice() {
+        [artist] => Ok(artist),
+        [] => Err(format!("no artist matches '{}'", value)),
+        _ => Err(format!("'{}' matches several artists; use their email or ID", value)),
+    }
+}
+
+/// Matches one of the artist's projects by ID or name.
+fn find_project<'a>(projects: &'a [Project], artist_id: &str, value: &str) -> Result<&'a Project, String> {
+    projects
+        .iter()
+        .filter(|p| p.artist_id == artist_id)
+        .find(|p| p.id == value || p.name.eq_ignore_ascii_case(value))
+        .ok_or_else(|| format!("the artist has no project '{}'", value))
+}
+
+/// Finds the column for each field: the mapped header, or a header with the
+/// field's own name. Reports every unknown or missing column at once.
+fn resolve_columns(
+    headers: &[String],
+    fields: &[&'static str],
+    required: &[&str],
+    mapping: &HashMap<String, String>,
+) -> Result<HashMap<&'static str, usize>, String> {
+    let position = |name: &str| headers.iter().position(|h| h.eq_ignore_ascii_case(name.trim()));
+    let mut problems = Vec::new();
+    for field in mapping.keys() {
+        if !fields.contains(&field.as_str()) {
+            problems.push(format!("unknown field '{}' (available: {})", field, fields.join(", ")));
+        }
+    }
+
+    let mut columns = HashMap::new();
+    for field in fields {
+        let index = match mapping.get(*field).filter(|h| !h.trim().is_empty()) {
+            Some(header) => {
+                let index = position(header);
+                if index.is_none() {
+                    problems.push(format!("column '{}' mapped to {} is not in the file", header, field));
+                }
+                index
+            }
+            None => position(field),
+        };
+        match index {
+            Some(index) => {
+                columns.insert(*field, index);
+            }
+            None if required.contains(field) && !mapping.contains_key(*field) => {
+                problems.push(format!("no column for required field {}", field));
+            }
+            None => {}
+        }
+    }
+
+    if problems.is_empty() {
+        Ok(columns)
+    } else {
+        Err(format!("Invalid column mapping: {}", problems.join("; ")))
+    }
+}
+
+/// Reads one row's fields, collecting parse errors as it goes.
+struct RowReader<'a> {
+    cells: &'a [Cell],
+    columns: &'a HashMap<&'static str, usize>,
+    request: &'a ImportRequest,
+    errors: Vec<String>,
+}
+
+impl<'a> RowReader<'a> {
+    fn new(cells: &'a [Cell], columns: &'a HashMap<&'static str, usize>, request: &'a ImportRequest) -> Self {
+        RowReader { cells, columns, request, errors: Vec::new() }
+    }
+
+    fn cell(&self, field: &str) -> &Cell {
+        self.columns.get(field).and_then(|i| self.cells.get(*i)).unwrap_or(&Cell::Empty)
+    }
+
+    fn text(&self, field: &str) -> Option<String> {
+        match self.cell(field) {
+            Cell::Text(text) => Some(text.clone()),
+            // Whole numbers, e.g. invoice numbers stored as numbers in a spreadsheet
+            Cell::Number(n) if n.fract() == 0.0 && n.abs() < 1e15 => Some(format!("{}", *n as i64)),
+            Cell::Number(n) => Some(n.to_string()),
+            Cell::Date(date) => Some(date.format("%Y-%m-%d").to_string()),
+            Cell::Empty => None,
+        }
+    }
+
+    fn number(&mut self, field: &str) -> Option<f64> {
+        let text = match self.cell(field) {
+            Cell::Number(n) => return Some(*n),
+            Cell::Empty => return None,
+            _ => self.text(field)?,
+        };
+        let value = parse_number(&text, self.request.decimal_separator);
+        if value.is_none() {
+            self.errors.push(format!("{} '{}' is not a number", field, text));
+        }
+        value
+    }
+
+    fn required_number(&mut self, field: &str) -> Option<f64> {
+        if matches!(self.cell(field), Cell::Empty) {
+            self.errors.push(format!("{} is required", field));
+        }
+        self.number(field)
+    }
+
+    fn date(&mut self, field: &str) -> Option<NaiveDate> {
+        let text = match self.cell(field) {
+            Cell::Date(date) => return Some(*date),
+            Cell::Empty => return None,
+            _ => self.text(field)?,
+        };
+        let date = NaiveDate::parse_from_str(&text, &self.request.date_format)
+            .or_else(|_| NaiveDate::parse_from_str(text.get(..10).unwrap_or(&text), "%Y-%m-%d"));
+        match date {
+            Ok(date) => Some(date),
+            Err(_) => {
+                let format = &self.request.date_format;
+                self.errors.push(format!("{} '{}' doesn't match the date format {}", field, text, format));
+                None
+            }
+        }
+    }
+
+    fn required_date(&mut self, field: &str) -> Option<NaiveDate> {
+        if matches!(self.cell(field), Cell::Empty) {
+            self.errors.push(format!("{} is required", field));
+        }
+        self.date(field)
+    }
+
+    /// Line items as stored on invoices: a JSON array from the items column,
+    /// or a single line from the description column.
+    fn items(&mut self, amount: f64) -> String {
+        if let Some(items) = self.text("items") {
+            if let Err(e) = serde_json::from_str::<Vec<LineItem>>(&items) {
+                self.errors.push(format!("items are not a valid line item list: {}", e));
+            }
+            return items;
+        }
+        let items: Vec<LineItem> = self
+            .text("description")
+            .map(|description| LineItem {
+                description,
+                artist: None,
+                song_project: None,
+                company: None,
+                delivered: false,
+                terms_agreed: false,
+                invoiced: false,
+                upstreamed: false,
+                upstream_amount: None,
+                amount,
+            })
+            .into_iter()
+            .collect();
+        serde_json::to_string(&items).unwrap_or_else(|_| "[]".to_string())
+    }
+}
+
+/// Parses "1,234.56" or, with a ',' separator, "1.234,56". Spaces, currency
+/// symbols and codes such as "EUR" are ignored.
+fn parse_number(text: &str, decimal_separator: char) -> Option<f64> {
+    if text.chars().any(|c| c.is_alphabetic() && !c.is_ascii_uppercase()) {
+        return None;
+    }
+    let cleaned: String = text
+        .chars()
+        .filter(|c| c.is_ascii_digit() || *c == '-' || *c == decimal_separator)
+        .map(|c| if c == decimal_separator { '.' } else { c })
+        .collect();
+    cleaned.parse().ok()
+}
+
+fn read_table(request: &ImportRequest) -> Result<Table, String> {
+    let path = Path::new(&request.path);
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("").to_ascii_lowercase();
+    let mut table = match extension.as_str() {
+        "csv" | "txt" | "tsv" => read_csv(path, request.delimiter)?,
+        _ => read_workbook(path, request.sheet.as_deref())?,
+    };
+    for header in &mut table.headers {
+        *header = header.trim_start_matches('\u{feff}').trim().to_string();
+    }
+    table.rows.retain(|(_, cells)| cells.iter().any(|c| !matches!(c, Cell::Empty)));
+    Ok(table)
+}
+
+fn read_csv(path: &Path, delimiter: char) -> Result<Table, String> {
+    if !delimiter.is_ascii() {
+        return Err(format!("The delimiter must be an ASCII character, got '{}'", delimiter));
+    }
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter as u8)
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_path(path)
+        .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    let headers = reader
+        .headers()
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?
+        .iter()
+        .map(str::to_string)
+        .collect();
+
+    let mut rows = Vec::new();
+    for record in reader.records() {
+        let record = record.map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        // Blank lines are skipped by the reader, so number rows by line
+        let line = record.position().map_or(rows.len() + 2, |p| p.line() as usize);
+        let cells = record
+            .iter()
+            .map(|value| if value.is_empty() { Cell::Empty } else { Cell::Text(value.to_string()) })
+            .collect();
+        rows.push((line, cells));
+    }
+    Ok(Table { headers, rows })
+}
+
+fn read_workbook(path: &Path, sheet: Option<&str>) -> Result<Table, String> {
+    let mut workbook = open_workbook_auto(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    let sheet = match sheet {
+        Some(sheet) => sheet.to_string(),
+        None => workbook.sheet_names().first().cloned().ok_or("The workbook has no sheets")?,
+    };
+    let range = workbook
+        .worksheet_range(&sheet)
+        .map_err(|e| format!("Failed to read sheet '{}': {}", sheet, e))?;
+
+    // Rows are numbered as the spreadsheet shows them
+    let first_row = range.start().map_or(0, |(row, _)| row as usize);
+    let mut rows = range.rows();
+    let headers = rows
+        .next()
+        .map(|cells| cells.iter().map(|c| c.to_string()).collect())
+        .unwrap_or_default();
+    let rows = rows
+        .enumerate()
+        .map(|(index, cells)| (first_row + index + 2, cells.iter().map(workbook_cell).collect()))
+        .collect();
+    Ok(Table { headers, rows })
+}
+
+fn workbook_cell(data: &Data) -> Cell {
+    match data {
+        Data::Empty => Cell::Empty,
+        Data::String(s) if s.trim().is_empty() => Cell::Empty,
+        Data::String(s) => Cell::Text(s.trim().to_string()),
+        Data::Int(n) => Cell::Number(*n as f64),
+        Data::Float(n) => Cell::Number(*n),
+        Data::DateTime(_) | Data::DateTimeIso(_) => {
+            data.as_date().map_or_else(|| Cell::Text(data.to_string()), Cell::Date)
+        }
+        _ => Cell::Text(data.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn import(db: &Database, csv: &str, dry_run: bool) -> ImportReport {
+        let path = std::env::temp_dir().join(format!("project-invoicer-import-{}.csv", uuid::Uuid::new_v4()));
+        std::fs::write(&path, csv).unwrap();
+        let request: ImportRequest = serde_json::from_value(serde_json::json!({
+            "path": path.to_string_lossy(),
+            "dry_run": dry_run,
+        }))
+        .unwrap();
+        let report = import_invoices(db, &request);
+        std::fs::remove_file(&path).unwrap();
+        report.unwrap()
+    }
+
+    #[test]
+    fn dry_run_reports_every_row_without_writing() {
+        let db = Database::in_memory().unwrap();
+        db.create_artist(NewArtist { name: "Nova".to_string(), ..Default::default() }).unwrap();
+        let csv = "artist,invoice_number,amount,issue_date,due_date\n\
+                   Nova,INV-1,100,2024-01-01,2024-01-31\n\
+                   Nova,INV-2,,2024-01-01,2024-01-31\n\
+                   Nova,INV-3,-5,2024-01-01,\n\
+                   Nova,INV-1,100,2024-01-01,2024-01-31\n";
+
+        let report = import(&db, csv, true);
+        assert_eq!((report.total_rows, report.valid_rows, report.imported, report.committed), (4, 1, 0, false));
+        let errors: Vec<(usize, Vec<String>)> = report.errors.into_iter().map(|e| (e.row, e.messages)).collect();
+        assert_eq!(
+            errors,
+            [
+                (3, vec!["amount is required".to_string()]),
+                (4, vec!["due_date is required".to_string(), "amount -5 must be zero or more".to_string()]),
+            ]
+        );
+        assert_eq!(report.duplicates[0].duplicate_of_row, Some(2));
+        assert!(db.get_all_invoices().unwrap().is_empty());
+
+        let valid = "artist,invoice_number,amount,issue_date,due_date\nNova,INV-1,100,2024-01-01,2024-01-31\n";
+        let report = import(&db, valid, true);
+        assert_eq!((report.valid_rows, report.imported, report.committed), (1, 0, false));
+        assert!(db.get_all_invoices().unwrap().is_empty());
+
+        let report = import(&db, valid, false);
+        assert_eq!((report.imported, report.committed), (1, true));
+        assert_eq!(db.get_all_invoices().unwrap()[0].amount, 100.0);
+    }
+}
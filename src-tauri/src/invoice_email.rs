@@ -0,0 +1,77 @@
+// src-tauri/src/invoice_email.rs
+use chrono::Local;
+use serde::Deserialize;
+
+use crate::database::{Database, InvoiceEmail};
+use crate::email::{self, render, variables, Email, EmailTemplate, PdfRenderer};
+use crate::settings;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct SendInvoiceRequest {
+    pub invoice_id: String,
+    // Defaults to the invoice's billing email
+    #[serde(default)]
+    pub to: Vec<String>,
+    // Defaults to the client's CC addresses
+    #[serde(default)]
+    pub cc: Vec<String>,
+    #[serde(default)]
+    pub bcc: Vec<String>,
+    // Override the saved template for this email
+    pub subject: Option<String>,
+    pub body: Option<String>,
+}
+
+/// Emails the invoice PDF to its billing contact, then marks the invoice sent
+/// and keeps the message. Nothing is recorded when sending fails.
+pub fn send_invoice(
+    db: &Database,
+    request: &SendInvoiceRequest,
+    render_pdf: &PdfRenderer,
+) -> Result<InvoiceEmail, String> {
+    let app_settings = settings::load();
+    let smtp = app_settings.smtp.ok_or("Email isn't set up; add the SMTP server in settings first")?;
+    let invoice = db.get_invoice(&request.invoice_id).map_err(|e| e.to_string())?;
+    let artist = db.get_artist(&invoice.artist_id).map_err(|e| e.to_string())?;
+
+    let to = if request.to.is_empty() {
+        let billing_email = invoice.bill_to_email.clone().filter(|e| !e.trim().is_empty()).ok_or_else(|| {
+            format!("Invoice {} has no billing email; add one or enter a recipient", invoice.invoice_number)
+        })?;
+        vec![billing_email]
+    } else {
+        request.to.clone()
+    };
+    let cc = match (request.cc.is_empty(), invoice.client_id.as_deref()) {
+        (true, Some(client_id)) => {
+            let client = db.get_client(client_id).map_err(|e| e.to_string())?;
+            let cc_emails = client.cc_emails.unwrap_or_default();
+            cc_emails.split(',').map(str::trim).filter(|a| !a.is_empty()).map(str::to_string).collect()
+        }
+        _ => request.cc.clone(),
+    };
+    let template = EmailTemplate {
+        subject: request.subject.clone().unwrap_or(app_settings.invoice_email.subject),
+        body: request.body.clone().unwrap_or(app_settings.invoice_email.body),
+    };
+    let errors = template.validate();
+    if !errors.is_empty() {
+        return Err(errors.join("; "));
+    }
+
+    let variables = variables(&invoice, &artist, Local::now().date_naive());
+    let email = Email {
+        to,
+        cc,
+        bcc: request.bcc.clone(),
+        subject: render(&template.subject, &variables),
+        body: render(&template.body, &variables),
+        attachments: vec![render_pdf(db, &invoice)?],
+    };
+    let message = email::build_message(&smtp, &email)?;
+    email::send(&smtp, &message)?;
+
+    let eml = String::from_utf8_lossy(&message.formatted()).into_owned();
+    db.record_invoice_email(&invoice.id, &email.to, &email.cc, &email.bcc, &email.subject, &eml)
+        .map_err(|e| format!("The invoice was sent but couldn't be recorded: {}", e))
+}
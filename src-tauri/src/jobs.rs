@@ -0,0 +1,66 @@
+// src-tauri/src/jobs.rs
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Shared flag a long-running job polls between steps to stop early.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// Cancellable jobs that are running, by the id the UI started them with.
+/// Tauri manages one for the app.
+#[derive(Default)]
+pub struct Jobs {
+    running: Mutex<HashMap<String, CancelToken>>,
+}
+
+impl Jobs {
+    /// Registers a job under `job_id` until the returned guard is dropped. Jobs
+    /// started without an id still get a token but can't be cancelled.
+    pub fn start(&self, job_id: Option<&str>) -> Result<RunningJob<'_>, String> {
+        let token = CancelToken::default();
+        if let Some(id) = job_id {
+            let mut running = self.running.lock().unwrap();
+            if running.contains_key(id) {
+                return Err(format!("A job with id '{}' is already running", id));
+            }
+            running.insert(id.to_string(), token.clone());
+        }
+        Ok(RunningJob { jobs: self, id: job_id.map(str::to_string), token })
+    }
+
+    /// Asks the job to stop; false when no job with that id is running.
+    pub fn cancel(&self, job_id: &str) -> bool {
+        match self.running.lock().unwrap().get(job_id) {
+            Some(token) => {
+                token.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+pub struct RunningJob<'a> {
+    jobs: &'a Jobs,
+    id: Option<String>,
+    pub token: CancelToken,
+}
+
+impl Drop for RunningJob<'_> {
+    fn drop(&mut self) {
+        if let Some(id) = &self.id {
+            self.jobs.running.lock().unwrap().remove(id);
+        }
+    }
+}
@@ -6,195 +6,248 @@
 
 use std::path::PathBuf;
 
+mod aging;
+mod backup;
+mod batch_export;
+mod branding;
+mod csv_export;
 mod database;
+mod einvoice;
+mod email;
+mod facturx;
+mod fonts;
+mod import;
+mod invoice_email;
+mod jobs;
+mod payment_qr;
+mod payment_terms;
 mod pdf_generator;
+mod pdf_template;
+mod reminders;
+mod settings;
+mod snapshots;
+mod ubl;
+mod validation;
+mod workspaces;
 
-use database::{Artist, Project, Invoice};
-use pdf_generator::{generate_invoice_pdf as generate_pdf, InvoiceData, LineItem};
+use aging::{AgingReport, AgingReportRequest};
+use backup::{BackupSummary, RestoreReport, RestoreRequest};
+use batch_export::{BatchPdfRequest, BatchSummary};
+use branding::Branding;
+use csv_export::CsvExportRequest;
+use database::{
+    Artist, ArtistPatch, ArtistTaxDetails, BankDetails, BrandingProfile, BrandingProfilePatch, Client, ClientPatch,
+    Database, Invoice, InvoiceEmail, InvoicePatch, InvoiceReminder, InvoiceTaxDetails, NewArtist,
+    NewBrandingProfile, NewClient, NewInvoice, NewProject, Project, ProjectPatch,
+};
+use email::{Email, EmailAttachment, EmailTemplate, RedactedSmtpSettings, SmtpSettings};
+use import::{ImportReport, ImportRequest};
+use invoice_email::SendInvoiceRequest;
+use jobs::Jobs;
+use payment_terms::PaymentTerms;
+use pdf_generator::{generate_invoice_pdf as generate_pdf, InvoiceData, LineItem, RenderOptions};
+use pdf_template::TemplateInfo;
+use reminders::{ReminderRun, ReminderSchedule};
+use snapshots::{SnapshotInfo, SnapshotSettings};
+use tauri::{Manager, State};
+use workspaces::Workspace;
 
 // Artist Commands (remain the same)
 #[tauri::command]
-async fn get_all_artists() -> Result<Vec<Artist>, String> {
-    database::get_all_artists()
-        .map_err(|e| e.to_string())
+async fn get_all_artists(db: State<'_, Database>) -> Result<Vec<Artist>, String> {
+    db.run(|db| db.get_all_artists()).await
 }
 
 #[tauri::command]
-async fn create_artist(
-    name: String,
-    company_name: Option<String>,
-    email: Option<String>,
-    phone: Option<String>,
-    address: Option<String>,
-    wire_details: Option<String>,
-    notes: Option<String>,
-) -> Result<Artist, String> {
-    database::create_artist(name, company_name, email, phone, address, wire_details, notes)
-        .map_err(|e| e.to_string())
+async fn create_artist(db: State<'_, Database>, artist: NewArtist) -> Result<Artist, String> {
+    db.run(move |db| db.create_artist(artist)).await
 }
 
 #[tauri::command]
 async fn update_artist(
+    db: State<'_, Database>,
     artist_id: String,
-    name: String,
-    company_name: Option<String>,
-    email: Option<String>,
-    phone: Option<String>,
-    address: Option<String>,
-    wire_details: Option<String>,
-    notes: Option<String>,
+    patch: ArtistPatch,
 ) -> Result<Artist, String> {
-    database::update_artist(artist_id, name, company_name, email, phone, address, wire_details, notes)
-        .map_err(|e| e.to_string())
+    db.run(move |db| db.update_artist(&artist_id, patch)).await
 }
 
 #[tauri::command]
-async fn delete_artist(artist_id: String) -> Result<(), String> {
-    database::delete_artist(artist_id)
-        .map_err(|e| e.to_string())
+async fn set_artist_bank_details(
+    db: State<'_, Database>,
+    artist_id: String,
+    bank_details: BankDetails,
+) -> Result<(), String> {
+    db.run(move |db| db.set_artist_bank_details(artist_id, bank_details)).await
 }
 
-// Project Commands (remain the same)
 #[tauri::command]
-async fn get_all_projects() -> Result<Vec<Project>, String> {
-    database::get_all_projects()
-        .map_err(|e| e.to_string())
+async fn set_artist_tax_details(
+    db: State<'_, Database>,
+    artist_id: String,
+    tax_details: ArtistTaxDetails,
+) -> Result<(), String> {
+    db.run(move |db| db.set_artist_tax_details(artist_id, tax_details)).await
 }
 
 #[tauri::command]
-async fn get_projects_by_artist(artist_id: String) -> Result<Vec<Project>, String> {
-    database::get_projects_by_artist(artist_id)
-        .map_err(|e| e.to_string())
+async fn set_artist_payment_terms(
+    db: State<'_, Database>,
+    artist_id: String,
+    payment_terms: Option<PaymentTerms>,
+) -> Result<(), String> {
+    db.run(move |db| db.set_artist_payment_terms(artist_id, payment_terms)).await
 }
 
+// `None` turns payment reminders off for the artist
 #[tauri::command]
-async fn create_project(
+async fn set_artist_reminder_schedule(
+    db: State<'_, Database>,
     artist_id: String,
-    name: String,
-    description: Option<String>,
-    status: String,
-    start_date: Option<String>,
-    end_date: Option<String>,
-    budget: f64,
-) -> Result<Project, String> {
-    database::create_project(artist_id, name, description, status, start_date, end_date, budget)
-        .map_err(|e| e.to_string())
+    schedule: Option<ReminderSchedule>,
+) -> Result<(), String> {
+    db.run(move |db| db.set_artist_reminder_schedule(artist_id, schedule)).await
+}
+
+#[tauri::command]
+async fn delete_artist(db: State<'_, Database>, artist_id: String) -> Result<(), String> {
+    db.run(move |db| db.delete_artist(artist_id)).await
+}
+
+// Project Commands (remain the same)
+#[tauri::command]
+async fn get_all_projects(db: State<'_, Database>) -> Result<Vec<Project>, String> {
+    db.run(|db| db.get_all_projects()).await
+}
+
+#[tauri::command]
+async fn get_projects_by_artist(db: State<'_, Database>, artist_id: String) -> Result<Vec<Project>, String> {
+    db.run(move |db| db.get_projects_by_artist(artist_id)).await
+}
+
+#[tauri::command]
+async fn create_project(db: State<'_, Database>, project: NewProject) -> Result<Project, String> {
+    db.run(move |db| db.create_project(project)).await
 }
 
 #[tauri::command]
 async fn update_project(
+    db: State<'_, Database>,
     project_id: String,
-    name: String,
-    description: Option<String>,
-    status: String,
-    start_date: Option<String>,
-    end_date: Option<String>,
-    budget: f64,
+    patch: ProjectPatch,
 ) -> Result<Project, String> {
-    database::update_project(project_id, name, description, status, start_date, end_date, budget)
-        .map_err(|e| e.to_string())
+    db.run(move |db| db.update_project(&project_id, patch)).await
+}
+
+#[tauri::command]
+async fn delete_project(db: State<'_, Database>, project_id: String) -> Result<(), String> {
+    db.run(move |db| db.delete_project(project_id)).await
+}
+
+// Client Commands
+#[tauri::command]
+async fn get_all_clients(db: State<'_, Database>) -> Result<Vec<Client>, String> {
+    db.run(|db| db.get_all_clients()).await
+}
+
+#[tauri::command]
+async fn get_client(db: State<'_, Database>, client_id: String) -> Result<Client, String> {
+    db.run(move |db| db.get_client(&client_id)).await
+}
+
+#[tauri::command]
+async fn create_client(db: State<'_, Database>, client: NewClient) -> Result<Client, String> {
+    db.run(move |db| db.create_client(client)).await
+}
+
+#[tauri::command]
+async fn update_client(db: State<'_, Database>, client_id: String, patch: ClientPatch) -> Result<Client, String> {
+    db.run(move |db| db.update_client(&client_id, patch)).await
 }
 
 #[tauri::command]
-async fn delete_project(project_id: String) -> Result<(), String> {
-    database::delete_project(project_id)
-        .map_err(|e| e.to_string())
+async fn delete_client(db: State<'_, Database>, client_id: String) -> Result<(), String> {
+    db.run(move |db| db.delete_client(client_id)).await
 }
 
 // Updated Invoice Commands with bill_to
 #[tauri::command]
-async fn get_all_invoices() -> Result<Vec<Invoice>, String> {
-    database::get_all_invoices()
-        .map_err(|e| e.to_string())
+async fn get_all_invoices(db: State<'_, Database>) -> Result<Vec<Invoice>, String> {
+    db.run(|db| db.get_all_invoices()).await
 }
 
 #[tauri::command]
-async fn get_invoices_by_artist(artist_id: String) -> Result<Vec<Invoice>, String> {
-    database::get_invoices_by_artist(artist_id)
-        .map_err(|e| e.to_string())
+async fn get_invoices_by_artist(db: State<'_, Database>, artist_id: String) -> Result<Vec<Invoice>, String> {
+    db.run(move |db| db.get_invoices_by_artist(artist_id)).await
 }
 
 #[tauri::command]
-async fn create_invoice(
-    artist_id: String,
-    project_id: Option<String>,
-    invoice_number: String,
-    amount: f64,
-    status: String,
-    issue_date: String,
-    due_date: String,
-    bill_to: Option<String>,
-    items: String,
-    notes: Option<String>,
-) -> Result<Invoice, String> {
-    // Filter out empty string project_id
-    let clean_project_id = project_id.filter(|s| !s.is_empty());
-    
-    database::create_invoice(
-        artist_id,
-        clean_project_id,
-        invoice_number,
-        amount,
-        status,
-        issue_date,
-        due_date,
-        bill_to,
-        items,
-        notes,
-    )
-    .map_err(|e| e.to_string())
+async fn create_invoice(db: State<'_, Database>, invoice: NewInvoice) -> Result<Invoice, String> {
+    db.run(move |db| db.create_invoice(invoice)).await
 }
 
 #[tauri::command]
 async fn update_invoice(
+    db: State<'_, Database>,
     invoice_id: String,
-    invoice_number: String,
-    amount: f64,
-    status: String,
-    issue_date: String,
-    due_date: String,
-    bill_to: Option<String>,
-    items: String,
-    notes: Option<String>,
+    patch: InvoicePatch,
 ) -> Result<Invoice, String> {
-    database::update_invoice(
-        invoice_id,
-        invoice_number,
-        amount,
-        status,
-        issue_date,
-        due_date,
-        bill_to,
-        items,
-        notes,
-    )
-    .map_err(|e| e.to_string())
+    db.run(move |db| db.update_invoice(&invoice_id, patch)).await
 }
 
 #[tauri::command]
-async fn delete_invoice(invoice_id: String) -> Result<(), String> {
-    database::delete_invoice(invoice_id)
-        .map_err(|e| e.to_string())
+async fn delete_invoice(db: State<'_, Database>, invoice_id: String) -> Result<(), String> {
+    db.run(move |db| db.delete_invoice(invoice_id)).await
 }
 
-// PDF Generation Command
 #[tauri::command]
-async fn generate_invoice_pdf(invoice_id: String, output_path: String) -> Result<String, String> {
-    // Get invoice data
-    let invoices = database::get_all_invoices().map_err(|e| e.to_string())?;
-    let invoice = invoices.into_iter()
-        .find(|i| i.id == invoice_id)
-        .ok_or("Invoice not found")?;
-    
+async fn record_invoice_payment(
+    db: State<'_, Database>,
+    invoice_id: String,
+    paid_date: Option<chrono::NaiveDate>,
+    payment_reference: Option<String>,
+) -> Result<(), String> {
+    db.run(move |db| db.record_invoice_payment(invoice_id, paid_date, payment_reference)).await
+}
+
+#[tauri::command]
+async fn set_invoice_tax_details(
+    db: State<'_, Database>,
+    invoice_id: String,
+    tax_details: InvoiceTaxDetails,
+) -> Result<(), String> {
+    db.run(move |db| db.set_invoice_tax_details(invoice_id, tax_details)).await
+}
+
+#[tauri::command]
+async fn set_invoice_document_type(
+    db: State<'_, Database>,
+    invoice_id: String,
+    document_type: String,
+    credited_invoice_id: Option<String>,
+) -> Result<(), String> {
+    if document_type != "invoice" && document_type != "credit_note" {
+        return Err(format!(
+            "Unknown document type '{}' (expected 'invoice' or 'credit_note')",
+            document_type
+        ));
+    }
+    db.run(move |db| db.set_invoice_document_type(invoice_id, document_type, credited_invoice_id)).await
+}
+
+// Invoice, artist, project and line items for rendering or export
+fn load_invoice_data(db: &Database, invoice_id: &str) -> Result<InvoiceData, String> {
+    let invoice = db.get_invoice(invoice_id).map_err(|e| e.to_string())?;
+    invoice_data_for(db, invoice)
+}
+
+fn invoice_data_for(db: &Database, invoice: Invoice) -> Result<InvoiceData, String> {
     // Get artist data
-    let artists = database::get_all_artists().map_err(|e| e.to_string())?;
-    let artist = artists.into_iter()
-        .find(|a| a.id == invoice.artist_id)
-        .ok_or("Artist not found")?;
+    let artist = db.get_artist(&invoice.artist_id).map_err(|e| e.to_string())?;
     
     // Get project data if exists
     let project = if let Some(project_id) = &invoice.project_id {
-        let projects = database::get_all_projects().map_err(|e| e.to_string())?;
+        let projects = db.get_all_projects().map_err(|e| e.to_string())?;
         projects.into_iter().find(|p| &p.id == project_id)
     } else {
         None
@@ -204,20 +257,497 @@ async fn generate_invoice_pdf(invoice_id: String, output_path: String) -> Result
     let line_items: Vec<LineItem> = serde_json::from_str(&invoice.items)
         .map_err(|e| format!("Failed to parse line items: {}", e))?;
     
-    // Prepare invoice data
-    let invoice_data = InvoiceData {
-        invoice: invoice.clone(),
-        artist: artist.clone(),
-        project: project.clone(),
+    Ok(InvoiceData {
+        invoice,
+        artist,
+        project,
         line_items,
-    };
+    })
+}
+
+// PDF Generation Command
+#[tauri::command]
+async fn generate_invoice_pdf(
+    app: tauri::AppHandle,
+    db: State<'_, Database>,
+    invoice_id: String,
+    output_path: String,
+    facturx: Option<bool>,
+) -> Result<String, String> {
+    db.run(move |db| {
+        let invoice_data = load_invoice_data(db, &invoice_id)?;
+        let options = render_options(&app, db, &invoice_data, facturx.unwrap_or(false))?;
+        
+        // Generate PDF
+        let path = PathBuf::from(output_path);
+        generate_pdf(invoice_data, path.clone(), &options)
+            .map_err(|e| format!("Failed to generate PDF: {}", e))?;
+        
+        Ok::<_, String>(path.to_string_lossy().to_string())
+    })
+    .await
+}
+
+fn render_options(
+    app: &tauri::AppHandle,
+    db: &Database,
+    invoice_data: &InvoiceData,
+    facturx: bool,
+) -> Result<RenderOptions, String> {
+    let invoice = &invoice_data.invoice;
+    let artist = &invoice_data.artist;
     
-    // Generate PDF
-    let path = PathBuf::from(output_path);
-    generate_pdf(invoice_data, path.clone())
+    // Artist branding, or the default profile when the artist has none
+    let resource_dir = app.path_resolver().resource_dir();
+    let branding = db.get_branding_profile_for_artist(&artist.id)
+        .map_err(|e| e.to_string())?
+        .map(|profile| Branding::load(&profile, resource_dir.as_deref()))
+        .unwrap_or_default();
+    
+    // Invoice template, else the artist's, else the default layout
+    let template_name = invoice.pdf_template.as_deref()
+        .or(artist.pdf_template.as_deref())
+        .filter(|s| !s.is_empty())
+        .unwrap_or(pdf_template::DEFAULT_TEMPLATE_NAME);
+    let template = pdf_template::load_template(template_name, Some(&user_template_dir()))?;
+    
+    // Fonts the user dropped in cover scripts the bundled font lacks, such as CJK
+    Ok(RenderOptions {
+        font_dir: Some(user_font_dir()),
+        branding,
+        template,
+        facturx,
+    })
+}
+
+// The invoice PDF as an email attachment, rendered through a temporary file
+fn invoice_pdf_attachment(app: &tauri::AppHandle, db: &Database, invoice: &Invoice) -> Result<EmailAttachment, String> {
+    let invoice_data = invoice_data_for(db, invoice.clone())?;
+    let options = render_options(app, db, &invoice_data, false)?;
+    let file_name = format!("{}.pdf", batch_export::sanitize(&invoice.invoice_number));
+    let path = std::env::temp_dir().join(format!("{}-{}", uuid::Uuid::new_v4(), file_name));
+    generate_pdf(invoice_data, path.clone(), &options)
         .map_err(|e| format!("Failed to generate PDF: {}", e))?;
+    let data = std::fs::read(&path).map_err(|e| format!("Failed to read the generated PDF: {}", e));
+    let _ = std::fs::remove_file(&path);
+    Ok(EmailAttachment::pdf(file_name, data?))
+}
+
+// Renders many invoices at once; progress is emitted as `batch-pdf-progress` events
+// and the batch can be stopped with `cancel_job` when started with a `job_id`
+#[tauri::command]
+async fn generate_invoice_pdfs(
+    app: tauri::AppHandle,
+    db: State<'_, Database>,
+    jobs: State<'_, Jobs>,
+    request: BatchPdfRequest,
+) -> Result<BatchSummary, String> {
+    batch_export::validate_pattern(
+        request.filename_pattern.as_deref().unwrap_or(batch_export::DEFAULT_FILENAME_PATTERN),
+    )?;
+    let job = jobs.start(request.job_id.as_deref())?;
+    let cancel = job.token.clone();
     
-    Ok(path.to_string_lossy().to_string())
+    db.run(move |db| {
+        let invoices = if request.invoice_ids.is_empty() {
+            db.get_all_invoices()
+                .map_err(|e| e.to_string())?
+                .into_iter()
+                .filter(|invoice| request.filter.matches(invoice))
+                .collect()
+        } else {
+            request.invoice_ids.iter()
+                .map(|id| db.get_invoice(id).map_err(|e| e.to_string()))
+                .collect::<Result<Vec<_>, _>>()?
+        };
+        let invoice_data = invoices.into_iter()
+            .map(|invoice| invoice_data_for(db, invoice))
+            .collect::<Result<Vec<_>, _>>()?;
+        
+        batch_export::run_batch(
+            invoice_data,
+            &request,
+            &cancel,
+            |invoice_data, path| {
+                let options = render_options(&app, db, &invoice_data, request.facturx)?;
+                generate_pdf(invoice_data, path.to_path_buf(), &options)
+                    .map_err(|e| format!("Failed to generate PDF: {}", e))
+            },
+            |progress| {
+                let _ = app.emit_all(batch_export::PROGRESS_EVENT, progress);
+            },
+        )
+    })
+    .await
+}
+
+// Asks a running job such as a batch export to stop; false when it isn't running
+#[tauri::command]
+async fn cancel_job(jobs: State<'_, Jobs>, job_id: String) -> Result<bool, String> {
+    Ok(jobs.cancel(&job_id))
+}
+
+// UBL Export Command
+#[tauri::command]
+async fn export_invoice_ubl(
+    db: State<'_, Database>,
+    invoice_id: String,
+    output_path: String,
+) -> Result<String, String> {
+    db.run(move |db| {
+        let invoice_data = load_invoice_data(db, &invoice_id)?;
+        ubl::validate(&invoice_data)?;
+        
+        // Credit notes reference the invoice they correct
+        let credited_invoice = match &invoice_data.invoice.credited_invoice_id {
+            Some(credited_id) => Some(db.get_invoice(credited_id).map_err(|e| e.to_string())?),
+            None => None,
+        };
+        
+        let xml = ubl::build_ubl_xml(&invoice_data, credited_invoice.as_ref());
+        std::fs::write(&output_path, xml)
+            .map_err(|e| format!("Failed to write UBL file: {}", e))?;
+        
+        Ok::<_, String>(output_path)
+    })
+    .await
+}
+
+// CSV Export Commands (return the number of rows written)
+#[tauri::command]
+async fn export_invoices_csv(db: State<'_, Database>, request: CsvExportRequest) -> Result<usize, String> {
+    db.run(move |db| csv_export::export_invoices(db, &request)).await
+}
+
+#[tauri::command]
+async fn export_projects_csv(db: State<'_, Database>, request: CsvExportRequest) -> Result<usize, String> {
+    db.run(move |db| csv_export::export_projects(db, &request)).await
+}
+
+#[tauri::command]
+async fn export_artists_csv(db: State<'_, Database>, request: CsvExportRequest) -> Result<usize, String> {
+    db.run(move |db| csv_export::export_artists(db, &request)).await
+}
+
+#[tauri::command]
+async fn export_payments_csv(db: State<'_, Database>, request: CsvExportRequest) -> Result<usize, String> {
+    db.run(move |db| csv_export::export_payments(db, &request)).await
+}
+
+// Receivables aging as of `as_of` (today by default), optionally also written as PDF and CSV
+#[tauri::command]
+async fn ar_aging_report(
+    app: tauri::AppHandle,
+    db: State<'_, Database>,
+    request: AgingReportRequest,
+) -> Result<AgingReport, String> {
+    db.run(move |db| {
+        let as_of = request.as_of.unwrap_or_else(|| chrono::Local::now().date_naive());
+        let report = aging::aging_report(db, as_of, request.artist_id.as_deref())?;
+
+        if let Some(pdf_path) = &request.pdf_path {
+            // The artist's branding for a single-artist report, else the default profile
+            let resource_dir = app.path_resolver().resource_dir();
+            let branding = db.get_branding_profile_for_artist(request.artist_id.as_deref().unwrap_or_default())
+                .map_err(|e| e.to_string())?
+                .map(|profile| Branding::load(&profile, resource_dir.as_deref()))
+                .unwrap_or_default();
+            let options = RenderOptions {
+                font_dir: Some(user_font_dir()),
+                branding,
+                template: pdf_template::load_template(pdf_template::DEFAULT_TEMPLATE_NAME, None)?,
+                facturx: false,
+            };
+            pdf_generator::generate_aging_pdf(&report, PathBuf::from(pdf_path), &options)
+                .map_err(|e| format!("Failed to generate PDF: {}", e))?;
+        }
+        if let Some(csv) = &request.csv {
+            csv_export::export_aging(&report, csv)?;
+        }
+        Ok::<_, String>(report)
+    })
+    .await
+}
+
+// Email Commands
+// The password stays in the settings file; the app only learns whether one is set
+#[tauri::command]
+async fn get_smtp_settings() -> Result<Option<RedactedSmtpSettings>, String> {
+    Ok(settings::load().smtp.map(SmtpSettings::redacted))
+}
+
+// `None` removes the mail server, which also stops payment reminders. A
+// missing password keeps the saved one.
+#[tauri::command]
+async fn update_smtp_settings(mut smtp: Option<SmtpSettings>) -> Result<(), String> {
+    if let Some(errors) = smtp.as_ref().map(SmtpSettings::validate).filter(|e| !e.is_empty()) {
+        return Err(errors.join("; "));
+    }
+    let mut app_settings = settings::load();
+    if let Some(smtp) = &mut smtp {
+        smtp.keep_password(app_settings.smtp.as_ref());
+    }
+    app_settings.smtp = smtp;
+    settings::save(&app_settings)
+}
+
+#[tauri::command]
+async fn send_test_email(to: String) -> Result<(), String> {
+    let smtp = settings::load().smtp.ok_or("Email isn't set up; add the SMTP server in settings first")?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let email = Email {
+            to: vec![to],
+            subject: "Test email".to_string(),
+            body: "Your mail server is set up correctly.".to_string(),
+            ..Default::default()
+        };
+        email::send(&smtp, &email::build_message(&smtp, &email)?)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+async fn get_invoice_email_template() -> Result<EmailTemplate, String> {
+    Ok(settings::load().invoice_email)
+}
+
+#[tauri::command]
+async fn update_invoice_email_template(template: EmailTemplate) -> Result<(), String> {
+    let errors = template.validate();
+    if !errors.is_empty() {
+        return Err(errors.join("; "));
+    }
+    let mut app_settings = settings::load();
+    app_settings.invoice_email = template;
+    settings::save(&app_settings)
+}
+
+// Emails the invoice PDF and marks the invoice sent
+#[tauri::command]
+async fn send_invoice(
+    app: tauri::AppHandle,
+    db: State<'_, Database>,
+    request: SendInvoiceRequest,
+) -> Result<InvoiceEmail, String> {
+    db.run(move |db| {
+        let render_pdf = |db: &Database, invoice: &Invoice| invoice_pdf_attachment(&app, db, invoice);
+        invoice_email::send_invoice(db, &request, &render_pdf)
+    })
+    .await
+}
+
+// Invoice emails sent from the app, oldest first
+#[tauri::command]
+async fn get_invoice_emails(db: State<'_, Database>, invoice_id: String) -> Result<Vec<InvoiceEmail>, String> {
+    db.run(move |db| db.get_invoice_emails(&invoice_id)).await
+}
+
+// Writes a sent invoice email to `output_path` as an .eml file
+#[tauri::command]
+async fn save_invoice_email(db: State<'_, Database>, email_id: String, output_path: String) -> Result<String, String> {
+    db.run(move |db| {
+        let message = db.get_invoice_email_message(&email_id).map_err(|e| e.to_string())?;
+        std::fs::write(&output_path, message).map_err(|e| format!("Failed to save the email: {}", e))?;
+        Ok::<_, String>(output_path)
+    })
+    .await
+}
+
+// The suggested escalation, as a starting point for an artist's schedule
+#[tauri::command]
+async fn get_default_reminder_schedule() -> Result<ReminderSchedule, String> {
+    Ok(ReminderSchedule::default())
+}
+
+// Reminders sent or attempted for an invoice, oldest first
+#[tauri::command]
+async fn get_invoice_reminders(db: State<'_, Database>, invoice_id: String) -> Result<Vec<InvoiceReminder>, String> {
+    db.run(move |db| db.get_invoice_reminders(&invoice_id)).await
+}
+
+// Sends the reminders due today now instead of waiting for the hourly check
+#[tauri::command]
+async fn send_payment_reminders(app: tauri::AppHandle, db: State<'_, Database>) -> Result<ReminderRun, String> {
+    db.run(move |db| {
+        let render_pdf = |db: &Database, invoice: &Invoice| invoice_pdf_attachment(&app, db, invoice);
+        reminders::send_due_reminders(db, chrono::Local::now().date_naive(), &render_pdf)
+    })
+    .await
+}
+
+// Backup Commands
+#[tauri::command]
+async fn export_backup(db: State<'_, Database>, output_path: String) -> Result<BackupSummary, String> {
+    db.run(move |db| backup::export_backup(db, &output_path)).await
+}
+
+#[tauri::command]
+async fn import_backup(db: State<'_, Database>, request: RestoreRequest) -> Result<RestoreReport, String> {
+    db.run(move |db| backup::import_backup(db, &request)).await
+}
+
+// Workspace Commands
+// Why the database couldn't be opened at startup, if it couldn't
+#[tauri::command]
+async fn get_startup_error(db: State<'_, Database>) -> Result<Option<String>, String> {
+    Ok(db.error())
+}
+
+#[tauri::command]
+async fn list_workspaces() -> Result<Vec<Workspace>, String> {
+    Ok(workspaces::list_workspaces())
+}
+
+#[tauri::command]
+async fn get_current_workspace(db: State<'_, Database>) -> Result<Workspace, String> {
+    Ok(workspaces::current_workspace(&db))
+}
+
+#[tauri::command]
+async fn create_workspace(
+    db: State<'_, Database>,
+    name: String,
+    folder: Option<String>,
+) -> Result<Workspace, String> {
+    db.run(move |db| workspaces::create_workspace(db, &name, folder.as_deref())).await
+}
+
+#[tauri::command]
+async fn open_workspace(
+    db: State<'_, Database>,
+    path: String,
+    name: Option<String>,
+) -> Result<Workspace, String> {
+    db.run(move |db| workspaces::open_workspace(db, &path, name.as_deref())).await
+}
+
+#[tauri::command]
+async fn switch_workspace(db: State<'_, Database>, workspace_id: String) -> Result<Workspace, String> {
+    db.run(move |db| workspaces::switch_workspace(db, &workspace_id)).await
+}
+
+#[tauri::command]
+async fn rename_workspace(workspace_id: String, name: String) -> Result<Workspace, String> {
+    workspaces::rename_workspace(&workspace_id, &name)
+}
+
+// Snapshot Commands
+#[tauri::command]
+async fn get_snapshot_settings() -> Result<SnapshotSettings, String> {
+    Ok(settings::load().snapshots)
+}
+
+#[tauri::command]
+async fn update_snapshot_settings(snapshot_settings: SnapshotSettings) -> Result<(), String> {
+    let mut app_settings = settings::load();
+    app_settings.snapshots = snapshot_settings;
+    settings::save(&app_settings)
+}
+
+#[tauri::command]
+async fn list_snapshots(db: State<'_, Database>) -> Result<Vec<SnapshotInfo>, String> {
+    db.run(snapshots::list_snapshots).await
+}
+
+#[tauri::command]
+async fn take_snapshot(db: State<'_, Database>) -> Result<SnapshotInfo, String> {
+    db.run(move |db| snapshots::take_snapshot(db, snapshots::MANUAL)).await
+}
+
+// `confirmed` must be set once the user has agreed to overwrite their data
+#[tauri::command]
+async fn restore_snapshot(db: State<'_, Database>, file_name: String, confirmed: bool) -> Result<(), String> {
+    if !confirmed {
+        return Err("Restoring a snapshot replaces all current data and needs confirmation".to_string());
+    }
+    db.run(move |db| snapshots::restore_snapshot(db, &file_name)).await
+}
+
+// Import Commands
+#[tauri::command]
+async fn import_artists(db: State<'_, Database>, request: ImportRequest) -> Result<ImportReport, String> {
+    db.run(move |db| import::import_artists(db, &request)).await
+}
+
+#[tauri::command]
+async fn import_projects(db: State<'_, Database>, request: ImportRequest) -> Result<ImportReport, String> {
+    db.run(move |db| import::import_projects(db, &request)).await
+}
+
+#[tauri::command]
+async fn import_invoices(db: State<'_, Database>, request: ImportRequest) -> Result<ImportReport, String> {
+    db.run(move |db| import::import_invoices(db, &request)).await
+}
+
+// Branding Profile Commands
+#[tauri::command]
+async fn get_all_branding_profiles(db: State<'_, Database>) -> Result<Vec<BrandingProfile>, String> {
+    db.run(|db| db.get_all_branding_profiles()).await
+}
+
+#[tauri::command]
+async fn create_branding_profile(
+    db: State<'_, Database>,
+    mut profile: NewBrandingProfile,
+) -> Result<BrandingProfile, String> {
+    profile.artist_id = profile.artist_id.filter(|s| !s.is_empty());
+    db.run(move |db| db.create_branding_profile(profile)).await
+}
+
+#[tauri::command]
+async fn update_branding_profile(
+    db: State<'_, Database>,
+    profile_id: String,
+    patch: BrandingProfilePatch,
+) -> Result<BrandingProfile, String> {
+    db.run(move |db| db.update_branding_profile(&profile_id, patch)).await
+}
+
+#[tauri::command]
+async fn delete_branding_profile(db: State<'_, Database>, profile_id: String) -> Result<(), String> {
+    db.run(move |db| db.delete_branding_profile(profile_id)).await
+}
+
+// Invoice Template Commands
+fn user_template_dir() -> PathBuf {
+    database::get_data_dir().join("templates")
+}
+
+// Extra fonts for scripts the bundled font doesn't cover
+fn user_font_dir() -> PathBuf {
+    database::get_data_dir().join("fonts")
+}
+
+#[tauri::command]
+async fn get_invoice_templates() -> Result<Vec<TemplateInfo>, String> {
+    Ok(pdf_template::list_templates(Some(&user_template_dir())))
+}
+
+#[tauri::command]
+async fn set_artist_pdf_template(
+    db: State<'_, Database>,
+    artist_id: String,
+    pdf_template: Option<String>,
+) -> Result<(), String> {
+    let pdf_template = pdf_template.filter(|s| !s.is_empty());
+    if let Some(name) = &pdf_template {
+        pdf_template::validate_name(name)?;
+    }
+    db.run(move |db| db.set_artist_pdf_template(artist_id, pdf_template)).await
+}
+
+#[tauri::command]
+async fn set_invoice_pdf_template(
+    db: State<'_, Database>,
+    invoice_id: String,
+    pdf_template: Option<String>,
+) -> Result<(), String> {
+    let pdf_template = pdf_template.filter(|s| !s.is_empty());
+    if let Some(name) = &pdf_template {
+        pdf_template::validate_name(name)?;
+    }
+    db.run(move |db| db.set_invoice_pdf_template(invoice_id, pdf_template)).await
 }
 
 #[tauri::command]
@@ -228,12 +758,15 @@ async fn get_downloads_directory() -> Result<String, String> {
 }
 
 fn main() {
-    // Initialize database on startup
-    if let Err(e) = database::init() {
-        eprintln!("Failed to initialize database: {}", e);
-    }
+    // Open the database on startup; a failure is reported through
+    // `get_startup_error` so the UI can offer another workspace
+    let database = workspaces::open_last_used();
+    snapshots::start_scheduler(database.clone());
+    aging::start_overdue_checker(database.clone());
 
     tauri::Builder::default()
+        .manage(database)
+        .manage(Jobs::default())
         .setup(|app| {
             // Create app directories if they don't exist
             let app_dir = app.path_resolver()
@@ -243,6 +776,13 @@ fn main() {
             std::fs::create_dir_all(&app_dir)
                 .expect("Failed to create app directory");
             
+            // Reminder PDFs are rendered with the bundled fonts, so this needs the app handle
+            let handle = app.handle();
+            reminders::start_scheduler(
+                app.state::<Database>().inner().clone(),
+                Box::new(move |db, invoice| invoice_pdf_attachment(&handle, db, invoice)),
+            );
+            
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
@@ -250,6 +790,10 @@ fn main() {
             get_all_artists,
             create_artist,
             update_artist,
+            set_artist_bank_details,
+            set_artist_tax_details,
+            set_artist_payment_terms,
+            set_artist_reminder_schedule,
             delete_artist,
             // Project commands
             get_all_projects,
@@ -257,15 +801,73 @@ fn main() {
             create_project,
             update_project,
             delete_project,
+            // Client commands
+            get_all_clients,
+            get_client,
+            create_client,
+            update_client,
+            delete_client,
             // Invoice commands
             get_all_invoices,
             get_invoices_by_artist,
             create_invoice,
             update_invoice,
             delete_invoice,
-            // PDF generation
+            record_invoice_payment,
+            set_invoice_tax_details,
+            set_invoice_document_type,
+            // Branding commands
+            get_all_branding_profiles,
+            create_branding_profile,
+            update_branding_profile,
+            delete_branding_profile,
+            // Invoice template commands
+            get_invoice_templates,
+            set_artist_pdf_template,
+            set_invoice_pdf_template,
+            // PDF generation and e-invoice export
             generate_invoice_pdf,
+            generate_invoice_pdfs,
+            cancel_job,
+            export_invoice_ubl,
             get_downloads_directory,
+            // CSV export
+            export_invoices_csv,
+            export_projects_csv,
+            export_artists_csv,
+            export_payments_csv,
+            ar_aging_report,
+            // Email commands
+            get_smtp_settings,
+            update_smtp_settings,
+            send_test_email,
+            get_invoice_email_template,
+            update_invoice_email_template,
+            send_invoice,
+            get_invoice_emails,
+            save_invoice_email,
+            get_default_reminder_schedule,
+            get_invoice_reminders,
+            send_payment_reminders,
+            // Workspaces
+            get_startup_error,
+            list_workspaces,
+            get_current_workspace,
+            create_workspace,
+            open_workspace,
+            switch_workspace,
+            rename_workspace,
+            // Backup and import
+            export_backup,
+            import_backup,
+            get_snapshot_settings,
+            update_snapshot_settings,
+            list_snapshots,
+            take_snapshot,
+            restore_snapshot,
+            import_artists,
+            import_projects,
+            import_invoices,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,120 @@
+// src-tauri/src/payment_qr.rs
+use qrcode::{Color, EcLevel, QrCode};
+
+use crate::database::{Artist, BankDetails, Invoice};
+use crate::pdf_generator::Totals;
+
+// EPC069-12 field limits
+const EPC_MAX_NAME: usize = 70;
+const EPC_MAX_REMITTANCE: usize = 140;
+const EPC_MAX_AMOUNT: f64 = 999_999_999.99;
+
+/// The modules of an encoded QR code; `true` is a dark module.
+pub struct QrMatrix {
+    pub width: usize,
+    modules: Vec<bool>,
+}
+
+impl QrMatrix {
+    pub fn is_dark(&self, x: usize, y: usize) -> bool {
+        self.modules[y * self.width + x]
+    }
+}
+
+pub fn encode(payload: &str) -> Result<QrMatrix, String> {
+    // The EPC guidelines require error correction level M
+    let code = QrCode::with_error_correction_level(payload.as_bytes(), EcLevel::M)
+        .map_err(|e| format!("Failed to encode QR code: {}", e))?;
+    Ok(QrMatrix {
+        width: code.width(),
+        modules: code.to_colors().into_iter().map(|c| c == Color::Dark).collect(),
+    })
+}
+
+/// Builds the QR payload the artist has configured for `invoice`, or None
+/// when the artist doesn't use a payment QR code.
+pub fn payment_payload(artist: &Artist, invoice: &Invoice) -> Result<Option<String>, String> {
+    let details = &artist.bank_details;
+    match details.payment_qr.as_deref().map(str::trim) {
+        None | Some("") => Ok(None),
+        Some("epc") => {
+            if invoice.currency != "EUR" {
+                return Err(format!("SEPA QR codes need a EUR invoice, not {}", invoice.currency));
+            }
+            let name = non_empty(&details.account_holder)
+                .or(non_empty(&artist.company_name))
+                .unwrap_or(&artist.name);
+            let amount = Totals::for_invoice(invoice).gross;
+            epc_payload(name, details, amount, &invoice.invoice_number).map(Some)
+        }
+        Some("uri") => {
+            let template = non_empty(&details.payment_uri)
+                .ok_or_else(|| "Payment QR is set to 'uri' but no payment URI is configured".to_string())?;
+            Ok(Some(uri_payload(template, details, invoice)))
+        }
+        Some(other) => Err(format!("Unknown payment QR type '{}'", other)),
+    }
+}
+
+/// An EPC069-12 (version 002) SEPA credit transfer payload. The amount is
+/// always in euro and the invoice number goes in the unstructured remittance.
+pub fn epc_payload(name: &str, details: &BankDetails, amount: f64, remittance: &str) -> Result<String, String> {
+    let iban = non_empty(&details.iban)
+        .map(|iban| iban.replace(' ', "").to_uppercase())
+        .ok_or_else(|| "A SEPA QR code needs an IBAN".to_string())?;
+    let bic = non_empty(&details.bic).map(str::to_uppercase).unwrap_or_default();
+
+    // The amount is optional; leave it out rather than encode one banks reject
+    let amount = if (0.01..=EPC_MAX_AMOUNT).contains(&amount) {
+        format!("EUR{:.2}", amount)
+    } else {
+        String::new()
+    };
+
+    let lines = [
+        "BCD",
+        "002",
+        "1", // UTF-8
+        "SCT",
+        &bic,
+        &truncate(name.trim(), EPC_MAX_NAME),
+        &iban,
+        &amount,
+        "", // Purpose
+        "", // Structured creditor reference
+        &truncate(remittance.trim(), EPC_MAX_REMITTANCE),
+    ];
+    Ok(lines.join("\n"))
+}
+
+/// Fills the `{amount}`, `{invoice_number}`, `{iban}`, `{bic}` and
+/// `{account_holder}` placeholders of a payment URI, percent-encoding values.
+pub fn uri_payload(template: &str, details: &BankDetails, invoice: &Invoice) -> String {
+    let value = |v: &Option<String>| percent_encode(non_empty(v).unwrap_or(""));
+    template
+        .trim()
+        .replace("{amount}", &format!("{:.2}", Totals::for_invoice(invoice).gross))
+        .replace("{invoice_number}", &percent_encode(&invoice.invoice_number))
+        .replace("{iban}", &value(&details.iban).replace("%20", ""))
+        .replace("{bic}", &value(&details.bic))
+        .replace("{account_holder}", &value(&details.account_holder))
+}
+
+fn percent_encode(value: &str) -> String {
+    let mut encoded = String::new();
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+fn truncate(value: &str, max_chars: usize) -> String {
+    value.chars().take(max_chars).collect()
+}
+
+fn non_empty(value: &Option<String>) -> Option<&str> {
+    value.as_deref().map(str::trim).filter(|s| !s.is_empty())
+}
@@ -0,0 +1,177 @@
+// src-tauri/src/payment_terms.rs
+use chrono::{Datelike, Duration, NaiveDate};
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
+use serde::{Deserialize, Serialize};
+
+// Longest term accepted, in days
+const MAX_DAYS: u32 = 365;
+
+/// When an invoice has to be paid. Stored as JSON, e.g.
+/// `{"type": "net", "days": 30}` or
+/// `{"type": "early_discount", "discount_percent": 2, "discount_days": 10, "net_days": 30}`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PaymentTerms {
+    UponReceipt,
+    // Net N: N days after the issue date
+    Net { days: u32 },
+    // N days after the end of the month the invoice was issued in
+    EndOfMonth { days: u32 },
+    FixedDate { date: NaiveDate },
+    // e.g. 2/10 net 30: 2% off when paid within 10 days, otherwise due in 30
+    EarlyDiscount { discount_percent: f64, discount_days: u32, net_days: u32 },
+}
+
+impl PaymentTerms {
+    pub fn due_date(&self, issue_date: NaiveDate) -> NaiveDate {
+        match self {
+            PaymentTerms::UponReceipt => issue_date,
+            PaymentTerms::Net { days } => issue_date + Duration::days(i64::from(*days)),
+            PaymentTerms::EndOfMonth { days } => end_of_month(issue_date) + Duration::days(i64::from(*days)),
+            PaymentTerms::FixedDate { date } => *date,
+            PaymentTerms::EarlyDiscount { net_days, .. } => issue_date + Duration::days(i64::from(*net_days)),
+        }
+    }
+
+    /// The last day the early-payment discount applies, for terms that have one.
+    pub fn discount(&self, issue_date: NaiveDate) -> Option<(f64, NaiveDate)> {
+        match self {
+            PaymentTerms::EarlyDiscount { discount_percent, discount_days, .. } => {
+                Some((*discount_percent, issue_date + Duration::days(i64::from(*discount_days))))
+            }
+            _ => None,
+        }
+    }
+
+    /// Short form for the PDF's due row, e.g. "Net 30" or "2/10 Net 30".
+    pub fn label(&self) -> String {
+        match self {
+            PaymentTerms::UponReceipt => "Upon Receipt".to_string(),
+            PaymentTerms::Net { days } => format!("Net {}", days),
+            PaymentTerms::EndOfMonth { days: 0 } => "End of Month".to_string(),
+            PaymentTerms::EndOfMonth { days } => format!("End of Month + {}", days),
+            PaymentTerms::FixedDate { date } => format!("By {}", date.format("%-d %B %Y")),
+            PaymentTerms::EarlyDiscount { discount_percent, discount_days, net_days } => {
+                format!("{}/{} Net {}", discount_percent, discount_days, net_days)
+            }
+        }
+    }
+
+    /// The terms spelled out with their dates, for e-invoice payment notes.
+    pub fn description(&self, issue_date: NaiveDate) -> String {
+        let due = self.due_date(issue_date).format("%Y-%m-%d");
+        match self.discount(issue_date) {
+            Some((percent, until)) => format!(
+                "{}% discount if paid by {}, otherwise payment due by {}",
+                percent,
+                until.format("%Y-%m-%d"),
+                due
+            ),
+            None if *self == PaymentTerms::UponReceipt => "Payment due upon receipt".to_string(),
+            None => format!("{}: payment due by {}", self.label(), due),
+        }
+    }
+
+    pub fn validate(&self, issue_date: NaiveDate) -> Vec<String> {
+        let mut errors = Vec::new();
+        match self {
+            PaymentTerms::UponReceipt => {}
+            PaymentTerms::Net { days } | PaymentTerms::EndOfMonth { days } => {
+                if *days > MAX_DAYS {
+                    errors.push(format!("payment terms of {} days are longer than {} days", days, MAX_DAYS));
+                }
+            }
+            PaymentTerms::FixedDate { date } => {
+                if *date < issue_date {
+                    errors.push(format!("payment terms date {} is before issue_date {}", date, issue_date));
+                }
+            }
+            PaymentTerms::EarlyDiscount { discount_percent, discount_days, net_days } => {
+                if !(discount_percent.is_finite() && *discount_percent > 0.0 && *discount_percent < 100.0) {
+                    errors.push(format!("early payment discount {}% must be between 0 and 100", discount_percent));
+                }
+                if discount_days >= net_days {
+                    errors.push(format!(
+                        "discount period of {} days must be shorter than the {} day payment term",
+                        discount_days, net_days
+                    ));
+                }
+                if *net_days > MAX_DAYS {
+                    errors.push(format!("payment terms of {} days are longer than {} days", net_days, MAX_DAYS));
+                }
+            }
+        }
+        errors
+    }
+}
+
+fn end_of_month(date: NaiveDate) -> NaiveDate {
+    let (year, month) = if date.month() == 12 { (date.year() + 1, 1) } else { (date.year(), date.month() + 1) };
+    NaiveDate::from_ymd_opt(year, month, 1).unwrap() - Duration::days(1)
+}
+
+impl ToSql for PaymentTerms {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        let json = serde_json::to_string(self).map_err(|e| rusqlite::Error::ToSqlConversionFailure(e.into()))?;
+        Ok(ToSqlOutput::from(json))
+    }
+}
+
+impl FromSql for PaymentTerms {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        serde_json::from_str(value.as_str()?).map_err(|e| FromSqlError::Other(e.into()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(value: &str) -> NaiveDate {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn due_dates() {
+        let issued = date("2024-12-10");
+        assert_eq!(PaymentTerms::UponReceipt.due_date(issued), issued);
+        assert_eq!(PaymentTerms::Net { days: 30 }.due_date(issued), date("2025-01-09"));
+        assert_eq!(PaymentTerms::EndOfMonth { days: 0 }.due_date(issued), date("2024-12-31"));
+        assert_eq!(PaymentTerms::EndOfMonth { days: 10 }.due_date(date("2024-02-05")), date("2024-03-10"));
+        let discount = PaymentTerms::EarlyDiscount { discount_percent: 2.0, discount_days: 10, net_days: 30 };
+        assert_eq!(discount.due_date(issued), date("2025-01-09"));
+        assert_eq!(discount.discount(issued), Some((2.0, date("2024-12-20"))));
+    }
+
+    #[test]
+    fn labels_and_descriptions() {
+        let issued = date("2024-05-01");
+        assert_eq!(PaymentTerms::Net { days: 14 }.label(), "Net 14");
+        assert_eq!(PaymentTerms::EndOfMonth { days: 0 }.label(), "End of Month");
+        assert_eq!(PaymentTerms::FixedDate { date: date("2024-05-15") }.label(), "By 15 May 2024");
+        let discount = PaymentTerms::EarlyDiscount { discount_percent: 2.0, discount_days: 10, net_days: 30 };
+        assert_eq!(discount.label(), "2/10 Net 30");
+        assert_eq!(
+            discount.description(issued),
+            "2% discount if paid by 2024-05-11, otherwise payment due by 2024-05-31"
+        );
+        assert_eq!(PaymentTerms::Net { days: 14 }.description(issued), "Net 14: payment due by 2024-05-15");
+    }
+
+    #[test]
+    fn stored_as_json() {
+        let terms: PaymentTerms = serde_json::from_str(r#"{"type": "end_of_month", "days": 15}"#).unwrap();
+        assert_eq!(terms, PaymentTerms::EndOfMonth { days: 15 });
+        assert_eq!(serde_json::to_string(&PaymentTerms::UponReceipt).unwrap(), r#"{"type":"upon_receipt"}"#);
+    }
+
+    #[test]
+    fn validation() {
+        let issued = date("2024-05-01");
+        assert!(PaymentTerms::Net { days: 365 }.validate(issued).is_empty());
+        assert_eq!(PaymentTerms::Net { days: 366 }.validate(issued).len(), 1);
+        assert_eq!(PaymentTerms::FixedDate { date: date("2024-04-30") }.validate(issued).len(), 1);
+        let discount = PaymentTerms::EarlyDiscount { discount_percent: 100.0, discount_days: 30, net_days: 30 };
+        assert_eq!(discount.validate(issued).len(), 2);
+    }
+}
@@ -1,303 +1,1510 @@
-// src/services/pdfGenerator.js
-import jsPDF from 'jspdf'
-import 'jspdf-autotable'
-import { supabase } from '@/lib/supabase'
-
-export async function generateInvoicePDF(invoiceData) {
-  const { invoice, artist, project, line_items } = invoiceData
-  
-  // Create new PDF document (A4 size)
-  const doc = new jsPDF({
-    orientation: 'portrait',
-    unit: 'pt',
-    format: 'a4'
-  })
-  
-  // Constants matching Rust version
-  const MARGIN = 50
-  const PAGE_WIDTH = 595  // A4 width in points
-  const PAGE_HEIGHT = 842 // A4 height in points
-  
-  let yPosition = PAGE_HEIGHT - MARGIN
-  
-  // Artist Name and Company (top left)
-  let artistName = artist.name
-  if (artist.company_name && artist.company_name.trim()) {
-    artistName = `${artist.name} / ${artist.company_name}`
-  }
-  
-  doc.setFontSize(16)
-  doc.setFont(undefined, 'bold')
-  doc.text(artistName, MARGIN, yPosition)
-  yPosition -= 20
-  
-  // Artist Address
-  doc.setFontSize(10)
-  doc.setFont(undefined, 'normal')
-  if (artist.address && artist.address.trim()) {
-    const addressLines = artist.address.split('\n')
-    for (let i = 0; i < Math.min(addressLines.length, 3); i++) {
-      doc.text(addressLines[i], MARGIN, yPosition)
-      yPosition -= 15
-    }
-  }
-  
-  // Artist Contact Info
-  if (artist.email && artist.email.trim()) {
-    doc.text(artist.email, MARGIN, yPosition)
-    yPosition -= 15
-  }
-  
-  if (artist.phone && artist.phone.trim()) {
-    doc.text(artist.phone, MARGIN, yPosition)
-  }
-  
-  // INVOICE header (right side)
-  doc.setFontSize(20)
-  doc.setFont(undefined, 'bold')
-  doc.text('INVOICE', PAGE_WIDTH - 150, PAGE_HEIGHT - MARGIN)
-  
-  // Invoice Number and Date (right side)
-  const invoiceY = PAGE_HEIGHT - 80
-  doc.setFontSize(12)
-  doc.setFont(undefined, 'bold')
-  doc.text(`#${invoice.invoice_number}`, PAGE_WIDTH - 150, invoiceY)
-  
-  doc.setFont(undefined, 'normal')
-  doc.setFontSize(12)
-  doc.text(formatDate(invoice.issue_date), PAGE_WIDTH - 150, invoiceY - 20)
-  
-  // Bill To section
-  yPosition = PAGE_HEIGHT - 160
-  doc.setFontSize(10)
-  doc.setFont(undefined, 'bold')
-  doc.text('BILL TO:', MARGIN, yPosition)
-  yPosition -= 15
-  
-  doc.setFont(undefined, 'normal')
-  if (invoice.bill_to && invoice.bill_to.trim()) {
-    const billToLines = invoice.bill_to.split('\n')
-    for (let i = 0; i < Math.min(billToLines.length, 4); i++) {
-      doc.text(billToLines[i], MARGIN, yPosition)
-      yPosition -= 15
-    }
-  } else {
-    doc.text('[Client Name]', MARGIN, yPosition)
-  }
-  
-  // Move down for the table
-  yPosition = PAGE_HEIGHT - 240
-  
-  // Table Header
-  drawTableRow(doc, yPosition, true)
-  
-  doc.setFontSize(12)
-  doc.setFont(undefined, 'bold')
-  doc.text('ITEM', MARGIN, yPosition - 15)
-  doc.text('COST', PAGE_WIDTH - 150, yPosition - 15)
-  yPosition -= 30
-  
-  // Line Items
-  doc.setFont(undefined, 'normal')
-  for (const item of line_items) {
-    const rowHeight = calculateRowHeight(item)
-    drawTableRow(doc, yPosition, false, rowHeight)
-    
-    // Build description with all details
-    let itemY = yPosition - 15
-    
-    // Main description
-    doc.setFontSize(11)
-    doc.text(item.description, MARGIN, itemY)
-    itemY -= 15
-    
-    // Additional details in smaller font
-    doc.setFontSize(10)
-    const details = []
-    if (item.artist) details.push(`Artist: ${item.artist}`)
-    if (item.songProject) details.push(`Song/Project: ${item.songProject}`)
-    if (item.company) details.push(`Company: ${item.company}`)
-    
-    if (details.length > 0) {
-      doc.text(details.join(' | '), MARGIN, itemY)
-      itemY -= 15
-    }
-    
-    // Status indicators
-    const status = []
-    if (item.delivered) status.push('✓ Delivered')
-    if (item.termsAgreed) status.push('✓ Terms Agreed')
-    if (item.invoiced) status.push('✓ Invoiced')
-    if (item.upstreamed) status.push(`✓ Upstreamed ($${item.upstreamAmount || 0})`)
-    
-    if (status.length > 0) {
-      doc.text(status.join(' | '), MARGIN, itemY)
-    }
-    
-    // Amount
-    doc.setFontSize(11)
-    const amountStr = formatCurrency(item.amount)
-    doc.text(amountStr, PAGE_WIDTH - 150, yPosition - 15)
-    
-    yPosition -= rowHeight
-  }
-  
-  // Due and Total rows
-  yPosition -= 20
-  
-  // Due row
-  drawTableRow(doc, yPosition, false)
-  doc.setFontSize(12)
-  doc.setFont(undefined, 'bold')
-  doc.text('Due', MARGIN, yPosition - 15)
-  
-  // Calculate due date text
-  let dueText = 'Upon Receipt'
-  if (invoice.due_date !== invoice.issue_date) {
-    const daysDiff = calculateDaysDifference(invoice.issue_date, invoice.due_date)
-    if (daysDiff === 30) {
-      dueText = 'Net 30'
-    } else if (daysDiff > 0) {
-      dueText = `Net ${daysDiff}`
-    }
-  }
-  
-  doc.setFontSize(11)
-  doc.setFont(undefined, 'normal')
-  doc.text(dueText, PAGE_WIDTH - 150, yPosition - 15)
-  yPosition -= 30
-  
-  // Total row
-  drawTableRow(doc, yPosition, false)
-  doc.setFontSize(12)
-  doc.setFont(undefined, 'bold')
-  doc.text('Total', MARGIN, yPosition - 15)
-  
-  const totalStr = formatCurrency(invoice.amount)
-  doc.text(totalStr, PAGE_WIDTH - 150, yPosition - 15)
-  
-  // Wire Details Footer
-  yPosition = 100
-  
-  // Draw line above wire details
-  drawLine(doc, MARGIN, yPosition + 20, PAGE_WIDTH - MARGIN, yPosition + 20)
-  
-  if (artist.wire_details && artist.wire_details.trim()) {
-    doc.setFontSize(10)
-    doc.setFont(undefined, 'bold')
-    doc.text('PAYMENT DETAILS', PAGE_WIDTH / 2 - 40, yPosition, { align: 'center' })
-    yPosition -= 15
-    
-    doc.setFont(undefined, 'normal')
-    doc.setFontSize(8)
-    const wireLines = artist.wire_details.split('\n')
-    for (let i = 0; i < Math.min(wireLines.length, 4); i++) {
-      if (wireLines[i].trim()) {
-        doc.text(wireLines[i], MARGIN, yPosition)
-        yPosition -= 12
-      }
-    }
-  }
-  
-  // Generate blob
-  const pdfBlob = doc.output('blob')
-  
-  // Create filename
-  const fileName = `${invoice.invoice_number.replace(/[^a-zA-Z0-9]/g, '_')}_${Date.now()}.pdf`
-  
-  // Upload to Supabase Storage
-  const { data, error } = await supabase.storage
-    .from('invoices')
-    .upload(fileName, pdfBlob, {
-      contentType: 'application/pdf',
-      upsert: true
-    })
-  
-  if (error) throw error
-  
-  // Get public URL
-  const { data: { publicUrl } } = supabase.storage
-    .from('invoices')
-    .getPublicUrl(fileName)
-  
-  // Update invoice with PDF URL
-  await supabase
-    .from('invoices')
-    .update({ pdf_url: publicUrl })
-    .eq('id', invoice.id)
-  
-  return publicUrl
-}
-
-function drawLine(doc, x1, y1, x2, y2) {
-  doc.setDrawColor(0, 0, 0)
-  doc.setLineWidth(1)
-  doc.line(x1, y1, x2, y2)
-}
-
-function drawTableRow(doc, yPosition, isHeader, height = 30) {
-  const MARGIN = 50
-  const PAGE_WIDTH = 595
-  
-  // Draw top line
-  drawLine(doc, MARGIN, yPosition, PAGE_WIDTH - MARGIN, yPosition)
-  
-  // Draw bottom line
-  drawLine(doc, MARGIN, yPosition - height, PAGE_WIDTH - MARGIN, yPosition - height)
-  
-  // Draw vertical lines
-  drawLine(doc, MARGIN, yPosition, MARGIN, yPosition - height)
-  drawLine(doc, PAGE_WIDTH - 200, yPosition, PAGE_WIDTH - 200, yPosition - height)
-  drawLine(doc, PAGE_WIDTH - MARGIN, yPosition, PAGE_WIDTH - MARGIN, yPosition - height)
-}
-
-function calculateRowHeight(item) {
-  let lines = 1 // Base description
-  
-  // Add line for additional details if present
-  if (item.artist || item.songProject || item.company) {
-    lines++
-  }
-  
-  // Add line for status indicators if present
-  if (item.delivered || item.termsAgreed || item.invoiced || item.upstreamed) {
-    lines++
-  }
-  
-  return 30 + (15 * (lines - 1))
-}
-
-function formatDate(dateStr) {
-  if (!dateStr) return ''
-  const date = new Date(dateStr)
-  const months = ['January', 'February', 'March', 'April', 'May', 'June', 
-                  'July', 'August', 'September', 'October', 'November', 'December']
-  return `${date.getDate()} ${months[date.getMonth()]} ${date.getFullYear()}`
-}
-
-function calculateDaysDifference(startDate, endDate) {
-  const start = new Date(startDate)
-  const end = new Date(endDate)
-  const diffTime = Math.abs(end - start)
-  return Math.ceil(diffTime / (1000 * 60 * 60 * 24))
-}
-
-function formatCurrency(amount) {
-  // Format with thousands separator
-  const whole = Math.trunc(amount)
-  const cents = Math.round((amount - whole) * 100)
-  
-  // Format the whole part with commas
-  const wholeStr = whole.toString()
-    .split('')
-    .reverse()
-    .reduce((acc, digit, i) => {
-      if (i > 0 && i % 3 === 0) acc.push(',')
-      acc.push(digit)
-      return acc
-    }, [])
-    .reverse()
-    .join('')
-  
-  return `$${wholeStr}.${cents.toString().padStart(2, '0')}`
-}
\ No newline at end of file
+// src-tauri/src/pdf_generator.rs
+use printpdf::image_crate::{DynamicImage, GenericImageView};
+use printpdf::lopdf::{self, content::Operation, Object};
+use printpdf::path::PaintMode;
+use printpdf::{
+    Color, CurTransMat, Greyscale, Image, ImageTransform, IndirectFontRef, Line, Mm, PdfDocument,
+    PdfDocumentReference, PdfLayerReference, Point, Pt, Rect, Rgb,
+};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::PathBuf;
+use chrono::NaiveDate;
+
+use crate::aging::{AgingBucket, AgingReport, BucketTotals};
+use crate::branding::Branding;
+use crate::database::{Artist, BankDetails, Invoice, Project};
+use crate::facturx;
+use crate::fonts::{self, FontFace, FontStack};
+use crate::payment_qr::{self, QrMatrix};
+use crate::pdf_template::{Align, Block, BlockKind, Borders, CellContent, Column, PageSpec, Template};
+
+// Fixed positions below the footer (points from the bottom edge)
+const PAGE_NUMBER_Y: f32 = 30.0;
+const FOOTER_TEXT_Y: f32 = 18.0;
+
+const LOGO_PADDING: f32 = 6.0;
+const LOGO_GAP: f32 = 10.0;
+const CELL_PADDING: f32 = 5.0;
+const STAMP_PADDING: f32 = 8.0;
+const STAMP_DETAIL_SIZE: f32 = 8.0;
+const QR_GAP: f32 = 12.0;
+
+// Name of the page graphics state that makes the watermark translucent
+const WATERMARK_STATE: &str = "Watermark";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LineItem {
+    pub description: String,
+    #[serde(default)]
+    pub artist: Option<String>,
+    #[serde(default)]
+    pub song_project: Option<String>,
+    #[serde(default)]
+    pub company: Option<String>,
+    #[serde(default)]
+    pub delivered: bool,
+    #[serde(default)]
+    pub terms_agreed: bool,
+    #[serde(default)]
+    pub invoiced: bool,
+    #[serde(default)]
+    pub upstreamed: bool,
+    #[serde(default)]
+    pub upstream_amount: Option<f64>,
+    #[serde(default)]
+    pub amount: f64,
+}
+
+impl LineItem {
+    pub fn details(&self) -> Vec<String> {
+        let mut details = Vec::new();
+        if let Some(artist) = self.artist.as_deref().filter(|s| !s.trim().is_empty()) {
+            details.push(format!("Artist: {}", artist));
+        }
+        if let Some(song) = self.song_project.as_deref().filter(|s| !s.trim().is_empty()) {
+            details.push(format!("Song/Project: {}", song));
+        }
+        if let Some(company) = self.company.as_deref().filter(|s| !s.trim().is_empty()) {
+            details.push(format!("Company: {}", company));
+        }
+        details
+    }
+
+    fn status(&self, currency: &str) -> Vec<String> {
+        let mut status = Vec::new();
+        if self.delivered {
+            status.push("✓ Delivered".to_string());
+        }
+        if self.terms_agreed {
+            status.push("✓ Terms Agreed".to_string());
+        }
+        if self.invoiced {
+            status.push("✓ Invoiced".to_string());
+        }
+        if self.upstreamed {
+            status.push(format!(
+                "✓ Upstreamed ({})",
+                format_currency(self.upstream_amount.unwrap_or(0.0), currency)
+            ));
+        }
+        status
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct InvoiceData {
+    pub invoice: Invoice,
+    pub artist: Artist,
+    pub project: Option<Project>,
+    pub line_items: Vec<LineItem>,
+}
+
+/// Net, tax and gross amounts of an invoice. Tax is only present when the
+/// invoice has a VAT rate.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Totals {
+    pub net: f64,
+    pub tax: Option<f64>,
+    pub gross: f64,
+}
+
+impl Totals {
+    pub fn for_invoice(invoice: &Invoice) -> Self {
+        let net = round_cents(invoice.amount);
+        let tax = invoice.tax_rate.map(|rate| round_cents(net * rate / 100.0));
+        Totals { net, tax, gross: net + tax.unwrap_or(0.0) }
+    }
+}
+
+/// How an invoice's status shows up on the PDF.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum StatusMark {
+    /// Diagonal text behind every page
+    Watermark(&'static str, (f32, f32, f32)),
+    /// Boxed stamp on the first page
+    Stamp(&'static str, (f32, f32, f32)),
+}
+
+const GRAY: (f32, f32, f32) = (0.45, 0.45, 0.45);
+const RED: (f32, f32, f32) = (0.78, 0.1, 0.1);
+const GREEN: (f32, f32, f32) = (0.1, 0.5, 0.2);
+
+fn status_mark(status: &str, template: &Template) -> Option<StatusMark> {
+    match status {
+        "draft" if template.watermark.enabled => Some(StatusMark::Watermark("DRAFT", GRAY)),
+        "cancelled" | "void" if template.watermark.enabled => Some(StatusMark::Watermark("VOID", RED)),
+        "paid" if template.stamp.enabled => Some(StatusMark::Stamp("PAID", GREEN)),
+        "overdue" if template.stamp.enabled && template.stamp.show_overdue => {
+            Some(StatusMark::Stamp("OVERDUE", RED))
+        }
+        _ => None,
+    }
+}
+
+/// Rendering settings that don't come from the invoice itself.
+#[derive(Clone, Default)]
+pub struct RenderOptions {
+    /// Directory with extra fonts used for glyphs the bundled font lacks
+    pub font_dir: Option<PathBuf>,
+    pub branding: Branding,
+    pub template: Template,
+    /// Produce a PDF/A-3b Factur-X invoice with embedded CII XML
+    pub facturx: bool,
+}
+
+/// Page geometry derived from the template, with y measured up from the
+/// bottom edge like PDF coordinates.
+struct Layout<'a> {
+    template: &'a Template,
+}
+
+impl<'a> Layout<'a> {
+    fn y_from_top(&self, top: f32) -> f32 {
+        self.template.page.height - top
+    }
+
+    fn table_top(&self, first_page: bool) -> f32 {
+        let table = &self.template.table;
+        self.y_from_top(if first_page { table.top } else { table.continuation_top })
+    }
+
+    /// Lowest y an item row may reach on a page that continues onto the next
+    /// one; leaves room for the carried subtotal row and the page number
+    fn continued_page_limit(&self) -> f32 {
+        self.template.page.margin + self.template.table.row_height
+    }
+
+    /// Lowest y the totals block may reach on the last page
+    fn footer_top(&self) -> f32 {
+        let footer = &self.template.footer;
+        if footer.payment_details {
+            footer.top + 10.0
+        } else {
+            self.template.page.margin
+        }
+    }
+
+    fn table_left(&self) -> f32 {
+        self.template.table.columns.iter().map(|c| c.x).fold(f32::MAX, f32::min)
+    }
+
+    fn table_right(&self) -> f32 {
+        self.template.table.columns.iter().map(|c| c.x + c.width).fold(0.0, f32::max)
+    }
+
+    /// The column that holds amounts, used for subtotal and total rows
+    fn amount_column(&self) -> &'a Column {
+        let columns = &self.template.table.columns;
+        columns
+            .iter()
+            .find(|c| c.content.contains(&CellContent::Amount))
+            .unwrap_or(&columns[columns.len() - 1])
+    }
+}
+
+/// One line of text inside a table cell.
+struct CellLine {
+    text: String,
+    size: f32,
+}
+
+/// A line item's text laid out into the template's columns.
+struct ItemRow {
+    cells: Vec<Vec<CellLine>>,
+    height: f32,
+}
+
+impl ItemRow {
+    fn new(item: &LineItem, template: &Template, fonts: &Fonts, currency: &str) -> Self {
+        let table = &template.table;
+        let stack = &fonts.regular.stack;
+
+        let cells: Vec<Vec<CellLine>> = table
+            .columns
+            .iter()
+            .map(|column| {
+                let width = column_text_width(column);
+                let mut lines = Vec::new();
+                for content in &column.content {
+                    let (texts, size) = match content {
+                        CellContent::Description => {
+                            (stack.wrap(&item.description, table.description_size, width), table.description_size)
+                        }
+                        CellContent::Details if table.show_details => {
+                            (fit_entries(stack, &item.details(), table.detail_size, width), table.detail_size)
+                        }
+                        CellContent::Status if table.show_status => {
+                            (fit_entries(stack, &item.status(currency), table.detail_size, width), table.detail_size)
+                        }
+                        CellContent::Amount => (vec![format_currency(item.amount, currency)], table.description_size),
+                        _ => continue,
+                    };
+                    lines.extend(texts.into_iter().map(|text| CellLine { text, size }));
+                }
+                lines
+            })
+            .collect();
+
+        let line_count = cells.iter().map(Vec::len).max().unwrap_or(0).max(1);
+        ItemRow {
+            cells,
+            height: table.row_height + table.line_height * (line_count - 1) as f32,
+        }
+    }
+}
+
+fn column_text_width(column: &Column) -> f32 {
+    match column.align {
+        Align::Left => column.width - column.text_offset - CELL_PADDING,
+        Align::Right => column.width - CELL_PADDING * 2.0,
+    }
+}
+
+/// Joins short entries on one line, or gives each its own (wrapped) lines
+/// when they don't fit the column together.
+fn fit_entries(stack: &FontStack, entries: &[String], size: f32, width: f32) -> Vec<String> {
+    if entries.is_empty() {
+        return Vec::new();
+    }
+
+    let joined = entries.join(" | ");
+    if stack.text_width(&joined, size) <= width {
+        return vec![joined];
+    }
+    entries.iter().flat_map(|entry| stack.wrap(entry, size, width)).collect()
+}
+
+/// The line items and carried subtotals that end up on a single page.
+#[derive(Debug, Clone, PartialEq)]
+struct PageLayout {
+    items: std::ops::Range<usize>,
+    /// Subtotal brought forward from the previous page
+    carried_in: Option<f64>,
+    /// Subtotal carried to the next page (None on the last page)
+    carried_out: Option<f64>,
+}
+
+/// Splits the line items across pages so no row crosses the bottom of a page
+/// and the totals block plus footer fit on the last one.
+fn paginate(layout: &Layout, items: &[LineItem], rows: &[ItemRow], totals_height: f32) -> Vec<PageLayout> {
+    let row_height = layout.template.table.row_height;
+    let mut pages = Vec::new();
+    let mut start = 0;
+    let mut running_total = 0.0;
+    let mut carried_in = None;
+
+    loop {
+        let mut y = layout.table_top(pages.is_empty());
+        y -= row_height; // Table header
+        if carried_in.is_some() {
+            y -= row_height; // Brought forward row
+        }
+
+        let mut end = start;
+        while end < items.len() {
+            let height = rows[end].height;
+            // Always place at least one item per page, even if it is taller than the page
+            if y - height < layout.continued_page_limit() && end > start {
+                break;
+            }
+            y -= height;
+            running_total += items[end].amount;
+            end += 1;
+        }
+
+        // An empty continuation page takes the totals even if they overflow it,
+        // otherwise a template with oversized totals would never finish
+        let fits_totals = y - totals_height >= layout.footer_top() || (end == start && carried_in.is_some());
+        let is_last = end == items.len() && fits_totals;
+        pages.push(PageLayout {
+            items: start..end,
+            carried_in,
+            carried_out: if is_last { None } else { Some(running_total) },
+        });
+
+        if is_last {
+            return pages;
+        }
+
+        carried_in = Some(running_total);
+        start = end;
+    }
+}
+
+/// A font stack together with the embedded PDF font for each of its faces.
+struct PdfFont {
+    stack: FontStack,
+    refs: Vec<IndirectFontRef>,
+}
+
+impl PdfFont {
+    fn embed(doc: &PdfDocumentReference, stack: FontStack) -> Result<Self, Box<dyn Error>> {
+        // Glyphs are subset on save, so only the characters we draw get embedded
+        let refs = stack
+            .faces()
+            .iter()
+            .map(|face| doc.add_external_font(face.bytes()))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(PdfFont { stack, refs })
+    }
+}
+
+struct Fonts {
+    regular: PdfFont,
+    bold: PdfFont,
+}
+
+impl Fonts {
+    fn load(doc: &PdfDocumentReference, options: &RenderOptions) -> Result<Self, Box<dyn Error>> {
+        let fallbacks = options
+            .font_dir
+            .as_deref()
+            .map(fonts::load_fallback_faces)
+            .unwrap_or_default();
+
+        let regular = FontFace::bundled_regular();
+        let bold = FontFace::bundled_bold();
+
+        // A branding font replaces both weights; the bundled font stays in the
+        // stack to cover glyphs the branding font lacks
+        let (regular, bold) = match &options.branding.font {
+            Some(font) => (
+                FontStack::new(font.clone(), &[&[regular][..], &fallbacks].concat()),
+                FontStack::new(font.clone(), &[&[bold][..], &fallbacks].concat()),
+            ),
+            None => (FontStack::new(regular, &fallbacks), FontStack::new(bold, &fallbacks)),
+        };
+
+        Ok(Fonts {
+            regular: PdfFont::embed(doc, regular)?,
+            bold: PdfFont::embed(doc, bold)?,
+        })
+    }
+
+    fn get(&self, bold: bool) -> &PdfFont {
+        if bold { &self.bold } else { &self.regular }
+    }
+}
+
+/// Everything the drawing functions need besides the layer they draw on.
+struct Renderer<'a> {
+    layout: Layout<'a>,
+    template: &'a Template,
+    fonts: Fonts,
+    branding: &'a Branding,
+    data: &'a InvoiceData,
+    payment_qr: Option<QrMatrix>,
+}
+
+pub fn generate_invoice_pdf(
+    data: InvoiceData,
+    path: PathBuf,
+    options: &RenderOptions,
+) -> Result<(), Box<dyn Error>> {
+    // Check the e-invoice data up front so nothing is rendered for nothing
+    if options.facturx {
+        facturx::validate(&data)?;
+    }
+
+    let template = &options.template;
+    let page_width = Mm::from(Pt(template.page.width));
+    let page_height = Mm::from(Pt(template.page.height));
+
+    let title = format!("Invoice {}", data.invoice.invoice_number);
+    let (doc, first_page, first_layer) = PdfDocument::new(&title, page_width, page_height, "Layer 1");
+
+    let renderer = Renderer {
+        layout: Layout { template },
+        template,
+        fonts: Fonts::load(&doc, options)?,
+        branding: &options.branding,
+        data: &data,
+        payment_qr: load_payment_qr(&data, template),
+    };
+
+    let rows: Vec<ItemRow> = data
+        .line_items
+        .iter()
+        .map(|item| ItemRow::new(item, template, &renderer.fonts, &data.invoice.currency))
+        .collect();
+    let notes = renderer.notes_lines();
+    let pages = paginate(&renderer.layout, &data.line_items, &rows, renderer.totals_height(&notes));
+    let page_count = pages.len();
+    let mark = status_mark(&data.invoice.status, template);
+
+    for (index, page) in pages.iter().enumerate() {
+        let layer = if index == 0 {
+            doc.get_page(first_page).get_layer(first_layer)
+        } else {
+            let (page_index, layer_index) = doc.add_page(page_width, page_height, "Layer 1");
+            doc.get_page(page_index).get_layer(layer_index)
+        };
+
+        // Watermarks go first so the page content is drawn over them
+        if let Some(StatusMark::Watermark(text, color)) = mark {
+            renderer.draw_watermark(&layer, text, color);
+        }
+
+        if index == 0 {
+            renderer.draw_header(&layer);
+            if let Some(StatusMark::Stamp(text, color)) = mark {
+                renderer.draw_stamp(&layer, text, color);
+            }
+        } else {
+            renderer.draw_continuation_header(&layer);
+        }
+
+        let mut y = renderer.draw_table_header(&layer, renderer.layout.table_top(index == 0));
+
+        if let Some(carried) = page.carried_in {
+            y = renderer.draw_summary_row(&layer, y, "Brought forward", &renderer.money(carried), true);
+        }
+
+        for row in &rows[page.items.clone()] {
+            y = renderer.draw_item_row(&layer, y, row);
+        }
+
+        match page.carried_out {
+            Some(subtotal) => {
+                renderer.draw_summary_row(&layer, y, "Subtotal (continued)", &renderer.money(subtotal), true);
+            }
+            None => {
+                renderer.draw_totals(&layer, y, &notes);
+                renderer.draw_footer(&layer);
+            }
+        }
+
+        if template.footer.page_numbers {
+            renderer.draw_page_number(&layer, index + 1, page_count);
+        }
+        if let Some(footer_text) = &options.branding.footer_text {
+            renderer.draw_footer_text(&layer, footer_text);
+        }
+    }
+
+    let mut pdf = doc.save_to_bytes()?;
+    if let Some(StatusMark::Watermark(..)) = mark {
+        pdf = add_watermark_opacity(&pdf, template.watermark.opacity)?;
+    }
+
+    if options.facturx {
+        let xml = facturx::build_cii_xml(&data);
+        let author = data.artist.company_name.as_deref().filter(|s| !s.trim().is_empty()).unwrap_or(&data.artist.name);
+        pdf = facturx::to_pdfa3(&pdf, &xml, &title, author)?;
+    }
+
+    std::fs::write(path, pdf)?;
+    Ok(())
+}
+
+/// Adds the graphics state the watermark is drawn with to every page. printpdf
+/// can't add transparency itself, so it's patched into the saved document.
+fn add_watermark_opacity(pdf: &[u8], opacity: f32) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut doc = lopdf::Document::load_mem(pdf)?;
+
+    // `ca` is the fill alpha text is painted with, `CA` the stroke alpha
+    let mut state = lopdf::Dictionary::new();
+    state.set("Type", Object::Name(b"ExtGState".to_vec()));
+    state.set("ca", Object::Real(opacity));
+    state.set("CA", Object::Real(opacity));
+    let state_id = doc.add_object(state);
+
+    let page_ids: Vec<_> = doc.get_pages().into_values().collect();
+    for page_id in page_ids {
+        let resources_id = doc.get_dictionary(page_id)?.get(b"Resources")?.as_reference()?;
+        let resources = doc.get_dictionary_mut(resources_id)?;
+        if !matches!(resources.get(b"ExtGState"), Ok(Object::Dictionary(_))) {
+            resources.set("ExtGState", lopdf::Dictionary::new());
+        }
+        if let Ok(Object::Dictionary(states)) = resources.get_mut(b"ExtGState") {
+            states.set(WATERMARK_STATE, Object::Reference(state_id));
+        }
+    }
+
+    let mut out = Vec::new();
+    doc.save_to(&mut out)?;
+    Ok(out)
+}
+
+/// Encodes the artist's payment QR code. Bad bank details are logged and
+/// skipped so they never stop an invoice from rendering.
+fn load_payment_qr(data: &InvoiceData, template: &Template) -> Option<QrMatrix> {
+    if !template.footer.payment_details || !template.footer.qr_code {
+        return None;
+    }
+
+    let qr = payment_qr::payment_payload(&data.artist, &data.invoice)
+        .and_then(|payload| payload.map(|p| payment_qr::encode(&p)).transpose());
+    match qr {
+        Ok(qr) => qr,
+        Err(e) => {
+            eprintln!("Skipping payment QR code for invoice {}: {}", data.invoice.invoice_number, e);
+            None
+        }
+    }
+}
+
+fn save(doc: PdfDocumentReference, path: PathBuf) -> Result<(), Box<dyn Error>> {
+    let file = File::create(path)?;
+    doc.save(&mut BufWriter::new(file))?;
+    Ok(())
+}
+
+/// Renders an accounts receivable aging report: bucket totals per artist and
+/// currency, then every open invoice, most overdue first. Uses the page size
+/// and fonts of `options`; the template's invoice layout doesn't apply.
+pub fn generate_aging_pdf(report: &AgingReport, path: PathBuf, options: &RenderOptions) -> Result<(), Box<dyn Error>> {
+    let page = &options.template.page;
+    let title = format!("Receivables aging {}", report.as_of);
+    let (doc, first_page, first_layer) =
+        PdfDocument::new(&title, Mm::from(Pt(page.width)), Mm::from(Pt(page.height)), "Layer 1");
+    let fonts = Fonts::load(&doc, options)?;
+    let mut report_page = ReportPage {
+        doc: &doc,
+        fonts: &fonts,
+        page,
+        layer: doc.get_page(first_page).get_layer(first_layer),
+        y: page.height - page.margin,
+    };
+
+    report_page.heading("Accounts Receivable Aging", 16.0);
+    report_page.line(&format!("As of {}", format_date(report.as_of)), 10.0, false);
+    report_page.y -= 12.0;
+
+    // Label column, then the five buckets and the total
+    let width = page.width - page.margin * 2.0;
+    let amount_width = (width * 0.7 / 6.0).floor();
+    let mut summary = vec![(width - amount_width * 6.0, false)];
+    summary.extend([(amount_width, true); 6]);
+    let mut header = vec!["Artist"];
+    header.extend(AgingBucket::ALL.map(AgingBucket::label));
+    header.push("Total");
+
+    let bucket_row = |label: String, totals: &BucketTotals| {
+        let mut cells = vec![label];
+        cells.extend(AgingBucket::ALL.map(|b| format_currency(totals.get(b), &totals.currency)));
+        cells.push(format_currency(totals.total, &totals.currency));
+        cells
+    };
+    let mut rows: Vec<(Vec<String>, bool)> = report
+        .artists
+        .iter()
+        .flat_map(|artist| artist.totals.iter().map(|t| (bucket_row(artist.artist_name.clone(), t), false)))
+        .collect();
+    rows.extend(report.totals.iter().map(|t| (bucket_row(format!("Total {}", t.currency), t), true)));
+    report_page.heading("Summary", 12.0);
+    report_page.table(&summary, &header, &rows);
+
+    report_page.y -= 12.0;
+    report_page.heading("Open invoices", 12.0);
+    let invoices = [
+        (width * 0.18, false),
+        (width * 0.3, false),
+        (width * 0.16, false),
+        (width * 0.12, true),
+        (width * 0.08, true),
+        (width * 0.16, true),
+    ];
+    let header = ["Invoice", "Artist", "Due", "Days overdue", "Bucket", "Outstanding"];
+    let rows: Vec<(Vec<String>, bool)> = report
+        .invoices
+        .iter()
+        .map(|entry| {
+            let cells = vec![
+                entry.invoice_number.clone(),
+                entry.artist_name.clone(),
+                format_date(entry.due_date),
+                entry.days_overdue.to_string(),
+                entry.bucket.label().to_string(),
+                format_currency(entry.outstanding, &entry.currency),
+            ];
+            (cells, false)
+        })
+        .collect();
+    report_page.table(&invoices, &header, &rows);
+
+    save(doc, path)
+}
+
+/// Writes report content top to bottom, starting a new page when one fills up.
+struct ReportPage<'a> {
+    doc: &'a PdfDocumentReference,
+    fonts: &'a Fonts,
+    page: &'a PageSpec,
+    layer: PdfLayerReference,
+    y: f32,
+}
+
+impl ReportPage<'_> {
+    const ROW_HEIGHT: f32 = 16.0;
+    const TEXT_SIZE: f32 = 8.0;
+
+    /// Starts a new page unless `height` still fits above the bottom margin.
+    fn make_room(&mut self, height: f32) -> bool {
+        if self.y - height >= self.page.margin {
+            return false;
+        }
+        let (page, layer) = self.doc.add_page(Mm::from(Pt(self.page.width)), Mm::from(Pt(self.page.height)), "Layer 1");
+        self.layer = self.doc.get_page(page).get_layer(layer);
+        self.y = self.page.height - self.page.margin;
+        true
+    }
+
+    fn heading(&mut self, text: &str, size: f32) {
+        self.make_room(size * 3.0);
+        self.line(text, size, true);
+    }
+
+    fn line(&mut self, text: &str, size: f32, bold: bool) {
+        self.y -= size * 1.4;
+        draw_text(&self.layer, self.fonts.get(bold), size, self.page.margin, self.y, text);
+    }
+
+    /// Rows of cells in columns of (width, right-aligned), with the header
+    /// repeated on every page the table runs onto.
+    fn table(&mut self, columns: &[(f32, bool)], header: &[&str], rows: &[(Vec<String>, bool)]) {
+        let header: Vec<String> = header.iter().map(|h| h.to_string()).collect();
+        self.make_room(Self::ROW_HEIGHT * 2.0);
+        self.row(columns, &header, true);
+        for (cells, bold) in rows {
+            if self.make_room(Self::ROW_HEIGHT) {
+                self.row(columns, &header, true);
+            }
+            self.row(columns, cells, *bold);
+        }
+    }
+
+    fn row(&mut self, columns: &[(f32, bool)], cells: &[String], bold: bool) {
+        let font = self.fonts.get(bold);
+        let baseline = self.y - Self::ROW_HEIGHT + 5.0;
+        let mut x = self.page.margin;
+        for ((width, right), cell) in columns.iter().zip(cells) {
+            let available = width - CELL_PADDING;
+            // Cells are cut to their first line rather than wrapped
+            let text = font.stack.wrap(cell, Self::TEXT_SIZE, available).into_iter().next().unwrap_or_default();
+            let text_x = if *right { x + width - font.stack.text_width(&text, Self::TEXT_SIZE) } else { x };
+            draw_text(&self.layer, font, Self::TEXT_SIZE, text_x, baseline, &text);
+            x += width;
+        }
+        self.y -= Self::ROW_HEIGHT;
+        if bold {
+            draw_line(&self.layer, self.page.margin, self.y + 1.0, x, self.y + 1.0);
+        }
+    }
+}
+
+impl<'a> Renderer<'a> {
+    fn draw_header(&self, layer: &PdfLayerReference) {
+        // Blocks marked beside_logo move right when a logo is actually drawn,
+        // so templates fall back to a plain text header without one
+        let logo_shift = self
+            .template
+            .header
+            .iter()
+            .find(|b| b.kind == BlockKind::Logo)
+            .filter(|_| self.branding.logo.is_some())
+            .map(|b| b.size + LOGO_GAP)
+            .unwrap_or(0.0);
+
+        for block in &self.template.header {
+            let shift = if block.beside_logo { logo_shift } else { 0.0 };
+            self.draw_block(layer, block, shift);
+        }
+    }
+
+    fn draw_block(&self, layer: &PdfLayerReference, block: &Block, shift: f32) {
+        let invoice = &self.data.invoice;
+        let x = block.x + shift;
+        let y = self.layout.y_from_top(block.top);
+        let width = block
+            .width
+            .map(|w| w - shift)
+            .unwrap_or(self.template.page.width - self.template.page.margin - x);
+
+        let lines: Vec<String> = match block.kind {
+            BlockKind::Logo => {
+                if let Some(logo) = &self.branding.logo {
+                    self.draw_logo(layer, block, logo);
+                }
+                return;
+            }
+            BlockKind::Artist => {
+                self.draw_artist_block(layer, block, x, y, width);
+                return;
+            }
+            BlockKind::BillTo => {
+                let bill_to = invoice.bill_to.as_deref().filter(|s| !s.trim().is_empty());
+                self.draw_multiline_block(layer, block, x, y, width, bill_to);
+                return;
+            }
+            BlockKind::Title => vec![block.text.clone().unwrap_or_else(|| "INVOICE".to_string())],
+            BlockKind::InvoiceNumber => vec![format!("#{}", invoice.invoice_number)],
+            BlockKind::IssueDate => vec![format_date(invoice.issue_date)],
+            BlockKind::DueDate => vec![format_date(invoice.due_date)],
+            BlockKind::Project => {
+                let project = self.data.project.as_ref().map(|p| p.name.as_str());
+                self.draw_multiline_block(layer, block, x, y, width, project);
+                return;
+            }
+            BlockKind::Text => {
+                let text = fill_placeholders(block.text.as_deref().unwrap_or(""), self.data);
+                self.fonts.get(block.bold).stack.wrap(&text, block.size, width)
+            }
+        };
+
+        let font = self.fonts.get(block.bold);
+        self.with_accent(layer, block.accent, || {
+            let mut line_y = y;
+            for (i, line) in lines.iter().take(block.max_lines.unwrap_or(usize::MAX)).enumerate() {
+                // Single-line blocks use their label as a prefix
+                let text = match (&block.label, i) {
+                    (Some(label), 0) => format!("{} {}", label, line),
+                    _ => line.clone(),
+                };
+                draw_text(layer, font, block.size, x, line_y, &text);
+                line_y -= block.line_height;
+            }
+        });
+    }
+
+    fn draw_artist_block(&self, layer: &PdfLayerReference, block: &Block, x: f32, y: f32, width: f32) {
+        let artist = &self.data.artist;
+        let mut y = y;
+
+        // Artist Name and Company
+        let artist_name = match artist.company_name.as_deref().filter(|s| !s.trim().is_empty()) {
+            Some(company) => format!("{} / {}", artist.name, company),
+            None => artist.name.clone(),
+        };
+        let title_size = block.title_size.unwrap_or(block.size + 6.0);
+        self.with_accent(layer, block.accent, || {
+            draw_text(layer, &self.fonts.bold, title_size, x, y, &artist_name);
+        });
+        y -= title_size + 4.0;
+
+        // Artist Address
+        if let Some(address) = artist.address.as_deref().filter(|s| !s.trim().is_empty()) {
+            let lines = self.fonts.regular.stack.wrap(address, block.size, width);
+            for line in lines.iter().take(block.max_lines.unwrap_or(3)) {
+                draw_text(layer, &self.fonts.regular, block.size, x, y, line);
+                y -= block.line_height;
+            }
+        }
+
+        // Artist Contact Info
+        for contact in [&artist.email, &artist.phone] {
+            if let Some(value) = contact.as_deref().filter(|s| !s.trim().is_empty()) {
+                draw_text(layer, &self.fonts.regular, block.size, x, y, value);
+                y -= block.line_height;
+            }
+        }
+    }
+
+    /// Draws a bold label heading followed by wrapped `content`, the
+    /// placeholder when there is no content, or nothing at all.
+    fn draw_multiline_block(
+        &self,
+        layer: &PdfLayerReference,
+        block: &Block,
+        x: f32,
+        y: f32,
+        width: f32,
+        content: Option<&str>,
+    ) {
+        let Some(content) = content.or(block.placeholder.as_deref()) else {
+            return;
+        };
+
+        let mut y = y;
+        if let Some(label) = &block.label {
+            self.with_accent(layer, block.accent, || {
+                draw_text(layer, &self.fonts.bold, block.size, x, y, label);
+            });
+            y -= block.line_height;
+        }
+
+        let font = self.fonts.get(block.bold);
+        let lines = font.stack.wrap(content, block.size, width);
+        for line in lines.iter().take(block.max_lines.unwrap_or(usize::MAX)) {
+            draw_text(layer, font, block.size, x, y, line);
+            y -= block.line_height;
+        }
+    }
+
+    fn draw_continuation_header(&self, layer: &PdfLayerReference) {
+        let invoice = &self.data.invoice;
+        let page = &self.template.page;
+        let y = page.height - page.margin;
+
+        let title = self
+            .template
+            .header
+            .iter()
+            .find(|b| b.kind == BlockKind::Title)
+            .and_then(|b| b.text.clone())
+            .unwrap_or_else(|| "INVOICE".to_string());
+        draw_text(
+            layer,
+            &self.fonts.bold,
+            12.0,
+            page.margin,
+            y,
+            &format!("{} #{} (continued)", title, invoice.invoice_number),
+        );
+
+        let date = format_date(invoice.issue_date);
+        let date_x = page.width - page.margin - self.fonts.regular.stack.text_width(&date, 10.0);
+        draw_text(layer, &self.fonts.regular, 10.0, date_x, y, &date);
+    }
+
+    fn draw_table_header(&self, layer: &PdfLayerReference, y: f32) -> f32 {
+        let table = &self.template.table;
+        let dividers: Vec<f32> = table.columns.iter().skip(1).map(|c| c.x).collect();
+        self.draw_row_frame(layer, y, table.row_height, &dividers, true);
+
+        self.with_accent(layer, true, || {
+            for column in &table.columns {
+                self.draw_cell_text(layer, &self.fonts.bold, column, table.header_size, y - 15.0, &column.header);
+            }
+        });
+        y - table.row_height
+    }
+
+    fn draw_item_row(&self, layer: &PdfLayerReference, y: f32, row: &ItemRow) -> f32 {
+        let table = &self.template.table;
+        let dividers: Vec<f32> = table.columns.iter().skip(1).map(|c| c.x).collect();
+        self.draw_row_frame(layer, y, row.height, &dividers, false);
+
+        for (column, lines) in table.columns.iter().zip(&row.cells) {
+            let mut line_y = y - 15.0;
+            for line in lines {
+                self.draw_cell_text(layer, &self.fonts.regular, column, line.size, line_y, &line.text);
+                line_y -= table.line_height;
+            }
+        }
+
+        y - row.height
+    }
+
+    /// A label in the first column and a value in the amount column.
+    fn draw_summary_row(&self, layer: &PdfLayerReference, y: f32, label: &str, value: &str, bold_value: bool) -> f32 {
+        let table = &self.template.table;
+        let amount_column = self.layout.amount_column();
+        self.draw_row_frame(layer, y, table.row_height, &[amount_column.x], false);
+
+        let size = self.template.totals.label_size;
+        self.draw_cell_text(layer, &self.fonts.bold, &table.columns[0], size, y - 15.0, label);
+        self.draw_cell_text(layer, self.fonts.get(bold_value), amount_column, size - 1.0, y - 15.0, value);
+        y - table.row_height
+    }
+
+    /// Wrapped invoice notes printed below the totals, if the template asks for them.
+    fn notes_lines(&self) -> Vec<String> {
+        let totals = &self.template.totals;
+        let notes = self.data.invoice.notes.as_deref().filter(|s| !s.trim().is_empty());
+        match notes {
+            Some(notes) if totals.show_notes => {
+                let width = self.layout.table_right() - self.layout.table_left();
+                self.fonts.regular.stack.wrap(notes, 9.0, width)
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    fn totals_height(&self, notes: &[String]) -> f32 {
+        let totals = &self.template.totals;
+        let table = &self.template.table;
+        let mut rows = if totals.show_due { 2.0 } else { 1.0 };
+        if self.data.invoice.tax_rate.is_some() {
+            rows += 2.0; // Subtotal and tax
+        }
+        let mut height = totals.gap + table.row_height * rows;
+        if !notes.is_empty() {
+            let label_lines = if totals.notes_label.is_some() { 1 } else { 0 };
+            height += 10.0 + table.line_height * (notes.len() + label_lines) as f32;
+        }
+        height
+    }
+
+    fn draw_totals(&self, layer: &PdfLayerReference, y: f32, notes: &[String]) {
+        let totals = &self.template.totals;
+        let invoice = &self.data.invoice;
+        let mut y = y - totals.gap;
+
+        if totals.show_due {
+            let terms = match &invoice.payment_terms {
+                Some(terms) => terms.label(),
+                None => due_text(invoice.issue_date, invoice.due_date),
+            };
+            y = self.draw_summary_row(layer, y, &totals.due_label, &terms, false);
+        }
+
+        let amounts = Totals::for_invoice(invoice);
+        if let (Some(rate), Some(tax)) = (invoice.tax_rate, amounts.tax) {
+            y = self.draw_summary_row(layer, y, &totals.subtotal_label, &self.money(amounts.net), false);
+            let tax_label = format!("{} {}%", totals.tax_label, rate);
+            y = self.draw_summary_row(layer, y, &tax_label, &self.money(tax), false);
+        }
+        y = self.draw_summary_row(layer, y, &totals.total_label, &self.money(amounts.gross), true);
+
+        if notes.is_empty() {
+            return;
+        }
+
+        let x = self.layout.table_left();
+        let line_height = self.template.table.line_height;
+        y -= 10.0 + line_height - 5.0;
+        if let Some(label) = &totals.notes_label {
+            draw_text(layer, &self.fonts.bold, 9.0, x, y, label);
+            y -= line_height;
+        }
+        for line in notes {
+            draw_text(layer, &self.fonts.regular, 9.0, x, y, line);
+            y -= line_height;
+        }
+    }
+
+    fn draw_footer(&self, layer: &PdfLayerReference) {
+        let footer = &self.template.footer;
+        let page = &self.template.page;
+        if !footer.payment_details {
+            return;
+        }
+
+        // Line above wire details
+        let mut y = footer.top;
+        layer.set_outline_color(self.branding.accent_color());
+        draw_line(layer, page.margin, y, page.width - page.margin, y);
+        layer.set_outline_color(black());
+
+        // The QR code sits against the right margin with the text beside it
+        let text_right = match &self.payment_qr {
+            Some(qr) => {
+                let size = footer.qr_size.min(footer.top - 10.0 - FOOTER_TEXT_Y - 8.0);
+                let x = page.width - page.margin - size;
+                draw_qr_code(layer, qr, x, footer.top - 10.0, size);
+                x - QR_GAP
+            }
+            None => page.width - page.margin,
+        };
+
+        let lines = self.payment_lines(text_right - page.margin);
+        if lines.is_empty() && self.payment_qr.is_none() {
+            return;
+        }
+
+        y -= 20.0;
+        let title_size = footer.size + 2.0;
+        let title_width = self.fonts.bold.stack.text_width(&footer.title, title_size);
+        let title_x = page.margin + (text_right - page.margin - title_width) / 2.0;
+        draw_text(layer, &self.fonts.bold, title_size, title_x, y, &footer.title);
+        y -= 15.0;
+
+        for line in &lines {
+            draw_text(layer, &self.fonts.regular, footer.size, page.margin, y, line);
+            y -= footer.size + 4.0;
+        }
+    }
+
+    /// The artist's free-form wire details, or their structured bank details
+    /// when no wire details were entered.
+    fn payment_lines(&self, width: f32) -> Vec<String> {
+        let footer = &self.template.footer;
+        let artist = &self.data.artist;
+        let text = match artist.wire_details.as_deref().filter(|s| !s.trim().is_empty()) {
+            Some(wire_details) => wire_details.to_string(),
+            None => bank_detail_lines(&artist.bank_details).join("\n"),
+        };
+
+        self.fonts
+            .regular
+            .stack
+            .wrap(&text, footer.size, width)
+            .into_iter()
+            .filter(|l| !l.trim().is_empty())
+            .take(footer.max_lines)
+            .collect()
+    }
+
+    fn draw_footer_text(&self, layer: &PdfLayerReference, text: &str) {
+        let page = &self.template.page;
+        let stack = &self.fonts.regular.stack;
+        let line = stack.wrap(text, 7.0, page.width - page.margin * 2.0).into_iter().next().unwrap_or_default();
+        let x = (page.width - stack.text_width(&line, 7.0)) / 2.0;
+        draw_text(layer, &self.fonts.regular, 7.0, x, FOOTER_TEXT_Y, &line);
+    }
+
+    fn draw_page_number(&self, layer: &PdfLayerReference, page: usize, page_count: usize) {
+        let text = page_label(page, page_count);
+        let x = (self.template.page.width - self.fonts.regular.stack.text_width(&text, 8.0)) / 2.0;
+        draw_text(layer, &self.fonts.regular, 8.0, x, PAGE_NUMBER_Y, &text);
+    }
+
+    /// Draws the logo centered in an accent-colored badge, so light logos such
+    /// as the bundled white one stay visible on the page.
+    fn draw_logo(&self, layer: &PdfLayerReference, block: &Block, logo: &DynamicImage) {
+        let size = block.size;
+        let top = self.layout.y_from_top(block.top);
+        let bottom = top - size;
+
+        layer.set_fill_color(self.branding.accent_color());
+        layer.add_rect(
+            Rect::new(
+                Mm::from(Pt(block.x)),
+                Mm::from(Pt(bottom)),
+                Mm::from(Pt(block.x + size)),
+                Mm::from(Pt(top)),
+            )
+            .with_mode(PaintMode::Fill),
+        );
+        layer.set_fill_color(black());
+
+        let (width, height) = logo.dimensions();
+        if width == 0 || height == 0 {
+            return;
+        }
+
+        // At 72 dpi one pixel is one point, so the scale maps pixels straight to points
+        let inner = size - LOGO_PADDING * 2.0;
+        let scale = (inner / width as f32).min(inner / height as f32);
+        let x = block.x + (size - width as f32 * scale) / 2.0;
+        let y = bottom + (size - height as f32 * scale) / 2.0;
+
+        Image::from_dynamic_image(logo).add_to_layer(
+            layer.clone(),
+            ImageTransform {
+                translate_x: Some(Mm::from(Pt(x))),
+                translate_y: Some(Mm::from(Pt(y))),
+                scale_x: Some(scale),
+                scale_y: Some(scale),
+                dpi: Some(72.0),
+                ..Default::default()
+            },
+        );
+    }
+
+    fn draw_cell_text(
+        &self,
+        layer: &PdfLayerReference,
+        font: &PdfFont,
+        column: &Column,
+        size: f32,
+        y: f32,
+        text: &str,
+    ) {
+        let x = match column.align {
+            Align::Left => column.x + column.text_offset,
+            Align::Right => column.x + column.width - CELL_PADDING - font.stack.text_width(text, size),
+        };
+        draw_text(layer, font, size, x, y, text);
+    }
+
+    fn draw_row_frame(&self, layer: &PdfLayerReference, y: f32, height: f32, dividers: &[f32], is_header: bool) {
+        let left = self.layout.table_left();
+        let right = self.layout.table_right();
+
+        match self.template.table.borders {
+            Borders::Grid => {
+                // Top and bottom lines
+                draw_line(layer, left, y, right, y);
+                draw_line(layer, left, y - height, right, y - height);
+
+                // Vertical lines
+                draw_line(layer, left, y, left, y - height);
+                for x in dividers {
+                    draw_line(layer, *x, y, *x, y - height);
+                }
+                draw_line(layer, right, y, right, y - height);
+            }
+            Borders::Rules => {
+                if is_header {
+                    draw_line(layer, left, y, right, y);
+                }
+                draw_line(layer, left, y - height, right, y - height);
+            }
+            Borders::None => {}
+        }
+    }
+
+    /// Draws `text` diagonally across the middle of the page at the template's
+    /// opacity, so whatever is underneath stays readable through it.
+    fn draw_watermark(&self, layer: &PdfLayerReference, text: &str, color: (f32, f32, f32)) {
+        let watermark = &self.template.watermark;
+        let page = &self.template.page;
+        let width = self.fonts.bold.stack.text_width(text, watermark.size);
+        let (r, g, b) = color;
+
+        layer.save_graphics_state();
+        // Defined on each page by `add_watermark_opacity` once the document is saved
+        layer.add_operation(Operation::new("gs", vec![Object::Name(WATERMARK_STATE.as_bytes().to_vec())]));
+        layer.set_ctm(CurTransMat::TranslateRotate(
+            Pt(page.width / 2.0),
+            Pt(page.height / 2.0),
+            watermark.angle,
+        ));
+        layer.set_fill_color(Color::Rgb(Rgb::new(r, g, b, None)));
+        // Centered on the origin; cap height is roughly 0.7 of the font size
+        draw_text(layer, &self.fonts.bold, watermark.size, -width / 2.0, -watermark.size * 0.35, text);
+        layer.restore_graphics_state();
+    }
+
+    /// Draws a rotated, boxed status stamp. Paid stamps also carry the paid
+    /// date and the payment reference when they're known.
+    fn draw_stamp(&self, layer: &PdfLayerReference, text: &str, color: (f32, f32, f32)) {
+        let stamp = &self.template.stamp;
+        let invoice = &self.data.invoice;
+
+        let mut details = Vec::new();
+        if invoice.status == "paid" {
+            if let Some(paid_date) = invoice.paid_date {
+                details.push(format!("Paid {}", format_date(paid_date)));
+            }
+            if let Some(reference) = invoice.payment_reference.as_deref().filter(|s| !s.trim().is_empty()) {
+                details.push(format!("Ref: {}", reference));
+            }
+        }
+
+        let title_width = self.fonts.bold.stack.text_width(text, stamp.size);
+        let detail_widths = details
+            .iter()
+            .map(|d| self.fonts.regular.stack.text_width(d, STAMP_DETAIL_SIZE));
+        let width = detail_widths.fold(title_width, f32::max) + STAMP_PADDING * 2.0;
+        let mut height = stamp.size * 0.7 + STAMP_PADDING * 2.0;
+        if !details.is_empty() {
+            height += 4.0 + details.len() as f32 * (STAMP_DETAIL_SIZE + 3.0);
+        }
+
+        let (r, g, b) = color;
+        let color = Color::Rgb(Rgb::new(r, g, b, None));
+
+        // Draw in coordinates relative to the stamp's top-left corner
+        layer.save_graphics_state();
+        layer.set_ctm(CurTransMat::TranslateRotate(
+            Pt(stamp.x),
+            Pt(self.layout.y_from_top(stamp.top)),
+            stamp.angle,
+        ));
+        layer.set_outline_color(color.clone());
+        layer.set_fill_color(color);
+        layer.set_outline_thickness(2.0);
+        layer.add_rect(
+            Rect::new(Mm::from(Pt(0.0)), Mm::from(Pt(-height)), Mm::from(Pt(width)), Mm::from(Pt(0.0)))
+                .with_mode(PaintMode::Stroke),
+        );
+
+        let mut y = -STAMP_PADDING - stamp.size * 0.7;
+        draw_text(layer, &self.fonts.bold, stamp.size, (width - title_width) / 2.0, y, text);
+        y -= 4.0;
+        for detail in &details {
+            y -= STAMP_DETAIL_SIZE + 3.0;
+            let detail_width = self.fonts.regular.stack.text_width(detail, STAMP_DETAIL_SIZE);
+            draw_text(layer, &self.fonts.regular, STAMP_DETAIL_SIZE, (width - detail_width) / 2.0, y, detail);
+        }
+        layer.restore_graphics_state();
+    }
+
+    fn money(&self, amount: f64) -> String {
+        format_currency(amount, &self.data.invoice.currency)
+    }
+
+    fn with_accent(&self, layer: &PdfLayerReference, accent: bool, draw: impl FnOnce()) {
+        if accent {
+            layer.set_fill_color(self.branding.accent_color());
+        }
+        draw();
+        if accent {
+            layer.set_fill_color(black());
+        }
+    }
+}
+
+// The page number printed at the bottom of every invoice page
+fn page_label(page: usize, page_count: usize) -> String {
+    format!("Page {} of {}", page, page_count)
+}
+
+/// Draws `text` starting at (x, y), switching to a fallback face for any
+/// characters the primary font can't represent.
+fn draw_text(layer: &PdfLayerReference, font: &PdfFont, size: f32, x: f32, y: f32, text: &str) {
+    let mut x = x;
+    for (face, run) in font.stack.runs(text) {
+        layer.use_text(run, size, Mm::from(Pt(x)), Mm::from(Pt(y)), &font.refs[face]);
+        x += font.stack.faces()[face].text_width(run, size);
+    }
+}
+
+/// Draws `qr` as filled squares with its top-left corner at (x, top), merging
+/// runs of dark modules in a row into a single rectangle.
+fn draw_qr_code(layer: &PdfLayerReference, qr: &QrMatrix, x: f32, top: f32, size: f32) {
+    let module = size / qr.width as f32;
+    layer.set_fill_color(black());
+
+    for row in 0..qr.width {
+        let y = top - (row + 1) as f32 * module;
+        let mut col = 0;
+        while col < qr.width {
+            if !qr.is_dark(col, row) {
+                col += 1;
+                continue;
+            }
+            let start = col;
+            while col < qr.width && qr.is_dark(col, row) {
+                col += 1;
+            }
+            layer.add_rect(
+                Rect::new(
+                    Mm::from(Pt(x + start as f32 * module)),
+                    Mm::from(Pt(y)),
+                    Mm::from(Pt(x + col as f32 * module)),
+                    Mm::from(Pt(y + module)),
+                )
+                .with_mode(PaintMode::Fill),
+            );
+        }
+    }
+}
+
+fn bank_detail_lines(details: &BankDetails) -> Vec<String> {
+    let field = |label: &str, value: Option<String>| {
+        value
+            .filter(|v| !v.trim().is_empty())
+            .map(|v| format!("{}: {}", label, v.trim()))
+    };
+    let join = |fields: [Option<String>; 2]| {
+        let parts: Vec<String> = fields.into_iter().flatten().collect();
+        if parts.is_empty() { None } else { Some(parts.join("   ")) }
+    };
+
+    // IBANs are stored compact; print them in groups of four
+    let iban = details.iban.as_ref().map(|iban| {
+        let chars: Vec<char> = iban.chars().filter(|c| !c.is_whitespace()).collect();
+        chars.chunks(4).map(|c| c.iter().collect::<String>()).collect::<Vec<_>>().join(" ")
+    });
+
+    [
+        field("Account holder", details.account_holder.clone()),
+        join([field("IBAN", iban), field("BIC", details.bic.clone())]),
+        join([
+            field("Routing number", details.routing_number.clone()),
+            field("Account number", details.account_number.clone()),
+        ]),
+    ]
+    .into_iter()
+    .flatten()
+    .collect()
+}
+
+fn draw_line(layer: &PdfLayerReference, x1: f32, y1: f32, x2: f32, y2: f32) {
+    layer.set_outline_thickness(1.0);
+    layer.add_line(Line {
+        points: vec![
+            (Point { x: Pt(x1), y: Pt(y1) }, false),
+            (Point { x: Pt(x2), y: Pt(y2) }, false),
+        ],
+        is_closed: false,
+    });
+}
+
+fn black() -> Color {
+    Color::Greyscale(Greyscale::new(0.0, None))
+}
+
+fn fill_placeholders(text: &str, data: &InvoiceData) -> String {
+    let invoice = &data.invoice;
+    text.replace("{invoice_number}", &invoice.invoice_number)
+        .replace("{issue_date}", &format_date(invoice.issue_date))
+        .replace("{due_date}", &format_date(invoice.due_date))
+        .replace("{artist_name}", &data.artist.name)
+        .replace("{project_name}", data.project.as_ref().map(|p| p.name.as_str()).unwrap_or(""))
+        .replace("{status}", &invoice.status)
+}
+
+pub fn format_date(date: NaiveDate) -> String {
+    date.format("%-d %B %Y").to_string()
+}
+
+fn due_text(issue_date: NaiveDate, due_date: NaiveDate) -> String {
+    if due_date > issue_date {
+        format!("Net {}", (due_date - issue_date).num_days())
+    } else {
+        "Upon Receipt".to_string()
+    }
+}
+
+pub fn format_currency(amount: f64, currency: &str) -> String {
+    let cents = (amount.abs() * 100.0).round() as u64;
+    let whole = (cents / 100).to_string();
+
+    // Format the whole part with commas
+    let mut grouped = String::new();
+    for (i, digit) in whole.chars().enumerate() {
+        if i > 0 && (whole.len() - i) % 3 == 0 {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+
+    let symbol = match currency {
+        "USD" | "" => "$".to_string(),
+        "EUR" => "€".to_string(),
+        "GBP" => "£".to_string(),
+        other => format!("{} ", other),
+    };
+    let sign = if amount < 0.0 { "-" } else { "" };
+    format!("{}{}{}.{:02}", sign, symbol, grouped, cents % 100)
+}
+
+pub fn round_cents(amount: f64) -> f64 {
+    (amount * 100.0).round() / 100.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(amount: f64) -> LineItem {
+        LineItem {
+            description: "Mix".to_string(),
+            artist: None,
+            song_project: None,
+            company: None,
+            delivered: false,
+            terms_agreed: false,
+            invoiced: false,
+            upstreamed: false,
+            upstream_amount: None,
+            amount,
+        }
+    }
+
+    fn rows(heights: &[f32]) -> Vec<ItemRow> {
+        heights.iter().map(|&height| ItemRow { cells: Vec::new(), height }).collect()
+    }
+
+    fn page(items: std::ops::Range<usize>, carried_in: Option<f64>, carried_out: Option<f64>) -> PageLayout {
+        PageLayout { items, carried_in, carried_out }
+    }
+
+    /// Room for item rows on the first page, below the table header
+    fn first_page_space(layout: &Layout) -> f32 {
+        layout.table_top(true) - layout.template.table.row_height - layout.continued_page_limit()
+    }
+
+    #[test]
+    fn watermark_opacity_is_set_on_every_page() {
+        let (doc, _, _) = PdfDocument::new("Test", Mm(210.0), Mm(297.0), "Layer 1");
+        doc.add_page(Mm(210.0), Mm(297.0), "Layer 1");
+        let pdf = add_watermark_opacity(&doc.save_to_bytes().unwrap(), 0.15).unwrap();
+
+        let doc = lopdf::Document::load_mem(&pdf).unwrap();
+        let pages = doc.get_pages();
+        assert_eq!(pages.len(), 2);
+        for page_id in pages.into_values() {
+            let resources = doc.get_dictionary(page_id).unwrap().get(b"Resources").unwrap().as_reference().unwrap();
+            let states = doc.get_dictionary(resources).unwrap().get(b"ExtGState").unwrap().as_dict().unwrap();
+            let state = doc.get_dictionary(states.get(WATERMARK_STATE.as_bytes()).unwrap().as_reference().unwrap());
+            assert_eq!(state.unwrap().get(b"ca").unwrap().as_float().unwrap(), 0.15);
+        }
+    }
+
+    #[test]
+    fn no_items_is_one_page() {
+        let template = Template::default();
+        let layout = Layout { template: &template };
+        assert_eq!(paginate(&layout, &[], &[], 0.0), [page(0..0, None, None)]);
+    }
+
+    #[test]
+    fn items_that_exactly_fill_a_page() {
+        let template = Template::default();
+        let layout = Layout { template: &template };
+        let row_height = template.table.row_height;
+        let full = first_page_space(&layout);
+
+        let items = [item(100.0), item(20.0)];
+        let pages = paginate(&layout, &items, &rows(&[full, row_height]), 0.0);
+        assert_eq!(pages, [page(0..1, None, Some(100.0)), page(1..2, Some(100.0), None)]);
+
+        // The row fits, but the payment footer doesn't, so the totals get a page of their own
+        let pages = paginate(&layout, &items[..1], &rows(&[full]), 0.0);
+        assert_eq!(pages, [page(0..1, None, Some(100.0)), page(1..1, Some(100.0), None)]);
+    }
+
+    #[test]
+    fn totals_that_do_not_fit_move_to_a_new_page() {
+        let template = Template::default();
+        let layout = Layout { template: &template };
+        let row_height = template.table.row_height;
+        let items = [item(100.0), item(50.0)];
+
+        let pages = paginate(&layout, &items, &rows(&[row_height, row_height]), 100.0);
+        assert_eq!(pages, [page(0..2, None, None)]);
+
+        let pages = paginate(&layout, &items, &rows(&[row_height, row_height]), first_page_space(&layout));
+        assert_eq!(pages, [page(0..2, None, Some(150.0)), page(2..2, Some(150.0), None)]);
+
+        // Totals taller than a page still end up somewhere
+        let pages = paginate(&layout, &items, &rows(&[row_height, row_height]), template.page.height * 2.0);
+        assert_eq!(pages.len(), 2);
+        assert_eq!(pages[1], page(2..2, Some(150.0), None));
+    }
+
+    #[test]
+    fn rows_taller_than_a_page_get_a_page_each() {
+        let template = Template::default();
+        let layout = Layout { template: &template };
+        let tall = template.page.height * 2.0;
+        let items = [item(1.0), item(2.0), item(4.0)];
+
+        let pages = paginate(&layout, &items, &rows(&[tall, tall, template.table.row_height]), 0.0);
+        assert_eq!(
+            pages,
+            [page(0..1, None, Some(1.0)), page(1..2, Some(1.0), Some(3.0)), page(2..3, Some(3.0), None)]
+        );
+    }
+
+    #[test]
+    fn page_numbers_count_every_page() {
+        let db = crate::database::Database::in_memory().unwrap();
+        let artist = db
+            .create_artist(crate::database::NewArtist { name: "Nova".to_string(), ..Default::default() })
+            .unwrap();
+        let invoice = db
+            .create_invoice(crate::database::NewInvoice {
+                artist_id: artist.id.clone(),
+                ..serde_json::from_str(r#"{"artist_id": "", "invoice_number": "INV-001", "amount": 600,
+                    "issue_date": "2024-03-01", "due_date": "2024-03-31"}"#).unwrap()
+            })
+            .unwrap();
+        let data = InvoiceData { invoice, artist, project: None, line_items: (0..60).map(|_| item(10.0)).collect() };
+        let options = RenderOptions {
+            font_dir: None,
+            branding: Branding::default(),
+            template: Template::default(),
+            facturx: false,
+        };
+        let path = std::env::temp_dir().join(format!("project-invoicer-pages-{}.pdf", uuid::Uuid::new_v4()));
+        generate_invoice_pdf(data, path.clone(), &options).unwrap();
+        let doc = lopdf::Document::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        // Text is written as glyph ids of the bundled font, so the labels are looked for the same way
+        let font = FontFace::bundled_regular();
+        let face = owned_ttf_parser::Face::parse(font.bytes(), 0).unwrap();
+        let shown = |text: &str| {
+            let glyphs: String = text.chars().map(|c| format!("{:04X}", face.glyph_index(c).unwrap().0)).collect();
+            format!("<{}> Tj", glyphs)
+        };
+        let labels = ["Page 1 of 4", "Page 2 of 4", "Page 3 of 4", "Page 4 of 4"];
+        let pages = doc.get_pages();
+        assert_eq!(pages.len(), labels.len());
+        for (page_id, label) in pages.into_values().zip(labels) {
+            let content = String::from_utf8_lossy(&doc.get_page_content(page_id).unwrap()).to_string();
+            assert!(content.contains(&shown(label)), "{} is missing", label);
+        }
+    }
+}
@@ -0,0 +1,476 @@
+// src-tauri/src/pdf_template.rs
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+// Built-in layouts, compiled in so they're always available
+const BUILTIN_TEMPLATES: [(&str, &str); 3] = [
+    ("default", include_str!("../templates/default.toml")),
+    ("minimal", include_str!("../templates/minimal.toml")),
+    ("detailed", include_str!("../templates/detailed.toml")),
+];
+
+pub const DEFAULT_TEMPLATE_NAME: &str = "default";
+
+/// A declarative invoice layout. All coordinates are in points; `top` values
+/// are measured down from the top edge of the page.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Template {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub page: PageSpec,
+    /// Blocks drawn on the first page above the item table
+    #[serde(default)]
+    pub header: Vec<Block>,
+    pub table: TableSpec,
+    #[serde(default)]
+    pub totals: TotalsSpec,
+    #[serde(default)]
+    pub footer: FooterSpec,
+    #[serde(default)]
+    pub watermark: WatermarkSpec,
+    #[serde(default)]
+    pub stamp: StampSpec,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PageSpec {
+    pub width: f32,
+    pub height: f32,
+    pub margin: f32,
+}
+
+impl Default for PageSpec {
+    fn default() -> Self {
+        // A4
+        PageSpec { width: 595.0, height: 842.0, margin: 50.0 }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BlockKind {
+    /// Branding logo in a square badge of `size` points
+    Logo,
+    /// Artist name / company in `title_size`, then address, email and phone
+    Artist,
+    /// Fixed `text`, e.g. "INVOICE"
+    Title,
+    InvoiceNumber,
+    IssueDate,
+    DueDate,
+    /// Client address; `label` is drawn as a heading above it
+    BillTo,
+    /// Project name, skipped when the invoice has no project
+    Project,
+    /// Free text with `{invoice_number}`, `{issue_date}`, `{due_date}`,
+    /// `{artist_name}`, `{project_name}` and `{status}` placeholders
+    Text,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Block {
+    pub kind: BlockKind,
+    pub x: f32,
+    pub top: f32,
+    /// Wrap width; defaults to the space up to the right margin
+    #[serde(default)]
+    pub width: Option<f32>,
+    #[serde(default = "default_block_size")]
+    pub size: f32,
+    #[serde(default)]
+    pub title_size: Option<f32>,
+    #[serde(default)]
+    pub bold: bool,
+    /// Draw in the branding accent color
+    #[serde(default)]
+    pub accent: bool,
+    /// Heading for multi-line blocks, prefix for single-line ones
+    #[serde(default)]
+    pub label: Option<String>,
+    #[serde(default)]
+    pub text: Option<String>,
+    /// Shown when the block has no content; otherwise the block is skipped
+    #[serde(default)]
+    pub placeholder: Option<String>,
+    #[serde(default)]
+    pub max_lines: Option<usize>,
+    #[serde(default = "default_line_height")]
+    pub line_height: f32,
+    /// Shift right to make room when a logo is drawn
+    #[serde(default)]
+    pub beside_logo: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Borders {
+    /// Boxed rows with column dividers
+    Grid,
+    /// Horizontal rules between rows only
+    Rules,
+    None,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Align {
+    Left,
+    Right,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CellContent {
+    Description,
+    /// Artist / Song/Project / Company, when `show_details` is on
+    Details,
+    /// Delivered / Terms Agreed / Invoiced / Upstreamed checkmarks, when `show_status` is on
+    Status,
+    Amount,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Column {
+    pub header: String,
+    /// Left edge of the cell
+    pub x: f32,
+    pub width: f32,
+    /// Distance from the cell's left edge to left-aligned text
+    #[serde(default)]
+    pub text_offset: f32,
+    #[serde(default = "default_align")]
+    pub align: Align,
+    pub content: Vec<CellContent>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TableSpec {
+    /// Top of the table on the first page
+    pub top: f32,
+    /// Top of the table on continuation pages
+    pub continuation_top: f32,
+    #[serde(default = "default_row_height")]
+    pub row_height: f32,
+    #[serde(default = "default_line_height")]
+    pub line_height: f32,
+    #[serde(default = "default_header_size")]
+    pub header_size: f32,
+    #[serde(default = "default_description_size")]
+    pub description_size: f32,
+    #[serde(default = "default_block_size")]
+    pub detail_size: f32,
+    #[serde(default = "default_borders")]
+    pub borders: Borders,
+    #[serde(default = "default_true")]
+    pub show_details: bool,
+    #[serde(default = "default_true")]
+    pub show_status: bool,
+    pub columns: Vec<Column>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TotalsSpec {
+    /// Space between the last item and the totals rows
+    #[serde(default = "default_totals_gap")]
+    pub gap: f32,
+    #[serde(default = "default_true")]
+    pub show_due: bool,
+    #[serde(default = "default_due_label")]
+    pub due_label: String,
+    #[serde(default = "default_total_label")]
+    pub total_label: String,
+    /// Labels for the net and tax rows shown when the invoice has a VAT rate
+    #[serde(default = "default_subtotal_label")]
+    pub subtotal_label: String,
+    #[serde(default = "default_tax_label")]
+    pub tax_label: String,
+    #[serde(default = "default_header_size")]
+    pub label_size: f32,
+    #[serde(default)]
+    pub show_notes: bool,
+    #[serde(default)]
+    pub notes_label: Option<String>,
+}
+
+impl Default for TotalsSpec {
+    fn default() -> Self {
+        TotalsSpec {
+            gap: default_totals_gap(),
+            show_due: true,
+            due_label: default_due_label(),
+            total_label: default_total_label(),
+            subtotal_label: default_subtotal_label(),
+            tax_label: default_tax_label(),
+            label_size: default_header_size(),
+            show_notes: false,
+            notes_label: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FooterSpec {
+    /// Print the artist's wire details on the last page
+    #[serde(default = "default_true")]
+    pub payment_details: bool,
+    #[serde(default = "default_payment_title")]
+    pub title: String,
+    /// Height of the rule above the payment details, measured from the bottom
+    /// of the page; nothing else is drawn below it on the last page
+    #[serde(default = "default_footer_top")]
+    pub top: f32,
+    #[serde(default = "default_footer_size")]
+    pub size: f32,
+    #[serde(default = "default_footer_lines")]
+    pub max_lines: usize,
+    #[serde(default = "default_true")]
+    pub page_numbers: bool,
+    /// Show the artist's payment QR code beside the payment details
+    #[serde(default = "default_true")]
+    pub qr_code: bool,
+    #[serde(default = "default_qr_size")]
+    pub qr_size: f32,
+}
+
+impl Default for FooterSpec {
+    fn default() -> Self {
+        FooterSpec {
+            payment_details: true,
+            title: default_payment_title(),
+            top: default_footer_top(),
+            size: default_footer_size(),
+            max_lines: default_footer_lines(),
+            page_numbers: true,
+            qr_code: true,
+            qr_size: default_qr_size(),
+        }
+    }
+}
+
+/// Large diagonal DRAFT / VOID text behind the content of every page.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatermarkSpec {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    #[serde(default = "default_watermark_size")]
+    pub size: f32,
+    /// Opacity of the text, 0.0 (invisible) to 1.0 (solid)
+    #[serde(default = "default_watermark_opacity")]
+    pub opacity: f32,
+    /// Counter-clockwise rotation in degrees
+    #[serde(default = "default_watermark_angle")]
+    pub angle: f32,
+}
+
+impl Default for WatermarkSpec {
+    fn default() -> Self {
+        WatermarkSpec {
+            enabled: true,
+            size: default_watermark_size(),
+            opacity: default_watermark_opacity(),
+            angle: default_watermark_angle(),
+        }
+    }
+}
+
+/// Boxed PAID / OVERDUE stamp on the first page, with the paid date and
+/// payment reference underneath.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StampSpec {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Left edge and top of the stamp before rotation
+    #[serde(default = "default_stamp_x")]
+    pub x: f32,
+    #[serde(default = "default_stamp_top")]
+    pub top: f32,
+    #[serde(default = "default_stamp_size")]
+    pub size: f32,
+    /// Counter-clockwise rotation in degrees
+    #[serde(default = "default_stamp_angle")]
+    pub angle: f32,
+    #[serde(default = "default_true")]
+    pub show_overdue: bool,
+}
+
+impl Default for StampSpec {
+    fn default() -> Self {
+        StampSpec {
+            enabled: true,
+            x: default_stamp_x(),
+            top: default_stamp_top(),
+            size: default_stamp_size(),
+            angle: default_stamp_angle(),
+            show_overdue: true,
+        }
+    }
+}
+
+fn default_true() -> bool { true }
+fn default_block_size() -> f32 { 10.0 }
+fn default_line_height() -> f32 { 15.0 }
+fn default_row_height() -> f32 { 30.0 }
+fn default_header_size() -> f32 { 12.0 }
+fn default_description_size() -> f32 { 11.0 }
+fn default_borders() -> Borders { Borders::Grid }
+fn default_align() -> Align { Align::Left }
+fn default_totals_gap() -> f32 { 20.0 }
+fn default_due_label() -> String { "Due".to_string() }
+fn default_total_label() -> String { "Total".to_string() }
+fn default_subtotal_label() -> String { "Subtotal".to_string() }
+fn default_tax_label() -> String { "VAT".to_string() }
+fn default_payment_title() -> String { "PAYMENT DETAILS".to_string() }
+fn default_footer_top() -> f32 { 120.0 }
+fn default_footer_size() -> f32 { 8.0 }
+fn default_footer_lines() -> usize { 4 }
+fn default_qr_size() -> f32 { 70.0 }
+fn default_watermark_size() -> f32 { 110.0 }
+fn default_watermark_opacity() -> f32 { 0.15 }
+fn default_watermark_angle() -> f32 { 35.0 }
+fn default_stamp_x() -> f32 { 415.0 }
+fn default_stamp_top() -> f32 { 150.0 }
+fn default_stamp_size() -> f32 { 26.0 }
+fn default_stamp_angle() -> f32 { 12.0 }
+
+impl Default for Template {
+    fn default() -> Self {
+        builtin_template(DEFAULT_TEMPLATE_NAME).expect("Built-in default template is invalid")
+    }
+}
+
+impl Template {
+    /// Parses a template from TOML, or JSON when `is_json` is set.
+    pub fn parse(source: &str, is_json: bool) -> Result<Self, String> {
+        let template: Template = if is_json {
+            serde_json::from_str(source).map_err(|e| e.to_string())?
+        } else {
+            toml::from_str(source).map_err(|e| e.to_string())?
+        };
+        template.validate()?;
+        Ok(template)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.table.columns.is_empty() {
+            return Err(format!("Template '{}' has no table columns", self.name));
+        }
+        if self.table.top >= self.page.height || self.table.continuation_top >= self.page.height {
+            return Err(format!("Template '{}' places the table below the page", self.name));
+        }
+        if !(0.0..=1.0).contains(&self.watermark.opacity) {
+            return Err(format!("Template '{}' has a watermark opacity outside 0.0 to 1.0", self.name));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TemplateInfo {
+    pub name: String,
+    pub description: String,
+    pub builtin: bool,
+}
+
+fn builtin_template(name: &str) -> Option<Template> {
+    BUILTIN_TEMPLATES
+        .iter()
+        .find(|(builtin, _)| *builtin == name)
+        .map(|(_, source)| Template::parse(source, false).expect("Built-in template is invalid"))
+}
+
+/// Template names become file names in the user's template folder, so only
+/// letters, digits, `-` and `_` are allowed; anything else could leave it.
+pub fn validate_name(name: &str) -> Result<(), String> {
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        return Err(format!("Invalid template name '{}'", name));
+    }
+    Ok(())
+}
+
+/// Loads a template by name. A `<name>.toml` or `<name>.json` file in
+/// `user_dir` takes precedence over the built-in template of the same name.
+pub fn load_template(name: &str, user_dir: Option<&Path>) -> Result<Template, String> {
+    validate_name(name)?;
+    if let Some(dir) = user_dir {
+        for (extension, is_json) in [("toml", false), ("json", true)] {
+            let path = dir.join(format!("{}.{}", name, extension));
+            if path.is_file() {
+                let source = std::fs::read_to_string(&path)
+                    .map_err(|e| format!("Failed to read template {}: {}", path.display(), e))?;
+                return Template::parse(&source, is_json)
+                    .map_err(|e| format!("Invalid template {}: {}", path.display(), e));
+            }
+        }
+    }
+
+    builtin_template(name).ok_or_else(|| format!("Template '{}' not found", name))
+}
+
+/// Lists the built-in templates plus any valid templates in `user_dir`.
+pub fn list_templates(user_dir: Option<&Path>) -> Vec<TemplateInfo> {
+    let mut templates: Vec<TemplateInfo> = BUILTIN_TEMPLATES
+        .iter()
+        .filter_map(|(name, _)| builtin_template(name))
+        .map(|t| TemplateInfo { name: t.name, description: t.description, builtin: true })
+        .collect();
+
+    let entries = match user_dir.map(std::fs::read_dir) {
+        Some(Ok(entries)) => entries,
+        _ => return templates,
+    };
+
+    for path in entries.filter_map(|e| e.ok()).map(|e| e.path()) {
+        let is_json = match path.extension().and_then(|e| e.to_str()) {
+            Some("toml") => false,
+            Some("json") => true,
+            _ => continue,
+        };
+        // Templates are selected by file name, so that's the name we report
+        let Some(name) = path.file_stem().and_then(|s| s.to_str()).filter(|s| validate_name(s).is_ok()) else {
+            continue;
+        };
+        let name = name.to_string();
+        let Some(template) = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|source| Template::parse(&source, is_json).ok())
+        else {
+            continue;
+        };
+
+        // User templates shadow built-ins with the same name
+        templates.retain(|t| t.name != name);
+        templates.push(TemplateInfo {
+            name,
+            description: template.description,
+            builtin: false,
+        });
+    }
+
+    templates.sort_by(|a, b| a.name.cmp(&b.name));
+    templates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_templates_are_valid() {
+        for (name, source) in BUILTIN_TEMPLATES {
+            let template = Template::parse(source, false).unwrap_or_else(|e| panic!("{}: {}", name, e));
+            assert_eq!(template.name, name);
+            assert!(load_template(name, None).is_ok());
+        }
+    }
+
+    #[test]
+    fn template_names_cannot_leave_the_template_folder() {
+        let dir = std::env::temp_dir();
+        for name in ["../default", "..", "a/b", "a\\b", "/etc/passwd", "", "in voice"] {
+            assert_eq!(load_template(name, Some(&dir)).unwrap_err(), format!("Invalid template name '{}'", name));
+        }
+        assert!(validate_name("my-template_2").is_ok());
+    }
+}
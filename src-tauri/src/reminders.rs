@@ -0,0 +1,388 @@
+// src-tauri/src/reminders.rs
+use chrono::{Local, NaiveDate};
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::database::{Artist, Database};
+use crate::email::{self, render, variables, Email, PdfRenderer};
+use crate::settings;
+use crate::validation::{Validator, MAX_NAME, MAX_NOTES};
+
+// How often the scheduler looks for reminders to send
+const CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+// Failed sends of one step are retried on later checks up to this many times
+const MAX_ATTEMPTS: usize = 3;
+// Earliest and latest reminder, in days relative to the due date
+const OFFSET_RANGE: std::ops::RangeInclusive<i64> = -60..=365;
+
+/// One reminder, sent `offset_days` after the due date (before it when negative).
+/// Subject and body may use the `{placeholders}` in `email::VARIABLES`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReminderStep {
+    pub offset_days: i64,
+    pub subject: String,
+    pub body: String,
+}
+
+/// An artist's reminder settings, stored as JSON. Artists without one get no
+/// reminders; `default()` is the suggested escalation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReminderSchedule {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    // Also send the invoice PDF with each reminder
+    #[serde(default = "default_true")]
+    pub attach_pdf: bool,
+    pub steps: Vec<ReminderStep>,
+}
+
+fn default_true() -> bool { true }
+
+impl Default for ReminderSchedule {
+    fn default() -> Self {
+        let step = |offset_days, subject: &str, opening: &str| ReminderStep {
+            offset_days,
+            subject: subject.to_string(),
+            body: format!(
+                "Hello,\n\n{}\n\nInvoice: {{invoice_number}}\nAmount: {{amount}}\nDue date: {{due_date}}\n\n\
+                 Kind regards,\n{{artist_name}}\n",
+                opening
+            ),
+        };
+        ReminderSchedule {
+            enabled: true,
+            attach_pdf: true,
+            steps: vec![
+                step(
+                    -3,
+                    "Invoice {invoice_number} is due on {due_date}",
+                    "A friendly reminder that the invoice below is due in {days_until_due} days.",
+                ),
+                step(0, "Invoice {invoice_number} is due today", "The invoice below is due today."),
+                step(
+                    7,
+                    "Reminder: invoice {invoice_number} is overdue",
+                    "We haven't received payment for the invoice below, which is {days_overdue} days overdue.",
+                ),
+                step(
+                    14,
+                    "Second reminder: invoice {invoice_number} is overdue",
+                    "The invoice below is now {days_overdue} days overdue. Please arrange payment soon.",
+                ),
+                step(
+                    30,
+                    "Final notice: invoice {invoice_number}",
+                    "The invoice below is {days_overdue} days overdue. Please pay within 7 days or contact us.",
+                ),
+            ],
+        }
+    }
+}
+
+impl ReminderSchedule {
+    pub fn validate(&self) -> Vec<String> {
+        let mut v = Validator::new();
+        let mut offsets: Vec<i64> = self.steps.iter().map(|s| s.offset_days).collect();
+        offsets.sort();
+        if offsets.windows(2).any(|w| w[0] == w[1]) {
+            v.error("reminder steps must have different offsets".to_string());
+        }
+        for step in &self.steps {
+            let field = format!("reminder at {} days", step.offset_days);
+            if !OFFSET_RANGE.contains(&step.offset_days) {
+                v.error(format!(
+                    "{} is outside {} to {} days from the due date",
+                    field,
+                    OFFSET_RANGE.start(),
+                    OFFSET_RANGE.end()
+                ));
+            }
+            v.required(&format!("{} subject", field), &step.subject, MAX_NAME);
+            v.max_length(&format!("{} body", field), Some(&step.body), MAX_NOTES);
+            for name in email::unknown_placeholders(&step.subject).chain(email::unknown_placeholders(&step.body)) {
+                v.error(format!("{} uses unknown placeholder {{{}}}", field, name));
+            }
+        }
+        v.into_errors()
+    }
+
+    /// The step to send on `as_of`: the latest one whose date has come. Earlier
+    /// steps that were missed, e.g. while the app was closed, are skipped.
+    pub fn due_step(&self, due_date: NaiveDate, as_of: NaiveDate) -> Option<&ReminderStep> {
+        let days = (as_of - due_date).num_days();
+        self.steps.iter().filter(|s| s.offset_days <= days).max_by_key(|s| s.offset_days)
+    }
+}
+
+impl ToSql for ReminderSchedule {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        let json = serde_json::to_string(self).map_err(|e| rusqlite::Error::ToSqlConversionFailure(e.into()))?;
+        Ok(ToSqlOutput::from(json))
+    }
+}
+
+impl FromSql for ReminderSchedule {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        serde_json::from_str(value.as_str()?).map_err(|e| FromSqlError::Other(e.into()))
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ReminderRun {
+    pub sent: usize,
+    pub failed: usize,
+    // Invoices with a reminder due but no recipient address
+    pub no_recipient: Vec<String>,
+}
+
+/// Sends the reminder due on `as_of` for every unpaid invoice whose artist
+/// has a schedule, and logs each attempt. Invoices stop getting reminders as
+/// soon as a payment is recorded, or they're archived or trashed, since only
+/// open invoices are considered.
+pub fn send_due_reminders(db: &Database, as_of: NaiveDate, render_pdf: &PdfRenderer) -> Result<ReminderRun, String> {
+    let smtp = settings::load().smtp.ok_or("Email isn't set up; add the SMTP server in settings first")?;
+    send_reminders(db, as_of, render_pdf, |email| email::send(&smtp, &email::build_message(&smtp, email)?))
+}
+
+// `send_due_reminders` with the delivery left to `send`
+fn send_reminders(
+    db: &Database,
+    as_of: NaiveDate,
+    render_pdf: &PdfRenderer,
+    send: impl Fn(&Email) -> Result<(), String>,
+) -> Result<ReminderRun, String> {
+    let artists: HashMap<String, Artist> = db
+        .get_all_artists()
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .map(|a| (a.id.clone(), a))
+        .collect();
+
+    let mut run = ReminderRun::default();
+    for invoice in db.get_open_invoices().map_err(|e| e.to_string())? {
+        let Some(artist) = artists.get(&invoice.artist_id) else { continue };
+        let Some(schedule) = artist.reminder_schedule.as_ref().filter(|s| s.enabled) else { continue };
+        let Some(step) = schedule.due_step(invoice.due_date, as_of) else { continue };
+        let attempts = db.get_invoice_reminders(&invoice.id).map_err(|e| e.to_string())?;
+        let attempts: Vec<_> = attempts.iter().filter(|r| r.offset_days == step.offset_days).collect();
+        if attempts.iter().any(|r| r.error.is_none()) || attempts.len() >= MAX_ATTEMPTS {
+            continue;
+        }
+        let Some(recipient) = invoice.bill_to_email.clone().filter(|e| !e.trim().is_empty()) else {
+            run.no_recipient.push(invoice.invoice_number.clone());
+            continue;
+        };
+
+        let variables = variables(&invoice, artist, as_of);
+        let subject = render(&step.subject, &variables);
+        let result = (|| {
+            let attachments = if schedule.attach_pdf { vec![render_pdf(db, &invoice)?] } else { Vec::new() };
+            let email = Email {
+                to: vec![recipient.clone()],
+                subject: subject.clone(),
+                body: render(&step.body, &variables),
+                attachments,
+                ..Default::default()
+            };
+            send(&email)
+        })();
+        match &result {
+            Ok(()) => run.sent += 1,
+            Err(_) => run.failed += 1,
+        }
+        db.record_invoice_reminder(&invoice.id, step.offset_days, &recipient, &subject, result.err())
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(run)
+}
+
+/// Starts the background thread that sends due reminders. Nothing is sent
+/// until SMTP is set up and an artist has a reminder schedule.
+pub fn start_scheduler(db: Database, render_pdf: Box<PdfRenderer>) {
+    std::thread::spawn(move || loop {
+        if db.error().is_none() && settings::load().smtp.is_some() {
+            match send_due_reminders(&db, Local::now().date_naive(), &*render_pdf) {
+                Ok(run) if run.failed > 0 => eprintln!("{} payment reminders failed to send", run.failed),
+                Ok(_) => {}
+                Err(e) => eprintln!("Sending payment reminders failed: {}", e),
+            }
+        }
+        std::thread::sleep(CHECK_INTERVAL);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{Invoice, InvoicePatch, NewArtist, NewInvoice};
+    use crate::email::EmailAttachment;
+    use std::cell::RefCell;
+
+    fn date(value: &str) -> NaiveDate {
+        value.parse().unwrap()
+    }
+
+    fn render_pdf(_: &Database, invoice: &Invoice) -> Result<EmailAttachment, String> {
+        Ok(EmailAttachment::pdf(format!("{}.pdf", invoice.invoice_number), b"%PDF".to_vec()))
+    }
+
+    // An artist with `schedule` and one invoice due 2024-03-31
+    fn invoice(db: &Database, schedule: Option<ReminderSchedule>, recipient: Option<&str>) -> Invoice {
+        let artist = db
+            .create_artist(NewArtist { name: "Reminder Artist".to_string(), ..Default::default() })
+            .unwrap();
+        db.set_artist_reminder_schedule(artist.id.clone(), schedule).unwrap();
+        db.create_invoice(NewInvoice {
+            artist_id: artist.id,
+            project_id: None,
+            invoice_number: format!("INV-{}", db.get_all_invoices().unwrap().len() + 1),
+            amount: 250.0,
+            status: "pending".to_string(),
+            issue_date: date("2024-03-01"),
+            due_date: Some(date("2024-03-31")),
+            payment_terms: None,
+            currency: Some("usd".to_string()),
+            client_id: None,
+            bill_to: Some("Acme Ltd".to_string()),
+            bill_to_email: recipient.map(str::to_string),
+            items: r#"[{"description":"Mixing","amount":250.0}]"#.to_string(),
+            notes: None,
+        })
+        .unwrap()
+    }
+
+    // Runs the reminders for `as_of` and returns what was sent; `fail` makes every send fail
+    fn run(db: &Database, as_of: &str, fail: bool) -> (ReminderRun, Vec<Email>) {
+        let sent = RefCell::new(Vec::new());
+        let run = send_reminders(db, date(as_of), &render_pdf, |email| {
+            sent.borrow_mut().push(email.clone());
+            if fail { Err("connection refused".to_string()) } else { Ok(()) }
+        })
+        .unwrap();
+        (run, sent.into_inner())
+    }
+
+    #[test]
+    fn a_sent_reminder_is_not_sent_again() {
+        let db = Database::in_memory().unwrap();
+        let invoice = invoice(&db, Some(ReminderSchedule::default()), Some("client@example.com"));
+
+        let (result, sent) = run(&db, "2024-04-08", false);
+        assert_eq!((result.sent, result.failed), (1, 0));
+        assert_eq!(sent[0].to, vec!["client@example.com".to_string()]);
+        assert_eq!(sent[0].subject, "Reminder: invoice INV-1 is overdue");
+        assert_eq!(sent[0].attachments[0].file_name, "INV-1.pdf");
+
+        // The same step on a later check, then the next step once its day comes
+        assert!(run(&db, "2024-04-09", false).1.is_empty());
+        let (_, sent) = run(&db, "2024-04-14", false);
+        assert_eq!(sent[0].subject, "Second reminder: invoice INV-1 is overdue");
+        let log = db.get_invoice_reminders(&invoice.id).unwrap();
+        assert_eq!(log.iter().map(|r| r.offset_days).collect::<Vec<_>>(), [7, 14]);
+    }
+
+    #[test]
+    fn failed_reminders_are_retried_up_to_max_attempts() {
+        let db = Database::in_memory().unwrap();
+        let invoice = invoice(&db, Some(ReminderSchedule::default()), Some("client@example.com"));
+
+        for _ in 0..MAX_ATTEMPTS {
+            let (result, _) = run(&db, "2024-04-08", true);
+            assert_eq!((result.sent, result.failed), (0, 1));
+        }
+        assert!(run(&db, "2024-04-08", false).1.is_empty());
+        let log = db.get_invoice_reminders(&invoice.id).unwrap();
+        assert_eq!(log.len(), MAX_ATTEMPTS);
+        assert!(log.iter().all(|r| r.error.as_deref() == Some("connection refused")));
+
+        // The next step starts with fresh attempts
+        assert_eq!(run(&db, "2024-04-14", false).0.sent, 1);
+    }
+
+    #[test]
+    fn invoices_without_a_recipient_are_reported() {
+        let db = Database::in_memory().unwrap();
+        let invoice = invoice(&db, Some(ReminderSchedule::default()), Some("  "));
+
+        let (result, sent) = run(&db, "2024-04-08", false);
+        assert_eq!(result.no_recipient, ["INV-1"]);
+        assert!(sent.is_empty());
+        assert!(db.get_invoice_reminders(&invoice.id).unwrap().is_empty());
+    }
+
+    #[test]
+    fn each_artist_uses_their_own_schedule() {
+        let db = Database::in_memory().unwrap();
+        invoice(&db, None, Some("none@example.com"));
+        let disabled = ReminderSchedule { enabled: false, ..Default::default() };
+        invoice(&db, Some(disabled), Some("disabled@example.com"));
+        let custom = ReminderSchedule {
+            enabled: true,
+            attach_pdf: false,
+            steps: vec![ReminderStep {
+                offset_days: 5,
+                subject: "{invoice_number} is {days_overdue} days late".to_string(),
+                body: "Please pay {amount}.".to_string(),
+            }],
+        };
+        invoice(&db, Some(custom), Some("custom@example.com"));
+
+        let (result, sent) = run(&db, "2024-04-08", false);
+        assert_eq!(result.sent, 1);
+        assert_eq!(sent[0].to, vec!["custom@example.com".to_string()]);
+        assert_eq!(sent[0].subject, "INV-3 is 8 days late");
+        assert!(sent[0].attachments.is_empty());
+    }
+
+    #[test]
+    fn recording_a_payment_stops_reminders() {
+        let db = Database::in_memory().unwrap();
+        let invoice = invoice(&db, Some(ReminderSchedule::default()), Some("client@example.com"));
+        assert_eq!(run(&db, "2024-04-08", false).0.sent, 1);
+
+        db.record_invoice_payment(invoice.id.clone(), Some(date("2024-04-10")), None).unwrap();
+        assert!(run(&db, "2024-04-14", false).1.is_empty());
+        assert_eq!(db.get_invoice_reminders(&invoice.id).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn default_schedule_escalates() {
+        let schedule = ReminderSchedule::default();
+        assert!(schedule.validate().is_empty(), "{:?}", schedule.validate());
+        let due = date("2024-03-31");
+        let offset = |as_of: &str| schedule.due_step(due, date(as_of)).map(|s| s.offset_days);
+        assert_eq!(offset("2024-03-27"), None);
+        assert_eq!(offset("2024-03-28"), Some(-3));
+        assert_eq!(offset("2024-03-31"), Some(0));
+        assert_eq!(offset("2024-04-10"), Some(7));
+        assert_eq!(offset("2024-04-14"), Some(14));
+        assert_eq!(offset("2024-06-01"), Some(30));
+    }
+
+    #[test]
+    fn invalid_schedule() {
+        let mut schedule = ReminderSchedule::default();
+        schedule.steps[1].offset_days = -3;
+        schedule.steps[2].subject = "Invoice {number}".to_string();
+        schedule.steps[3].offset_days = 400;
+        let errors = schedule.validate();
+        assert_eq!(errors.len(), 3, "{:?}", errors);
+        assert!(errors[1].contains("unknown placeholder {number}"), "{:?}", errors);
+    }
+
+    #[test]
+    fn archived_and_trashed_invoices_get_no_reminders() {
+        let db = Database::in_memory().unwrap();
+        for status in ["archived", "trashed"] {
+            let invoice = invoice(&db, Some(ReminderSchedule::default()), Some("client@example.com"));
+            let patch = InvoicePatch { status: Some(status.to_string()), ..Default::default() };
+            db.update_invoice(&invoice.id, patch).unwrap();
+        }
+
+        let (result, sent) = run(&db, "2024-04-08", false);
+        assert_eq!((result.sent, result.failed), (0, 0));
+        assert!(sent.is_empty());
+    }
+}
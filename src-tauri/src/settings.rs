@@ -0,0 +1,51 @@
+// src-tauri/src/settings.rs
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use crate::database;
+use crate::email::{EmailTemplate, SmtpSettings};
+use crate::snapshots::SnapshotSettings;
+use crate::workspaces::Workspace;
+
+const SETTINGS_FILE_NAME: &str = "settings.toml";
+
+/// App-wide preferences that live next to the database rather than in it,
+/// so they survive restoring or switching databases.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AppSettings {
+    #[serde(default)]
+    pub snapshots: SnapshotSettings,
+    #[serde(default)]
+    pub workspaces: Vec<Workspace>,
+    // The workspace opened at startup; the default database when unset
+    #[serde(default)]
+    pub last_workspace_id: Option<String>,
+    // Outgoing mail for invoices and payment reminders; unset until configured
+    #[serde(default)]
+    pub smtp: Option<SmtpSettings>,
+    // Subject and body for invoices sent with send_invoice
+    #[serde(default)]
+    pub invoice_email: EmailTemplate,
+}
+
+fn settings_path() -> PathBuf {
+    database::get_data_dir().join(SETTINGS_FILE_NAME)
+}
+
+/// The saved settings, or the defaults when there are none yet. A file that
+/// can't be parsed is reported and ignored rather than blocking startup.
+pub fn load() -> AppSettings {
+    let path = settings_path();
+    match std::fs::read_to_string(&path) {
+        Ok(text) => toml::from_str(&text).unwrap_or_else(|e| {
+            eprintln!("Ignoring invalid settings in {}: {}", path.display(), e);
+            AppSettings::default()
+        }),
+        Err(_) => AppSettings::default(),
+    }
+}
+
+pub fn save(settings: &AppSettings) -> Result<(), String> {
+    let text = toml::to_string_pretty(settings).map_err(|e| e.to_string())?;
+    std::fs::write(settings_path(), text).map_err(|e| format!("Failed to save settings: {}", e))
+}
@@ -0,0 +1,286 @@
+// src-tauri/src/snapshots.rs
+use chrono::{DateTime, Datelike, NaiveDateTime, Utc};
+use rusqlite::backup::Progress;
+use rusqlite::{Connection, DatabaseName};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::database::{self, Database};
+use crate::settings;
+
+const FILE_PREFIX: &str = "snapshot-";
+const FILE_EXTENSION: &str = "db";
+const TIMESTAMP_FORMAT: &str = "%Y%m%d-%H%M%S";
+// How often the scheduler checks whether a snapshot is due
+const CHECK_INTERVAL: Duration = Duration::from_secs(15 * 60);
+
+// Why a snapshot was taken; part of the file name
+pub const SCHEDULED: &str = "scheduled";
+pub const MANUAL: &str = "manual";
+pub const PRE_MIGRATION: &str = "pre-migration";
+pub const PRE_RESTORE: &str = "pre-restore";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotSettings {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    // Defaults to a "snapshots" folder next to the database. Each database
+    // file gets its own subfolder.
+    #[serde(default)]
+    pub folder: Option<String>,
+    #[serde(default = "default_interval_hours")]
+    pub interval_hours: u32,
+    // Newest snapshot kept for each of the last N days, weeks and months
+    #[serde(default = "default_keep_daily")]
+    pub keep_daily: usize,
+    #[serde(default = "default_keep_weekly")]
+    pub keep_weekly: usize,
+    #[serde(default = "default_keep_monthly")]
+    pub keep_monthly: usize,
+}
+
+fn default_true() -> bool { true }
+fn default_interval_hours() -> u32 { 24 }
+fn default_keep_daily() -> usize { 7 }
+fn default_keep_weekly() -> usize { 4 }
+fn default_keep_monthly() -> usize { 12 }
+
+impl Default for SnapshotSettings {
+    fn default() -> Self {
+        SnapshotSettings {
+            enabled: true,
+            folder: None,
+            interval_hours: default_interval_hours(),
+            keep_daily: default_keep_daily(),
+            keep_weekly: default_keep_weekly(),
+            keep_monthly: default_keep_monthly(),
+        }
+    }
+}
+
+impl SnapshotSettings {
+    pub fn folder(&self, db_path: &Path) -> PathBuf {
+        let base = match self.folder.as_deref().map(str::trim).filter(|f| !f.is_empty()) {
+            Some(folder) => PathBuf::from(folder),
+            None => db_path.parent().map_or_else(database::get_data_dir, Path::to_path_buf).join("snapshots"),
+        };
+        let database = db_path.file_stem().map_or("database".into(), |stem| stem.to_string_lossy());
+        base.join(database.as_ref())
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SnapshotInfo {
+    pub file_name: String,
+    pub path: String,
+    pub created_at: String,
+    pub reason: String,
+    pub size: u64,
+}
+
+/// Copies the live database into the snapshot folder with SQLite's online
+/// backup API, so the copy is consistent even while the app is writing.
+pub fn take_snapshot(db: &Database, reason: &str) -> Result<SnapshotInfo, String> {
+    let conn = db.connection().map_err(|e| e.to_string())?;
+    snapshot_connection(&conn, reason)
+}
+
+/// Snapshots an already open database, e.g. from `database::init` before it
+/// migrates the schema.
+pub fn snapshot_connection(conn: &Connection, reason: &str) -> Result<SnapshotInfo, String> {
+    let settings = settings::load().snapshots;
+    let db_path = conn
+        .path()
+        .filter(|path| !path.is_empty())
+        .ok_or("In-memory databases can't be snapshotted")?;
+    let folder = settings.folder(Path::new(db_path));
+    std::fs::create_dir_all(&folder).map_err(|e| format!("Failed to create {}: {}", folder.display(), e))?;
+
+    let now = Utc::now();
+    let file_name = format!("{}{}-{}.{}", FILE_PREFIX, now.format(TIMESTAMP_FORMAT), reason, FILE_EXTENSION);
+    let path = folder.join(&file_name);
+    // Written under a temporary name so a half-written copy is never listed
+    let partial = folder.join(format!("{}.partial", file_name));
+    let written = conn
+        .backup(DatabaseName::Main, &partial, None)
+        .map_err(|e| e.to_string())
+        .and_then(|_| std::fs::rename(&partial, &path).map_err(|e| e.to_string()));
+    if let Err(e) = written {
+        let _ = std::fs::remove_file(&partial);
+        return Err(format!("Failed to write snapshot {}: {}", path.display(), e));
+    }
+
+    rotate(&folder, &settings)?;
+    parse_snapshot(&path).ok_or_else(|| format!("Snapshot {} was removed by rotation", file_name))
+}
+
+/// Every snapshot in the configured folder, newest first.
+pub fn list_snapshots(db: &Database) -> Result<Vec<SnapshotInfo>, String> {
+    match db.path() {
+        Some(path) => list_in(&settings::load().snapshots.folder(&path)),
+        None => Ok(Vec::new()),
+    }
+}
+
+fn list_in(folder: &Path) -> Result<Vec<SnapshotInfo>, String> {
+    let entries = match std::fs::read_dir(folder) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("Failed to read {}: {}", folder.display(), e)),
+    };
+    let mut snapshots: Vec<SnapshotInfo> = entries.flatten().filter_map(|e| parse_snapshot(&e.path())).collect();
+    snapshots.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+    Ok(snapshots)
+}
+
+fn parse_snapshot(path: &Path) -> Option<SnapshotInfo> {
+    if path.extension()?.to_str()? != FILE_EXTENSION {
+        return None;
+    }
+    let file_name = path.file_name()?.to_str()?.to_string();
+    let stem = path.file_stem()?.to_str()?.strip_prefix(FILE_PREFIX)?;
+    // "20240131-235900" is 15 characters, followed by "-reason"
+    let timestamp = NaiveDateTime::parse_from_str(stem.get(..15)?, TIMESTAMP_FORMAT).ok()?;
+    let reason = stem.get(16..).unwrap_or(MANUAL).to_string();
+    Some(SnapshotInfo {
+        path: path.to_string_lossy().to_string(),
+        created_at: timestamp.and_utc().to_rfc3339(),
+        reason,
+        size: std::fs::metadata(path).map(|m| m.len()).unwrap_or(0),
+        file_name,
+    })
+}
+
+/// Keeps the newest snapshot of each of the last `keep_daily` days,
+/// `keep_weekly` weeks and `keep_monthly` months and deletes the rest.
+/// Pre-migration and pre-restore snapshots are never rotated out.
+fn rotate(folder: &Path, settings: &SnapshotSettings) -> Result<(), String> {
+    let snapshots = list_in(folder)?;
+    let mut days = HashSet::new();
+    let mut weeks = HashSet::new();
+    let mut months = HashSet::new();
+
+    for (index, snapshot) in snapshots.iter().enumerate() {
+        if snapshot.reason == PRE_MIGRATION || snapshot.reason == PRE_RESTORE {
+            continue;
+        }
+        let Ok(created) = DateTime::parse_from_rfc3339(&snapshot.created_at) else { continue };
+        let date = created.date_naive();
+        let week = date.iso_week();
+
+        let mut keep = index == 0;
+        if days.len() < settings.keep_daily && days.insert(date) {
+            keep = true;
+        }
+        if weeks.len() < settings.keep_weekly && weeks.insert((week.year(), week.week())) {
+            keep = true;
+        }
+        if months.len() < settings.keep_monthly && months.insert((date.year(), date.month())) {
+            keep = true;
+        }
+        if !keep {
+            std::fs::remove_file(&snapshot.path)
+                .map_err(|e| format!("Failed to remove old snapshot {}: {}", snapshot.file_name, e))?;
+        }
+    }
+    Ok(())
+}
+
+/// Replaces the live database with a snapshot. The current state is saved as
+/// a pre-restore snapshot first, and the restored copy is migrated to the
+/// current schema.
+pub fn restore_snapshot(db: &Database, file_name: &str) -> Result<(), String> {
+    let snapshot = list_snapshots(db)?
+        .into_iter()
+        .find(|s| s.file_name == file_name)
+        .ok_or_else(|| format!("Snapshot '{}' not found", file_name))?;
+
+    take_snapshot(db, PRE_RESTORE)?;
+    let mut conn = db.connection().map_err(|e| e.to_string())?;
+    conn.restore(DatabaseName::Main, &snapshot.path, None::<fn(Progress)>)
+        .map_err(|e| format!("Failed to restore {}: {}", file_name, e))?;
+    drop(conn);
+    db.migrate().map_err(|e| format!("Restored {}, but migrating it failed: {}", file_name, e))
+}
+
+/// Starts the background thread that takes a snapshot whenever the newest
+/// scheduled one is older than the configured interval.
+pub fn start_scheduler(db: Database) {
+    std::thread::spawn(move || loop {
+        if let Err(e) = take_scheduled_snapshot_if_due(&db) {
+            eprintln!("Scheduled snapshot failed: {}", e);
+        }
+        std::thread::sleep(CHECK_INTERVAL);
+    });
+}
+
+fn take_scheduled_snapshot_if_due(db: &Database) -> Result<(), String> {
+    // Settings are re-read every time so changes apply without a restart
+    let settings = settings::load().snapshots;
+    // Nothing to snapshot while the database is unavailable or in memory
+    let Some(db_path) = db.path().filter(|_| settings.enabled && db.error().is_none()) else {
+        return Ok(());
+    };
+    let latest = list_in(&settings.folder(&db_path))?
+        .into_iter()
+        .find(|s| s.reason == SCHEDULED)
+        .and_then(|s| DateTime::parse_from_rfc3339(&s.created_at).ok());
+    let interval = chrono::Duration::hours(settings.interval_hours.max(1) as i64);
+    if latest.is_none_or(|latest| Utc::now() - latest.with_timezone(&Utc) >= interval) {
+        take_snapshot(db, SCHEDULED)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rotation_keeps_the_newest_snapshot_per_day_week_and_month() {
+        let folder = std::env::temp_dir().join(format!("project-invoicer-snapshots-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&folder).unwrap();
+        let files = [
+            "snapshot-20240315-120000-scheduled.db",
+            "snapshot-20240315-080000-scheduled.db",
+            "snapshot-20240314-120000-manual.db",
+            "snapshot-20240313-120000-scheduled.db",
+            "snapshot-20240305-120000-scheduled.db",
+            "snapshot-20240220-120000-scheduled.db",
+            "snapshot-20240115-120000-scheduled.db",
+            "snapshot-20240101-120000-pre-migration.db",
+            "notes.txt",
+        ];
+        for file in files {
+            std::fs::write(folder.join(file), b"").unwrap();
+        }
+
+        let settings = SnapshotSettings { keep_daily: 2, keep_weekly: 2, keep_monthly: 2, ..Default::default() };
+        rotate(&folder, &settings).unwrap();
+
+        let mut left: Vec<String> = std::fs::read_dir(&folder)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        left.sort();
+        assert_eq!(
+            left,
+            [
+                "notes.txt",
+                // Kept because pre-migration snapshots never rotate out
+                "snapshot-20240101-120000-pre-migration.db",
+                // Second month
+                "snapshot-20240220-120000-scheduled.db",
+                // Second week
+                "snapshot-20240305-120000-scheduled.db",
+                // Second day
+                "snapshot-20240314-120000-manual.db",
+                // Newest, first day, week and month
+                "snapshot-20240315-120000-scheduled.db",
+            ]
+        );
+        std::fs::remove_dir_all(folder).unwrap();
+    }
+}
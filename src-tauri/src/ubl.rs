@@ -0,0 +1,560 @@
+// src-tauri/src/ubl.rs
+use chrono::NaiveDate;
+
+use crate::database::Invoice;
+use crate::einvoice::{
+    self, amount, bill_to_lines, exemption_reason, is_credit_note, non_empty, seller_address_lines, seller_name,
+    tax_category, XmlWriter,
+};
+use crate::pdf_generator::{InvoiceData, Totals};
+
+const CUSTOMIZATION_ID: &str = "urn:cen.eu:en16931:2017#compliant#urn:fdc:peppol.eu:2017:poacc:billing:3.0";
+const PROFILE_ID: &str = "urn:fdc:peppol.eu:2017:poacc:billing:01:1.0";
+
+/// Checks the EN 16931 fields plus the Peppol BIS 3.0 rules layered on top,
+/// and reports every missing field at once.
+pub fn validate(data: &InvoiceData) -> Result<(), String> {
+    let invoice = &data.invoice;
+    let artist = &data.artist;
+    let mut errors = einvoice::missing_fields(data);
+
+    // Both parties need an electronic address; the seller can fall back to their email
+    match non_empty(&artist.peppol_id) {
+        Some(id) if parse_endpoint(id).is_none() => {
+            errors.push(format!("seller Peppol ID as scheme:identifier (got '{}')", id))
+        }
+        None if non_empty(&artist.email).is_none() => errors.push("seller Peppol ID or email".to_string()),
+        _ => {}
+    }
+    match non_empty(&invoice.buyer_peppol_id) {
+        Some(id) if parse_endpoint(id).is_none() => {
+            errors.push(format!("buyer Peppol ID as scheme:identifier (got '{}')", id))
+        }
+        None => errors.push("buyer Peppol ID".to_string()),
+        _ => {}
+    }
+
+    // VAT numbers start with the issuing country's code
+    for (party, tax_id) in [("seller", &artist.tax_id), ("buyer", &invoice.buyer_tax_id)] {
+        if let Some(tax_id) = non_empty(tax_id) {
+            if !tax_id.chars().take(2).all(|c| c.is_ascii_uppercase()) {
+                errors.push(format!("{} VAT number with a country prefix (got '{}')", party, tax_id));
+            }
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("UBL invoice is missing: {}", errors.join("; ")))
+    }
+}
+
+/// Builds a Peppol BIS Billing 3.0 UBL document: an Invoice, or a CreditNote
+/// referencing `credited_invoice` for credit notes. Call `validate` first.
+pub fn build_ubl_xml(data: &InvoiceData, credited_invoice: Option<&Invoice>) -> String {
+    let invoice = &data.invoice;
+    let artist = &data.artist;
+    let totals = Totals::for_invoice(invoice);
+    let currency = &invoice.currency;
+    let category = tax_category(invoice);
+    let rate = invoice.tax_rate.unwrap_or(0.0);
+    let credit_note = is_credit_note(invoice);
+    let (root, line_tag, quantity_tag) = if credit_note {
+        ("CreditNote", "cac:CreditNoteLine", "cbc:CreditedQuantity")
+    } else {
+        ("Invoice", "cac:InvoiceLine", "cbc:InvoicedQuantity")
+    };
+    let mut xml = XmlWriter::default();
+
+    xml.line("<?xml version=\"1.0\" encoding=\"UTF-8\"?>");
+    xml.open(&format!(
+        "{root} xmlns=\"urn:oasis:names:specification:ubl:schema:xsd:{root}-2\" \
+         xmlns:cac=\"urn:oasis:names:specification:ubl:schema:xsd:CommonAggregateComponents-2\" \
+         xmlns:cbc=\"urn:oasis:names:specification:ubl:schema:xsd:CommonBasicComponents-2\""
+    ));
+    xml.element("cbc:CustomizationID", CUSTOMIZATION_ID);
+    xml.element("cbc:ProfileID", PROFILE_ID);
+    xml.element("cbc:ID", &invoice.invoice_number);
+    xml.date("cbc:IssueDate", invoice.issue_date);
+    // UBL 2.1 credit notes have no due date; the payment terms carry it
+    if credit_note {
+        xml.element("cbc:CreditNoteTypeCode", "381");
+    } else {
+        xml.date("cbc:DueDate", invoice.due_date);
+        xml.element("cbc:InvoiceTypeCode", "380");
+    }
+    if let Some(notes) = non_empty(&invoice.notes) {
+        xml.element("cbc:Note", notes);
+    }
+    xml.element("cbc:DocumentCurrencyCode", currency);
+    // Peppol needs a buyer or order reference; the invoice number is the one the buyer sees
+    xml.element("cbc:BuyerReference", &invoice.invoice_number);
+
+    if let Some(credited) = credited_invoice {
+        xml.open("cac:BillingReference");
+        xml.open("cac:InvoiceDocumentReference");
+        xml.element("cbc:ID", &credited.invoice_number);
+        xml.date("cbc:IssueDate", credited.issue_date);
+        xml.close("cac:InvoiceDocumentReference");
+        xml.close("cac:BillingReference");
+    }
+
+    xml.open("cac:AccountingSupplierParty");
+    xml.open("cac:Party");
+    match non_empty(&artist.peppol_id).and_then(parse_endpoint) {
+        Some((scheme, id)) => xml.endpoint(scheme, id),
+        None => xml.endpoint("EM", non_empty(&artist.email).unwrap_or("")),
+    }
+    xml.party_name(seller_name(artist));
+    xml.address(&seller_address_lines(artist), artist.country_code.as_deref().unwrap_or(""));
+    if let Some(tax_id) = non_empty(&artist.tax_id) {
+        xml.party_tax_scheme(tax_id);
+    }
+    xml.legal_entity(seller_name(artist));
+    if let Some(email) = non_empty(&artist.email) {
+        xml.open("cac:Contact");
+        xml.element("cbc:ElectronicMail", email);
+        xml.close("cac:Contact");
+    }
+    xml.close("cac:Party");
+    xml.close("cac:AccountingSupplierParty");
+
+    let bill_to = bill_to_lines(invoice);
+    let buyer_name = bill_to.first().copied().unwrap_or("");
+    xml.open("cac:AccountingCustomerParty");
+    xml.open("cac:Party");
+    if let Some((scheme, id)) = non_empty(&invoice.buyer_peppol_id).and_then(parse_endpoint) {
+        xml.endpoint(scheme, id);
+    }
+    xml.party_name(buyer_name);
+    xml.address(bill_to.get(1..).unwrap_or(&[]), invoice.buyer_country_code.as_deref().unwrap_or(""));
+    if let Some(buyer_tax_id) = non_empty(&invoice.buyer_tax_id) {
+        xml.party_tax_scheme(buyer_tax_id);
+    }
+    xml.legal_entity(buyer_name);
+    xml.close("cac:Party");
+    xml.close("cac:AccountingCustomerParty");
+
+    // SEPA credit transfer when there's an IBAN, plain credit transfer otherwise
+    let bank = &artist.bank_details;
+    let account = match (non_empty(&bank.iban), non_empty(&bank.account_number)) {
+        (Some(iban), _) => Some(("58", iban.replace(' ', ""))),
+        (None, Some(account_number)) => Some(("30", account_number.to_string())),
+        (None, None) => None,
+    };
+    if let Some((code, account_id)) = account {
+        xml.open("cac:PaymentMeans");
+        xml.element("cbc:PaymentMeansCode", code);
+        xml.element("cbc:PaymentID", &invoice.invoice_number);
+        xml.open("cac:PayeeFinancialAccount");
+        xml.element("cbc:ID", &account_id);
+        if let Some(holder) = non_empty(&bank.account_holder) {
+            xml.element("cbc:Name", holder);
+        }
+        if let Some(bic) = non_empty(&bank.bic).filter(|_| code == "58") {
+            xml.open("cac:FinancialInstitutionBranch");
+            xml.element("cbc:ID", bic);
+            xml.close("cac:FinancialInstitutionBranch");
+        }
+        xml.close("cac:PayeeFinancialAccount");
+        xml.close("cac:PaymentMeans");
+    }
+
+    xml.open("cac:PaymentTerms");
+    xml.element("cbc:Note", &payment_terms_note(invoice));
+    xml.close("cac:PaymentTerms");
+
+    let tax = totals.tax.unwrap_or(0.0);
+    xml.open("cac:TaxTotal");
+    xml.money("cbc:TaxAmount", currency, tax);
+    xml.open("cac:TaxSubtotal");
+    xml.money("cbc:TaxableAmount", currency, totals.net);
+    xml.money("cbc:TaxAmount", currency, tax);
+    xml.tax_category("cac:TaxCategory", &category, rate, exemption_reason(&category));
+    xml.close("cac:TaxSubtotal");
+    xml.close("cac:TaxTotal");
+
+    let paid = if invoice.status == "paid" { totals.gross } else { 0.0 };
+    xml.open("cac:LegalMonetaryTotal");
+    xml.money("cbc:LineExtensionAmount", currency, totals.net);
+    xml.money("cbc:TaxExclusiveAmount", currency, totals.net);
+    xml.money("cbc:TaxInclusiveAmount", currency, totals.gross);
+    if paid != 0.0 {
+        xml.money("cbc:PrepaidAmount", currency, paid);
+    }
+    xml.money("cbc:PayableAmount", currency, totals.gross - paid);
+    xml.close("cac:LegalMonetaryTotal");
+
+    // Every line is a single unit; negative lines are credited as -1 units
+    // since Peppol doesn't allow negative prices
+    for (index, item) in data.line_items.iter().enumerate() {
+        let quantity = if item.amount < 0.0 { "-1" } else { "1" };
+        xml.open(line_tag);
+        xml.element("cbc:ID", &(index + 1).to_string());
+        xml.element_with(quantity_tag, "unitCode=\"C62\"", quantity);
+        xml.money("cbc:LineExtensionAmount", currency, item.amount);
+        xml.open("cac:Item");
+        let details = item.details();
+        if !details.is_empty() {
+            xml.element("cbc:Description", &details.join("; "));
+        }
+        xml.element("cbc:Name", item.description.trim());
+        xml.tax_category("cac:ClassifiedTaxCategory", &category, rate, None);
+        xml.close("cac:Item");
+        xml.open("cac:Price");
+        xml.money("cbc:PriceAmount", currency, item.amount.abs());
+        xml.close("cac:Price");
+        xml.close(line_tag);
+    }
+
+    xml.close(root);
+    xml.finish()
+}
+
+fn payment_terms_note(invoice: &Invoice) -> String {
+    match &invoice.payment_terms {
+        Some(terms) => terms.description(invoice.issue_date),
+        None => format!("Payment due by {}", invoice.due_date.format("%Y-%m-%d")),
+    }
+}
+
+/// Splits a Peppol participant ID such as "0088:5790000435975" into its
+/// scheme and identifier.
+fn parse_endpoint(id: &str) -> Option<(&str, &str)> {
+    let (scheme, identifier) = id.split_once(':')?;
+    let scheme_ok = !scheme.is_empty() && scheme.chars().all(|c| c.is_ascii_alphanumeric());
+    (scheme_ok && !identifier.trim().is_empty()).then(|| (scheme, identifier.trim()))
+}
+
+/// UBL-specific elements
+trait UblElements {
+    fn date(&mut self, tag: &str, date: NaiveDate);
+    fn money(&mut self, tag: &str, currency: &str, value: f64);
+    fn endpoint(&mut self, scheme: &str, id: &str);
+    fn party_name(&mut self, name: &str);
+    fn address(&mut self, lines: &[&str], country_code: &str);
+    fn party_tax_scheme(&mut self, tax_id: &str);
+    fn legal_entity(&mut self, name: &str);
+    fn tax_category(&mut self, tag: &str, category: &str, rate: f64, exemption: Option<&str>);
+    fn vat_scheme(&mut self);
+}
+
+impl UblElements for XmlWriter {
+    fn date(&mut self, tag: &str, date: NaiveDate) {
+        self.element(tag, &date.format("%Y-%m-%d").to_string());
+    }
+
+    fn money(&mut self, tag: &str, currency: &str, value: f64) {
+        self.element_with(tag, &format!("currencyID=\"{}\"", einvoice::escape(currency)), &amount(value));
+    }
+
+    fn endpoint(&mut self, scheme: &str, id: &str) {
+        self.element_with("cbc:EndpointID", &format!("schemeID=\"{}\"", einvoice::escape(scheme)), id);
+    }
+
+    fn party_name(&mut self, name: &str) {
+        self.open("cac:PartyName");
+        self.element("cbc:Name", name);
+        self.close("cac:PartyName");
+    }
+
+    /// The first two address lines are the street; anything after goes into
+    /// a single free-form address line.
+    fn address(&mut self, lines: &[&str], country_code: &str) {
+        self.open("cac:PostalAddress");
+        for (tag, line) in ["cbc:StreetName", "cbc:AdditionalStreetName"].iter().zip(lines) {
+            self.element(tag, line);
+        }
+        if lines.len() > 2 {
+            self.open("cac:AddressLine");
+            self.element("cbc:Line", &lines[2..].join(", "));
+            self.close("cac:AddressLine");
+        }
+        self.open("cac:Country");
+        self.element("cbc:IdentificationCode", country_code);
+        self.close("cac:Country");
+        self.close("cac:PostalAddress");
+    }
+
+    fn party_tax_scheme(&mut self, tax_id: &str) {
+        self.open("cac:PartyTaxScheme");
+        self.element("cbc:CompanyID", tax_id);
+        self.vat_scheme();
+        self.close("cac:PartyTaxScheme");
+    }
+
+    fn legal_entity(&mut self, name: &str) {
+        self.open("cac:PartyLegalEntity");
+        self.element("cbc:RegistrationName", name);
+        self.close("cac:PartyLegalEntity");
+    }
+
+    /// Category O ("not subject to VAT") is the only one without a rate.
+    fn tax_category(&mut self, tag: &str, category: &str, rate: f64, exemption: Option<&str>) {
+        self.open(tag);
+        self.element("cbc:ID", category);
+        if category != "O" {
+            self.element("cbc:Percent", &rate.to_string());
+        }
+        if let Some(reason) = exemption {
+            self.element("cbc:TaxExemptionReason", reason);
+        }
+        self.vat_scheme();
+        self.close(tag);
+    }
+
+    fn vat_scheme(&mut self) {
+        self.open("cac:TaxScheme");
+        self.element("cbc:ID", "VAT");
+        self.close("cac:TaxScheme");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{Artist, BankDetails};
+    use crate::pdf_generator::LineItem;
+    use roxmltree::{Document, Node};
+
+    const INVOICE_SAMPLE: &str = include_str!("../tests/fixtures/ubl/invoice.xml");
+    const CREDIT_NOTE_SAMPLE: &str = include_str!("../tests/fixtures/ubl/credit-note.xml");
+
+    fn child<'a, 'input>(node: Node<'a, 'input>, path: &[&str]) -> Option<Node<'a, 'input>> {
+        path.iter().try_fold(node, |node, name| {
+            node.children().find(|c| c.is_element() && c.tag_name().name() == *name)
+        })
+    }
+
+    fn text(node: Node, path: &[&str]) -> Option<String> {
+        child(node, path).and_then(|n| n.text()).map(str::to_string)
+    }
+
+    fn number(node: Node, path: &[&str]) -> f64 {
+        text(node, path).and_then(|t| t.parse().ok()).unwrap_or(0.0)
+    }
+
+    /// Address lines as stored in the app: street lines, then the free-form line.
+    fn address(party: Node) -> Vec<String> {
+        ["StreetName", "AdditionalStreetName"]
+            .iter()
+            .filter_map(|tag| text(party, &["PostalAddress", tag]))
+            .chain(text(party, &["PostalAddress", "AddressLine", "Line"]))
+            .collect()
+    }
+
+    fn endpoint(party: Node) -> Option<String> {
+        let node = child(party, &["EndpointID"])?;
+        Some(format!("{}:{}", node.attribute("schemeID")?, node.text()?))
+    }
+
+    fn line_item(line: Node) -> LineItem {
+        let name = text(line, &["Item", "Name"]).unwrap_or_default();
+        let mut item: LineItem = serde_json::from_value(serde_json::json!({ "description": name })).unwrap();
+        item.amount = number(line, &["LineExtensionAmount"]);
+        for detail in text(line, &["Item", "Description"]).unwrap_or_default().split("; ") {
+            match detail.split_once(": ") {
+                Some(("Artist", value)) => item.artist = Some(value.to_string()),
+                Some(("Song/Project", value)) => item.song_project = Some(value.to_string()),
+                Some(("Company", value)) => item.company = Some(value.to_string()),
+                _ => {}
+            }
+        }
+        item
+    }
+
+    fn blank_invoice(number: String, issue_date: NaiveDate, due_date: NaiveDate) -> Invoice {
+        Invoice {
+            id: number.clone(),
+            artist_id: "artist".to_string(),
+            project_id: None,
+            invoice_number: number,
+            amount: 0.0,
+            status: "pending".to_string(),
+            issue_date,
+            due_date,
+            payment_terms: None,
+            paid_date: None,
+            payment_reference: None,
+            sent_at: None,
+            currency: "USD".to_string(),
+            tax_rate: None,
+            tax_category: None,
+            buyer_tax_id: None,
+            buyer_country_code: None,
+            buyer_peppol_id: None,
+            document_type: "invoice".to_string(),
+            credited_invoice_id: None,
+            client_id: None,
+            bill_to: None,
+            bill_to_email: None,
+            items: "[]".to_string(),
+            notes: None,
+            pdf_template: None,
+            created_at: Default::default(),
+            updated_at: Default::default(),
+        }
+    }
+
+    /// Reads a UBL document back into the app's model.
+    fn parse(xml: &str) -> (InvoiceData, Option<Invoice>) {
+        let document = Document::parse(xml).unwrap();
+        let root = document.root_element();
+        let seller = child(root, &["AccountingSupplierParty", "Party"]).unwrap();
+        let buyer = child(root, &["AccountingCustomerParty", "Party"]).unwrap();
+        let payment = child(root, &["PaymentMeans"]).unwrap();
+        let tax_category = child(root, &["TaxTotal", "TaxSubtotal", "TaxCategory"]).unwrap();
+        let totals = child(root, &["LegalMonetaryTotal"]).unwrap();
+
+        let seller_endpoint = endpoint(seller).unwrap();
+        let account = text(payment, &["PayeeFinancialAccount", "ID"]);
+        let sepa = text(payment, &["PaymentMeansCode"]).as_deref() == Some("58");
+        let artist = Artist {
+            id: "artist".to_string(),
+            name: text(seller, &["PartyLegalEntity", "RegistrationName"]).unwrap(),
+            company_name: None,
+            email: text(seller, &["Contact", "ElectronicMail"]),
+            phone: None,
+            address: Some(address(seller).join("\n")),
+            wire_details: None,
+            notes: None,
+            tax_id: text(seller, &["PartyTaxScheme", "CompanyID"]),
+            country_code: text(seller, &["PostalAddress", "Country", "IdentificationCode"]),
+            peppol_id: Some(seller_endpoint).filter(|id| !id.starts_with("EM:")),
+            pdf_template: None,
+            payment_terms: None,
+            reminder_schedule: None,
+            bank_details: BankDetails {
+                account_holder: text(payment, &["PayeeFinancialAccount", "Name"]),
+                iban: account.clone().filter(|_| sepa),
+                bic: text(payment, &["PayeeFinancialAccount", "FinancialInstitutionBranch", "ID"]),
+                account_number: account.filter(|_| !sepa),
+                ..Default::default()
+            },
+            created_at: Default::default(),
+            updated_at: Default::default(),
+        };
+
+        let due_date = text(root, &["PaymentTerms", "Note"]).unwrap().replace("Payment due by ", "").parse().unwrap();
+        let issue_date = text(root, &["IssueDate"]).unwrap().parse().unwrap();
+        let mut invoice = blank_invoice(text(root, &["ID"]).unwrap(), issue_date, due_date);
+        if root.tag_name().name() == "CreditNote" {
+            invoice.document_type = "credit_note".to_string();
+        }
+        invoice.amount = number(totals, &["LineExtensionAmount"]);
+        if child(totals, &["PrepaidAmount"]).is_some() {
+            invoice.status = "paid".to_string();
+        }
+        invoice.currency = text(root, &["DocumentCurrencyCode"]).unwrap();
+        invoice.tax_rate = Some(number(tax_category, &["Percent"]));
+        invoice.tax_category = text(tax_category, &["ID"]);
+        invoice.buyer_tax_id = text(buyer, &["PartyTaxScheme", "CompanyID"]);
+        invoice.buyer_country_code = text(buyer, &["PostalAddress", "Country", "IdentificationCode"]);
+        invoice.buyer_peppol_id = endpoint(buyer);
+        let buyer_name = text(buyer, &["PartyLegalEntity", "RegistrationName"]).unwrap();
+        invoice.bill_to = Some(std::iter::once(buyer_name).chain(address(buyer)).collect::<Vec<_>>().join("\n"));
+        invoice.notes = text(root, &["Note"]);
+
+        let credited = child(root, &["BillingReference", "InvoiceDocumentReference"]).map(|reference| {
+            let number = text(reference, &["ID"]).unwrap();
+            let issue_date = text(reference, &["IssueDate"]).unwrap().parse().unwrap();
+            invoice.credited_invoice_id = Some(number.clone());
+            blank_invoice(number, issue_date, issue_date)
+        });
+
+        let line_items = root
+            .children()
+            .filter(|c| matches!(c.tag_name().name(), "InvoiceLine" | "CreditNoteLine"))
+            .map(line_item)
+            .collect();
+
+        (InvoiceData { invoice, artist, project: None, line_items }, credited)
+    }
+
+    #[test]
+    fn invoice_sample_round_trips() {
+        let (data, credited) = parse(INVOICE_SAMPLE);
+        assert!(credited.is_none());
+        assert_eq!(data.line_items.len(), 3);
+        assert_eq!(data.invoice.tax_category.as_deref(), Some("S"));
+
+        validate(&data).unwrap();
+        assert_eq!(build_ubl_xml(&data, None), INVOICE_SAMPLE);
+    }
+
+    #[test]
+    fn credit_note_sample_round_trips() {
+        let (data, credited) = parse(CREDIT_NOTE_SAMPLE);
+        assert!(is_credit_note(&data.invoice));
+        assert_eq!(credited.as_ref().map(|i| i.invoice_number.as_str()), Some("INV-2024-017"));
+
+        validate(&data).unwrap();
+        assert_eq!(build_ubl_xml(&data, credited.as_ref()), CREDIT_NOTE_SAMPLE);
+    }
+
+    #[test]
+    fn credit_notes_use_the_credit_note_document() {
+        let (mut data, credited) = parse(INVOICE_SAMPLE);
+        data.invoice.document_type = "credit_note".to_string();
+        let xml = build_ubl_xml(&data, credited.as_ref());
+        let document = Document::parse(&xml).unwrap();
+        let root = document.root_element();
+
+        assert_eq!(root.tag_name().name(), "CreditNote");
+        assert_eq!(root.tag_name().namespace(), Some("urn:oasis:names:specification:ubl:schema:xsd:CreditNote-2"));
+        assert_eq!(text(root, &["CreditNoteTypeCode"]).as_deref(), Some("381"));
+        assert!(child(root, &["DueDate"]).is_none());
+        assert_eq!(root.children().filter(|c| c.tag_name().name() == "CreditNoteLine").count(), 3);
+        assert_eq!(text(root, &["CreditNoteLine", "CreditedQuantity"]).as_deref(), Some("1"));
+    }
+
+    #[test]
+    fn totals_add_up() {
+        for sample in [INVOICE_SAMPLE, CREDIT_NOTE_SAMPLE] {
+            let document = Document::parse(sample).unwrap();
+            let root = document.root_element();
+            let totals = child(root, &["LegalMonetaryTotal"]).unwrap();
+            let lines: f64 = root
+                .children()
+                .filter(|c| matches!(c.tag_name().name(), "InvoiceLine" | "CreditNoteLine"))
+                .map(|line| number(line, &["LineExtensionAmount"]))
+                .sum();
+            let tax = number(root, &["TaxTotal", "TaxAmount"]);
+            let net = number(totals, &["TaxExclusiveAmount"]);
+
+            assert!((lines - number(totals, &["LineExtensionAmount"])).abs() < 0.005);
+            assert!((net + tax - number(totals, &["TaxInclusiveAmount"])).abs() < 0.005);
+            assert!(
+                (number(totals, &["TaxInclusiveAmount"]) - number(totals, &["PrepaidAmount"])
+                    - number(totals, &["PayableAmount"]))
+                .abs()
+                    < 0.005
+            );
+        }
+    }
+
+    #[test]
+    fn validate_reports_every_missing_peppol_field() {
+        let (mut data, _) = parse(INVOICE_SAMPLE);
+        data.artist.peppol_id = None;
+        data.artist.email = None;
+        data.invoice.buyer_peppol_id = Some("not-an-id".to_string());
+        data.invoice.buyer_tax_id = Some("123456789".to_string());
+
+        let error = validate(&data).unwrap_err();
+        assert!(error.contains("seller Peppol ID or email"), "{}", error);
+        assert!(error.contains("buyer Peppol ID as scheme:identifier"), "{}", error);
+        assert!(error.contains("buyer VAT number with a country prefix"), "{}", error);
+    }
+
+    #[test]
+    fn seller_without_peppol_id_uses_email_endpoint() {
+        let (mut data, _) = parse(INVOICE_SAMPLE);
+        data.artist.peppol_id = None;
+        validate(&data).unwrap();
+
+        let xml = build_ubl_xml(&data, None);
+        assert!(xml.contains("<cbc:EndpointID schemeID=\"EM\">billing@okafor.example</cbc:EndpointID>"));
+    }
+}
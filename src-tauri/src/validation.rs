@@ -0,0 +1,209 @@
+// src-tauri/src/validation.rs
+use chrono::NaiveDate;
+
+// Length limits for free-text fields, in characters
+pub const MAX_NAME: usize = 200;
+pub const MAX_SHORT: usize = 64;
+pub const MAX_TEXT: usize = 2_000;
+pub const MAX_NOTES: usize = 10_000;
+
+/// Collects every problem with a record so the caller can report them all at
+/// once instead of one per save attempt. Optional fields are only checked
+/// when they hold something other than whitespace.
+#[derive(Debug, Default)]
+pub struct Validator {
+    errors: Vec<String>,
+}
+
+impl Validator {
+    pub fn new() -> Validator {
+        Validator::default()
+    }
+
+    pub fn into_errors(self) -> Vec<String> {
+        self.errors
+    }
+
+    pub fn error(&mut self, message: String) {
+        self.errors.push(message);
+    }
+
+    pub fn required(&mut self, field: &str, value: &str, max: usize) {
+        if value.trim().is_empty() {
+            self.error(format!("{} is required", field));
+        } else {
+            self.max_length(field, Some(value), max);
+        }
+    }
+
+    pub fn max_length(&mut self, field: &str, value: Option<&str>, max: usize) {
+        if value.is_some_and(|v| v.chars().count() > max) {
+            self.error(format!("{} is longer than {} characters", field, max));
+        }
+    }
+
+    pub fn email(&mut self, field: &str, value: Option<&str>) {
+        if let Some(email) = present(value) {
+            if !is_email(email) {
+                self.error(format!("{} '{}' is not a valid email address", field, email));
+            }
+        }
+    }
+
+    pub fn phone(&mut self, field: &str, value: Option<&str>) {
+        if let Some(phone) = present(value) {
+            if normalize_phone(phone).is_none() {
+                self.error(format!(
+                    "{} '{}' is not an international number with country code, e.g. +14155550123",
+                    field, phone
+                ));
+            }
+        }
+    }
+
+    pub fn not_before(
+        &mut self,
+        field: &str,
+        date: Option<NaiveDate>,
+        earlier_field: &str,
+        earlier: Option<NaiveDate>,
+    ) {
+        if let (Some(date), Some(earlier)) = (date, earlier) {
+            if date < earlier {
+                self.error(format!("{} {} is before {} {}", field, date, earlier_field, earlier));
+            }
+        }
+    }
+
+    pub fn non_negative(&mut self, field: &str, value: f64) {
+        if !value.is_finite() {
+            self.error(format!("{} must be a number", field));
+        } else if value < 0.0 {
+            self.error(format!("{} {} must be zero or more", field, value));
+        }
+    }
+
+    pub fn iban(&mut self, field: &str, value: Option<&str>) {
+        if let Some(iban) = present(value) {
+            if !is_iban(iban) {
+                self.error(format!("{} '{}' is not a valid IBAN", field, iban));
+            }
+        }
+    }
+
+    pub fn bic(&mut self, field: &str, value: Option<&str>) {
+        if let Some(bic) = present(value) {
+            if !is_bic(bic) {
+                self.error(format!("{} '{}' is not a valid BIC", field, bic));
+            }
+        }
+    }
+}
+
+fn present(value: Option<&str>) -> Option<&str> {
+    value.map(str::trim).filter(|v| !v.is_empty())
+}
+
+pub fn is_email(value: &str) -> bool {
+    let Some((user, domain)) = value.rsplit_once('@') else { return false };
+    let labels: Vec<&str> = domain.split('.').collect();
+    value.len() <= 254
+        && !user.is_empty()
+        && !value.chars().any(|c| c.is_whitespace() || c.is_control())
+        && !user.contains('@')
+        && labels.len() >= 2
+        && labels.iter().all(|l| !l.is_empty() && !l.starts_with('-') && !l.ends_with('-'))
+        && labels.last().is_some_and(|tld| tld.len() >= 2 && tld.chars().all(char::is_alphabetic))
+}
+
+/// Normalises a phone number to E.164 ("+" and up to 15 digits). Spaces,
+/// dashes, dots and brackets are dropped and a "00" prefix becomes "+";
+/// numbers without a country code are rejected since the country can't be
+/// guessed.
+pub fn normalize_phone(value: &str) -> Option<String> {
+    let compact: String = value.chars().filter(|c| !matches!(c, ' ' | '-' | '.' | '(' | ')')).collect();
+    let digits = compact.strip_prefix('+').or_else(|| compact.strip_prefix("00"))?;
+    let valid = (7..=15).contains(&digits.len())
+        && digits.chars().all(|c| c.is_ascii_digit())
+        && !digits.starts_with('0');
+    valid.then(|| format!("+{}", digits))
+}
+
+/// ISO 13616 check: country code, two check digits and up to 30 letters or
+/// digits, with the whole number leaving 1 mod 97.
+pub fn is_iban(value: &str) -> bool {
+    let iban: String = value.chars().filter(|c| !c.is_whitespace()).collect::<String>().to_uppercase();
+    let shape = (15..=34).contains(&iban.len())
+        && iban.chars().all(|c| c.is_ascii_alphanumeric())
+        && iban[..2].chars().all(|c| c.is_ascii_alphabetic())
+        && iban[2..4].chars().all(|c| c.is_ascii_digit());
+    if !shape {
+        return false;
+    }
+    // Move the first four characters to the end and read letters as 10..35
+    let remainder = iban[4..].chars().chain(iban[..4].chars()).fold(0u32, |acc, c| {
+        let value = c.to_digit(36).unwrap();
+        if value < 10 { (acc * 10 + value) % 97 } else { (acc * 100 + value) % 97 }
+    });
+    remainder == 1
+}
+
+/// ISO 9362: bank code, country, location and an optional branch.
+pub fn is_bic(value: &str) -> bool {
+    let bic = value.trim().to_uppercase();
+    bic.is_ascii()
+        && (bic.len() == 8 || bic.len() == 11)
+        && bic[..6].chars().all(|c| c.is_ascii_alphabetic())
+        && bic[6..].chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn emails() {
+        assert!(is_email("nova@example.com"));
+        assert!(is_email("first.last+tag@mail.example.co.uk"));
+        for email in ["nova", "nova@", "@example.com", "nova@example", "nova@exa mple.com", "nova@example..com"] {
+            assert!(!is_email(email), "{}", email);
+        }
+    }
+
+    #[test]
+    fn phones_normalise_to_e164() {
+        assert_eq!(normalize_phone("+1 (415) 555-0123").as_deref(), Some("+14155550123"));
+        assert_eq!(normalize_phone("0044 20 7946 0958").as_deref(), Some("+442079460958"));
+        assert_eq!(normalize_phone("415 555 0123"), None);
+        assert_eq!(normalize_phone("+0123456789"), None);
+        assert_eq!(normalize_phone("+1234567890123456"), None);
+    }
+
+    #[test]
+    fn ibans_and_bics() {
+        assert!(is_iban("DE89 3704 0044 0532 0130 00"));
+        assert!(is_iban("gb82west12345698765432"));
+        assert!(!is_iban("DE89 3704 0044 0532 0130 01"));
+        assert!(!is_iban("DE89"));
+        assert!(is_bic("DEUTDEFF"));
+        assert!(is_bic("deutdeff500"));
+        assert!(!is_bic("DEUT1EFF"));
+        assert!(!is_bic("DEUTDEF"));
+    }
+
+    #[test]
+    fn reports_every_problem() {
+        let mut v = Validator::new();
+        v.required("name", " ", MAX_NAME);
+        v.max_length("notes", Some(&"x".repeat(MAX_NOTES + 1)), MAX_NOTES);
+        let issued = NaiveDate::from_ymd_opt(2024, 3, 31);
+        let due = NaiveDate::from_ymd_opt(2024, 3, 1);
+        v.not_before("due_date", due, "issue_date", issued);
+        v.not_before("end_date", due, "start_date", None);
+        v.non_negative("amount", -1.0);
+        v.email("email", Some("   "));
+        let errors = v.into_errors();
+        assert_eq!(errors.len(), 4, "{:?}", errors);
+        assert!(errors[2].starts_with("due_date 2024-03-01 is before"), "{:?}", errors);
+    }
+}
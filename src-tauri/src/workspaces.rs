@@ -0,0 +1,243 @@
+// src-tauri/src/workspaces.rs
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::database::{self, Database};
+use crate::settings;
+
+// The database at the original location, which always exists
+pub const DEFAULT_WORKSPACE_ID: &str = "default";
+const DEFAULT_WORKSPACE_NAME: &str = "Default";
+
+/// A named database file. Workspaces can live anywhere, e.g. a synced folder.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Workspace {
+    pub id: String,
+    pub name: String,
+    pub path: String,
+    pub created_at: String,
+}
+
+fn default_workspace() -> Workspace {
+    Workspace {
+        id: DEFAULT_WORKSPACE_ID.to_string(),
+        name: DEFAULT_WORKSPACE_NAME.to_string(),
+        path: database::default_db_path().to_string_lossy().to_string(),
+        created_at: String::new(),
+    }
+}
+
+/// Every workspace, the default one first. The default workspace is only
+/// stored in the settings once it has been renamed.
+pub fn list_workspaces() -> Vec<Workspace> {
+    let mut workspaces = settings::load().workspaces;
+    match workspaces.iter_mut().find(|w| w.id == DEFAULT_WORKSPACE_ID) {
+        // Follows the data folder rather than wherever it was when renamed
+        Some(default) => default.path = default_workspace().path,
+        None => workspaces.insert(0, default_workspace()),
+    }
+    workspaces
+}
+
+/// The workspace whose database is open.
+pub fn current_workspace(db: &Database) -> Workspace {
+    let path = db.path();
+    list_workspaces()
+        .into_iter()
+        .find(|w| path.as_deref() == Some(Path::new(&w.path)))
+        .unwrap_or_else(default_workspace)
+}
+
+/// Creates a workspace with an empty database in `folder`, or in the app's
+/// workspaces folder when none is given, and switches to it.
+pub fn create_workspace(db: &Database, name: &str, folder: Option<&str>) -> Result<Workspace, String> {
+    let name = check_name(name, None)?;
+    let folder = match folder.map(str::trim).filter(|f| !f.is_empty()) {
+        Some(folder) => PathBuf::from(folder),
+        None => database::get_data_dir().join("workspaces"),
+    };
+
+    let stem = file_stem(&name);
+    let mut path = folder.join(format!("{}.db", stem));
+    let mut counter = 2;
+    while path.exists() {
+        path = folder.join(format!("{}-{}.db", stem, counter));
+        counter += 1;
+    }
+    add_and_switch(db, name, path)
+}
+
+/// Adds an existing database file as a workspace and switches to it. Files
+/// that are already a workspace are simply switched to.
+pub fn open_workspace(db: &Database, path: &str, name: Option<&str>) -> Result<Workspace, String> {
+    let path = PathBuf::from(path);
+    if !path.is_file() {
+        return Err(format!("{} doesn't exist", path.display()));
+    }
+    if let Some(existing) = list_workspaces().into_iter().find(|w| Path::new(&w.path) == path) {
+        return switch_workspace(db, &existing.id);
+    }
+
+    let name = match name.map(str::trim).filter(|n| !n.is_empty()) {
+        Some(name) => name.to_string(),
+        None => path.file_stem().map_or("Workspace".to_string(), |s| s.to_string_lossy().to_string()),
+    };
+    let name = check_name(&name, None)?;
+    add_and_switch(db, name, path)
+}
+
+fn add_and_switch(db: &Database, name: String, path: PathBuf) -> Result<Workspace, String> {
+    db.switch_to(&path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+
+    let workspace = Workspace {
+        id: uuid::Uuid::new_v4().to_string(),
+        name,
+        path: path.to_string_lossy().to_string(),
+        created_at: chrono::Utc::now().to_rfc3339(),
+    };
+    let mut app_settings = settings::load();
+    app_settings.workspaces.push(workspace.clone());
+    app_settings.last_workspace_id = Some(workspace.id.clone());
+    settings::save(&app_settings)?;
+    Ok(workspace)
+}
+
+/// Opens the workspace's database in place of the current one. The current
+/// database stays open when the new one can't be opened.
+pub fn switch_workspace(db: &Database, id: &str) -> Result<Workspace, String> {
+    let workspace = find(id)?;
+    check_exists(&workspace)
+        .and_then(|_| db.switch_to(Path::new(&workspace.path)).map_err(|e| e.to_string()))
+        .map_err(|e| format!("Failed to open workspace '{}': {}", workspace.name, e))?;
+
+    let mut app_settings = settings::load();
+    app_settings.last_workspace_id = Some(workspace.id.clone());
+    settings::save(&app_settings)?;
+    Ok(workspace)
+}
+
+/// Renames a workspace. The database file keeps its name.
+pub fn rename_workspace(id: &str, name: &str) -> Result<Workspace, String> {
+    let mut workspace = find(id)?;
+    workspace.name = check_name(name, Some(id))?;
+
+    let mut app_settings = settings::load();
+    match app_settings.workspaces.iter_mut().find(|w| w.id == id) {
+        Some(stored) => stored.name = workspace.name.clone(),
+        None => app_settings.workspaces.insert(0, workspace.clone()),
+    }
+    settings::save(&app_settings)?;
+    Ok(workspace)
+}
+
+/// Opens the workspace used last, falling back to the default database when
+/// it's gone, e.g. on a synced folder that isn't mounted. When neither opens
+/// the returned handle is unavailable and carries the reason for the UI.
+pub fn open_last_used() -> Database {
+    let last = settings::load().last_workspace_id.and_then(|id| find(&id).ok());
+    if let Some(workspace) = last.filter(|w| w.id != DEFAULT_WORKSPACE_ID) {
+        match open_database(&workspace) {
+            Ok(db) => return db,
+            Err(e) => eprintln!("Failed to open workspace '{}', using the default: {}", workspace.name, e),
+        }
+    }
+    let path = database::default_db_path();
+    Database::open(&path).unwrap_or_else(|e| {
+        Database::unavailable(format!("Failed to open {}: {}", path.display(), e))
+    })
+}
+
+fn open_database(workspace: &Workspace) -> Result<Database, String> {
+    check_exists(workspace)?;
+    Database::open(Path::new(&workspace.path)).map_err(|e| e.to_string())
+}
+
+fn check_exists(workspace: &Workspace) -> Result<(), String> {
+    let path = Path::new(&workspace.path);
+    // Only the default database is created on demand; a missing workspace
+    // file usually means its folder isn't available right now
+    if workspace.id != DEFAULT_WORKSPACE_ID && !path.is_file() {
+        return Err(format!("{} doesn't exist", path.display()));
+    }
+    Ok(())
+}
+
+fn find(id: &str) -> Result<Workspace, String> {
+    list_workspaces()
+        .into_iter()
+        .find(|w| w.id == id)
+        .ok_or_else(|| format!("Workspace '{}' not found", id))
+}
+
+fn check_name(name: &str, renaming: Option<&str>) -> Result<String, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Workspace name is required".to_string());
+    }
+    let taken = list_workspaces()
+        .iter()
+        .any(|w| Some(w.id.as_str()) != renaming && w.name.eq_ignore_ascii_case(name));
+    if taken {
+        return Err(format!("A workspace named '{}' already exists", name));
+    }
+    Ok(name.to_string())
+}
+
+/// A file name for the workspace's database, e.g. "Acme Ltd" -> "acme-ltd".
+fn file_stem(name: &str) -> String {
+    let stem: String = name
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c.to_ascii_lowercase() } else { '-' })
+        .collect();
+    let stem = stem.split('-').filter(|part| !part.is_empty()).collect::<Vec<_>>().join("-");
+    if stem.is_empty() { "workspace".to_string() } else { stem }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{NewArtist, TEST_DATA_DIR};
+
+    fn artist_names(db: &Database) -> Vec<String> {
+        db.get_all_artists().unwrap().into_iter().map(|a| a.name).collect()
+    }
+
+    fn add_artist(db: &Database, name: &str) {
+        db.create_artist(NewArtist { name: name.to_string(), ..Default::default() }).unwrap();
+    }
+
+    #[test]
+    fn switching_workspaces_swaps_the_open_database() {
+        let dir = std::env::temp_dir().join(format!("project-invoicer-workspaces-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        TEST_DATA_DIR.with(|data_dir| *data_dir.borrow_mut() = Some(dir.clone()));
+
+        let db = open_last_used();
+        add_artist(&db, "Nova");
+        let acme = create_workspace(&db, "Acme Ltd", None).unwrap();
+        assert_eq!(Path::new(&acme.path), dir.join("workspaces").join("acme-ltd.db"));
+        assert!(artist_names(&db).is_empty());
+        add_artist(&db, "Echo");
+
+        switch_workspace(&db, DEFAULT_WORKSPACE_ID).unwrap();
+        assert_eq!(artist_names(&db), ["Nova"]);
+        assert_eq!(current_workspace(&db).id, DEFAULT_WORKSPACE_ID);
+
+        switch_workspace(&db, &acme.id).unwrap();
+        assert_eq!(artist_names(&db), ["Echo"]);
+        assert_eq!(current_workspace(&db).id, acme.id);
+        // The last workspace used is the one opened at startup
+        assert_eq!(artist_names(&open_last_used()), ["Echo"]);
+
+        // A workspace whose file has gone leaves the current database open
+        let gone = create_workspace(&db, "Gone", None).unwrap();
+        switch_workspace(&db, &acme.id).unwrap();
+        std::fs::remove_file(&gone.path).unwrap();
+        assert!(switch_workspace(&db, &gone.id).is_err());
+        assert_eq!(artist_names(&db), ["Echo"]);
+        assert_eq!(settings::load().last_workspace_id, Some(acme.id));
+
+        drop(db);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
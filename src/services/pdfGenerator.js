@@ -175,9 +175,16 @@ export async function generateInvoicePDF(invoiceData) {
   return publicUrl
 }
 
+// Dates are stored as YYYY-MM-DD calendar days. new Date() would read them as
+// UTC midnight and print the previous day west of Greenwich.
+function parseDate(dateStr) {
+  const [year, month, day] = dateStr.slice(0, 10).split('-').map(Number)
+  return new Date(year, month - 1, day)
+}
+
 function formatDate(dateStr) {
   if (!dateStr) return ''
-  const date = new Date(dateStr)
+  const date = parseDate(dateStr)
   return date.toLocaleDateString('en-US', {
     year: 'numeric',
     month: 'long',
@@ -190,10 +197,10 @@ function getDueText(invoice) {
     return 'Upon Receipt'
   }
   
-  const issue = new Date(invoice.issue_date)
-  const due = new Date(invoice.due_date)
+  const issue = parseDate(invoice.issue_date)
+  const due = parseDate(invoice.due_date)
   const diffTime = Math.abs(due - issue)
-  const diffDays = Math.ceil(diffTime / (1000 * 60 * 60 * 24))
+  const diffDays = Math.round(diffTime / (1000 * 60 * 60 * 24))
   
   if (diffDays === 30) {
     return 'Net 30'
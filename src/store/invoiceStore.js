@@ -3,6 +3,10 @@ import { defineStore } from 'pinia'
 import { supabase } from '@/lib/supabase'
 import { monitoredQuery } from '@/utils/performanceMonitor'
 
+// Statuses of invoices still waiting for payment
+const OUTSTANDING_STATUSES = ['pending', 'sent', 'overdue']
+const isOutstanding = (invoice) => OUTSTANDING_STATUSES.includes(invoice.status)
+
 export const useInvoiceStore = defineStore('invoices', {
   state: () => ({
     invoices: [],
@@ -57,14 +61,14 @@ export const useInvoiceStore = defineStore('invoices', {
     },
     
     pendingInvoices: (state) => {
-      return state.invoices.filter(i => i.status === 'pending')
+      return state.invoices.filter(isOutstanding)
     },
     
     overDueInvoices: (state) => {
       const today = new Date()
       return state.invoices.filter(i => 
-        i.status === 'pending' && 
-        new Date(i.due_date) < today
+        isOutstanding(i) && 
+        (i.status === 'overdue' || new Date(i.due_date) < today)
       )
     },
     
@@ -86,7 +90,7 @@ export const useInvoiceStore = defineStore('invoices', {
     
     outstandingAmount: (state) => {
       return state.invoices
-        .filter(i => i.status === 'pending')
+        .filter(isOutstanding)
         .reduce((total, invoice) => total + parseFloat(invoice.amount), 0)
     },
     
@@ -102,7 +106,7 @@ export const useInvoiceStore = defineStore('invoices', {
     
     totalPending: (state) => {
       return state.invoices
-        .filter(i => i.status === 'pending')
+        .filter(isOutstanding)
         .reduce((sum, i) => sum + parseFloat(i.amount || 0), 0)
     },
     